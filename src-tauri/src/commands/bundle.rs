@@ -0,0 +1,131 @@
+use age::secrecy::SecretString;
+use serde::Deserialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+
+/// An export option: also pack the written archive into one passphrase-encrypted file
+/// for backups. The file is a ZIP encrypted with age, so it can be opened without the app
+/// (`age -d "ChatGPT History.zip.age" > "ChatGPT History.zip"`).
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BundleOptions {
+    pub passphrase: String,
+    /// Where to write the bundle; `None` puts `ChatGPT History.zip.age` next to the archive folder.
+    pub path: Option<String>,
+}
+
+impl BundleOptions {
+    pub fn destination(&self, root: &Path) -> PathBuf {
+        match &self.path {
+            Some(path) => PathBuf::from(path),
+            None => root.with_file_name(format!(
+                "{}.zip.age",
+                root.file_name().map(|n| n.to_string_lossy()).unwrap_or_default()
+            )),
+        }
+    }
+}
+
+/// Zips everything under `root` and encrypts it with `passphrase` into `dest`.
+/// Returns the number of files packed.
+///
+/// The ZIP is built in a temporary file beside `dest` (the ZIP writer needs to seek), which
+/// holds nothing the plaintext archive folder doesn't, and is removed afterwards.
+pub fn write_encrypted_bundle(root: &Path, dest: &Path, passphrase: &str) -> Result<usize, String> {
+    if passphrase.trim().is_empty() {
+        return Err("The backup bundle needs a passphrase".to_string());
+    }
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let partial = dest.with_extension("partial");
+    let result = zip_folder(root, &partial).and_then(|files| {
+        encrypt_file(&partial, dest, passphrase)?;
+        Ok(files)
+    });
+    let _ = std::fs::remove_file(&partial);
+    result
+}
+
+fn zip_folder(root: &Path, dest: &Path) -> Result<usize, String> {
+    let file = std::fs::File::create(dest).map_err(|e| e.to_string())?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = SimpleFileOptions::default().large_file(true);
+    let mut files = 0;
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries: Vec<_> = std::fs::read_dir(&dir)
+            .map_err(|e| e.to_string())?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .collect();
+        entries.sort();
+        for path in entries {
+            if path.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            let name = path
+                .strip_prefix(root)
+                .map_err(|e| e.to_string())?
+                .to_string_lossy()
+                .replace('\\', "/");
+            zip.start_file(name, options).map_err(|e| e.to_string())?;
+            let mut input = std::fs::File::open(&path).map_err(|e| e.to_string())?;
+            std::io::copy(&mut input, &mut zip).map_err(|e| e.to_string())?;
+            files += 1;
+        }
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(files)
+}
+
+fn encrypt_file(src: &Path, dest: &Path, passphrase: &str) -> Result<(), String> {
+    let encryptor = age::Encryptor::with_user_passphrase(SecretString::from(passphrase.to_string()));
+    let mut input = std::fs::File::open(src).map_err(|e| e.to_string())?;
+    let output = std::fs::File::create(dest).map_err(|e| e.to_string())?;
+    let mut writer = encryptor.wrap_output(output).map_err(|e| e.to_string())?;
+    std::io::copy(&mut input, &mut writer).map_err(|e| e.to_string())?;
+    writer.finish().and_then(|mut f| f.flush()).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Seek};
+
+    /// Decrypts a bundle written by `write_encrypted_bundle` back into a seekable ZIP reader.
+    fn open_encrypted_bundle(path: &Path, passphrase: &str) -> Result<zip::ZipArchive<impl Read + Seek>, String> {
+        let identity = age::scrypt::Identity::new(SecretString::from(passphrase.to_string()));
+        let input = std::io::BufReader::new(std::fs::File::open(path).map_err(|e| e.to_string())?);
+        let decryptor = age::Decryptor::new_buffered(input).map_err(|e| e.to_string())?;
+        let mut reader = decryptor
+            .decrypt(std::iter::once(&identity as &dyn age::Identity))
+            .map_err(|e| e.to_string())?;
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+        zip::ZipArchive::new(std::io::Cursor::new(bytes)).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn bundle_round_trips_with_the_passphrase() {
+        let dir = std::env::temp_dir().join(format!("bundle-test-{}", std::process::id()));
+        let root = dir.join("ChatGPT History");
+        std::fs::create_dir_all(root.join("2024")).unwrap();
+        std::fs::write(root.join("INDEX.md"), "# Index").unwrap();
+        std::fs::write(root.join("2024/trip.md"), "# Trip").unwrap();
+
+        let dest = BundleOptions::default().destination(&root);
+        assert_eq!(dest, dir.join("ChatGPT History.zip.age"));
+        assert_eq!(write_encrypted_bundle(&root, &dest, "correct horse").unwrap(), 2);
+        assert!(!std::fs::read(&dest).unwrap().windows(5).any(|w| w == b"# Tri"));
+
+        let mut zip = open_encrypted_bundle(&dest, "correct horse").unwrap();
+        let mut text = String::new();
+        zip.by_name("2024/trip.md").unwrap().read_to_string(&mut text).unwrap();
+        assert_eq!(text, "# Trip");
+        assert!(open_encrypted_bundle(&dest, "wrong").is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
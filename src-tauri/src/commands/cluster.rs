@@ -4,6 +4,8 @@ use tauri::State;
 use tauri::ipc::Channel;
 use std::time::Duration;
 use crate::AppState;
+use crate::commands;
+use crate::redact::RedactionPolicy;
 use crate::store::db;
 use crate::ai::{batch, prompts};
 
@@ -48,7 +50,7 @@ pub async fn estimate_cost(state: State<'_, AppState>) -> Result<CostEstimate, S
     //    Local estimates are accurate enough for a cost preview.
     let conversations = {
         let conn = state.db.lock().map_err(|e| e.to_string())?;
-        db::get_all_conversations(&conn).map_err(|e| e.to_string())?
+        db::get_all_conversations(&conn, db::ConversationSort::Created).map_err(|e| e.to_string())?
     };
 
     let conversation_count = conversations.len() as u64;
@@ -66,9 +68,12 @@ pub async fn estimate_cost(state: State<'_, AppState>) -> Result<CostEstimate, S
     Ok(CostEstimate { input_tokens, estimated_usd })
 }
 
+/// Clusters every conversation with the Anthropic batch API. With `redaction`, titles and
+/// transcripts are masked before anything is sent.
 #[tauri::command]
 pub async fn start_clustering(
     state: State<'_, AppState>,
+    redaction: Option<RedactionPolicy>,
     on_event: Channel<ClusterEvent>,
 ) -> Result<(), String> {
     // 1. Get API key from Keychain
@@ -76,15 +81,25 @@ pub async fn start_clustering(
     let api_key = entry.get_password().map_err(|e| e.to_string())?;
 
     // 2. Load all conversations from SQLite
-    let conversations = {
+    let mut conversations = {
         let conn = state.db.lock().map_err(|e| e.to_string())?;
-        db::get_all_conversations(&conn).map_err(|e| e.to_string())?
+        db::get_all_conversations(&conn, db::ConversationSort::Created).map_err(|e| e.to_string())?
     };
 
     if conversations.is_empty() {
         return Err("No conversations found in database".to_string());
     }
 
+    // Mask secrets and personal data before they leave the machine
+    let redactor = commands::redaction::redactor(&state, &redaction.unwrap_or_default())?;
+    if redactor.is_active() {
+        for c in &mut conversations {
+            c.title = redactor.redact_opt(c.title.as_deref());
+            c.full_text = redactor.redact(&c.full_text);
+        }
+    }
+    commands::redaction::save_tokens(&state, &redactor)?;
+
     let client = reqwest::Client::new();
 
     // 3. Pass 1: build titles+snippets sample and discover cluster vocabulary
@@ -0,0 +1,30 @@
+use crate::commands::keychain;
+use crate::store::encryption;
+use crate::AppState;
+use tauri::State;
+
+/// Whether conversations.db is encrypted at rest.
+#[tauri::command]
+pub async fn get_database_encryption(state: State<'_, AppState>) -> Result<bool, String> {
+    Ok(!encryption::is_plaintext(&state.db_path))
+}
+
+/// Encrypts conversations.db with a new key kept in the OS keychain, or decrypts it and
+/// deletes the key. Media in the app's media store is not affected.
+#[tauri::command]
+pub async fn set_database_encryption(state: State<'_, AppState>, enabled: bool) -> Result<(), String> {
+    let mut conn = state.db.lock().map_err(|e| e.to_string())?;
+    let current = keychain::get_database_key()?;
+    if enabled == current.is_some() && enabled != encryption::is_plaintext(&state.db_path) {
+        return Ok(());
+    }
+    if enabled {
+        let key = current.unwrap_or_else(encryption::new_key);
+        // Saved first: if the app stops before the file is rewritten, startup finishes the job
+        keychain::set_database_key(&key)?;
+        encryption::rekey(&mut conn, &state.db_path, None, Some(&key))
+    } else {
+        encryption::rekey(&mut conn, &state.db_path, current.as_deref(), None)?;
+        keychain::delete_database_key()
+    }
+}
@@ -0,0 +1,51 @@
+use crate::commands::ingest::unix_now;
+use crate::store::db;
+use crate::store::exclusions::ExclusionRule;
+use crate::AppState;
+use tauri::State;
+
+/// Every saved exclusion rule, oldest first.
+#[tauri::command]
+pub async fn get_exclusion_rules(state: State<'_, AppState>) -> Result<Vec<db::ExclusionRuleRow>, String> {
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    db::get_exclusion_rules(&conn).map_err(|e| e.to_string())
+}
+
+/// Saves a rule and applies it to the archive. Returns how many conversations are now excluded.
+#[tauri::command]
+pub async fn add_exclusion_rule(state: State<'_, AppState>, rule: ExclusionRule) -> Result<usize, String> {
+    rule.validate()?;
+    let mut conn = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    db::insert_exclusion_rule(&tx, &rule, unix_now()).map_err(|e| e.to_string())?;
+    let excluded = db::update_exclusions(&tx).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(excluded)
+}
+
+/// Deletes a rule; conversations only it excluded are included again.
+#[tauri::command]
+pub async fn delete_exclusion_rule(state: State<'_, AppState>, rule_id: i64) -> Result<usize, String> {
+    let mut conn = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    db::delete_exclusion_rule(&tx, rule_id).map_err(|e| e.to_string())?;
+    let excluded = db::update_exclusions(&tx).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(excluded)
+}
+
+/// Marks a conversation private (never exported or sent to the API), or clears the mark.
+/// The mark outlives re-imports of the conversation.
+#[tauri::command]
+pub async fn set_conversation_private(
+    state: State<'_, AppState>,
+    conversation_id: String,
+    private: bool,
+) -> Result<usize, String> {
+    let mut conn = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    db::set_private(&tx, &conversation_id, private).map_err(|e| e.to_string())?;
+    let excluded = db::update_exclusions(&tx).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(excluded)
+}
@@ -1,9 +1,144 @@
+use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use tauri::State;
 use crate::AppState;
+use crate::commands::bundle::{self, BundleOptions};
+use crate::commands::redaction;
+use crate::commands::sources::{self, MissingSource};
+use crate::commands::{obsidian, profile};
+use crate::dates::{self, Zone};
+use crate::pipeline::metadata::ToolCategory;
+use crate::pipeline::normalizer::ConversationKind;
+use crate::pipeline::source::{sha256_hex, ExportSource};
+use crate::pipeline::traversal::{InclusionPolicy, MessageKind};
+use crate::redact::{RedactionPolicy, Redactor};
 use crate::store::db;
+use crate::store::media_store::{self, MediaKind, MediaStore};
+
+/// Options chosen in the UI for one export run.
+/// Every field has a default so the frontend may omit the whole object.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportOptions {
+    /// IANA zone name or "UTC" for folder years and timestamps; `None` uses the OS zone.
+    pub time_zone: Option<String>,
+    /// Which date decides a conversation's year folder and INDEX.md section.
+    pub bucket_by: DateBucket,
+    /// Overall shape of the written archive.
+    pub profile: ExportProfile,
+    /// How conversation files are grouped into folders.
+    pub layout: FolderLayout,
+    /// Which roles and how much tool activity transcripts show.
+    pub include: InclusionPolicy,
+    /// Only export conversations from this account's exports (email, or name when the
+    /// export had no email — see `get_import_sources`). `None` exports every account.
+    pub account: Option<String>,
+    /// Secrets and personal data to mask in everything written.
+    pub redaction: RedactionPolicy,
+    /// Also pack the archive into an encrypted backup file.
+    pub bundle: Option<BundleOptions>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FolderLayout {
+    /// `2024/` — one folder per year (see `DateBucket`).
+    #[default]
+    Year,
+    /// `Cluster Label/` — one folder per AI cluster, with a README for a Claude Project.
+    /// Conversations assigned to a project go in the project's folder instead.
+    Cluster,
+    /// `GPT g-abc123/` — one folder per custom GPT / ChatGPT project gizmo.
+    Gizmo,
+    /// `Cluster Label/2024/` — clusters split by year.
+    ClusterYear,
+}
+
+/// Folder for conversations that clustering has not labelled (yet).
+const UNCLUSTERED_FOLDER: &str = "Unclustered";
+/// Folder for conversations made without a custom GPT, in the gizmo layout.
+const NO_GIZMO_FOLDER: &str = "No custom GPT";
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportProfile {
+    /// Plain markdown files in year folders plus INDEX.md — tuned for Claude via MCP.
+    #[default]
+    Standard,
+    /// An Obsidian vault: stable note names, Map-of-Content pages, wikilinks and embeds.
+    Obsidian,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DateBucket {
+    /// Year the conversation was started.
+    #[default]
+    Created,
+    /// Year the conversation was last worked on — keeps long-running threads with current work.
+    LastActive,
+}
+
+impl FolderLayout {
+    /// Folder of `conv` relative to the export root.
+    pub(crate) fn folder_for(self, conv: &db::ExportRow, zone: Zone, bucket_by: DateBucket) -> PathBuf {
+        let year = || {
+            bucket_by.timestamp(conv)
+                .map(|ts| dates::year(ts, zone).to_string())
+                .unwrap_or_else(|| "Unknown".to_string())
+        };
+        match self {
+            FolderLayout::Year => PathBuf::from(year()),
+            FolderLayout::Cluster => PathBuf::from(cluster_folder(conv)),
+            FolderLayout::Gizmo => PathBuf::from(
+                conv.gizmo_id.as_deref()
+                    .map(|g| folder_name(&format!("GPT {g}")))
+                    .unwrap_or_else(|| NO_GIZMO_FOLDER.to_string()),
+            ),
+            FolderLayout::ClusterYear => PathBuf::from(cluster_folder(conv)).join(year()),
+        }
+    }
+
+    fn groups_by_cluster(self) -> bool {
+        matches!(self, FolderLayout::Cluster | FolderLayout::ClusterYear)
+    }
+
+    /// Tree line shown in START_HERE.md.
+    fn describe(self) -> &'static str {
+        match self {
+            FolderLayout::Year => "  2023/ 2024/ 2025/   ← conversations by year, one .md file each\n",
+            FolderLayout::Cluster => "  [Cluster]/          ← conversations by project or topic cluster, with a README.md each\n",
+            FolderLayout::Gizmo => "  GPT [id]/           ← conversations by custom GPT used\n",
+            FolderLayout::ClusterYear => "  [Cluster]/[Year]/   ← conversations by project or topic cluster, then year; README.md per cluster\n",
+        }
+    }
+}
+
+/// Folder of a conversation in the cluster layouts: its project's, or else its AI cluster's.
+fn cluster_folder(conv: &db::ExportRow) -> String {
+    conv.group()
+        .map(|group| folder_name(&group.folder_label()))
+        .unwrap_or_else(|| UNCLUSTERED_FOLDER.to_string())
+}
+
+impl DateBucket {
+    pub(crate) fn timestamp(self, conv: &db::ExportRow) -> Option<i64> {
+        match self {
+            DateBucket::Created => conv.created_at,
+            DateBucket::LastActive => conv.last_active_at,
+        }
+    }
+
+    fn sort(self) -> db::ConversationSort {
+        match self {
+            DateBucket::Created => db::ConversationSort::Created,
+            DateBucket::LastActive => db::ConversationSort::LastActive,
+        }
+    }
+}
 
 #[derive(Serialize)]
 pub struct ExportResult {
@@ -11,40 +146,97 @@ pub struct ExportResult {
     pub folder_path: String,
     pub mcp_configured: bool,
     pub media_extracted: usize,
+    /// Imported exports that could not be found or no longer match their checksum, and have
+    /// no media in the store. Their media is missing from this export.
+    pub missing_sources: Vec<MissingSource>,
+    /// The encrypted backup written with `ExportOptions::bundle`.
+    pub bundle_path: Option<String>,
 }
 
 /// Exports all conversations as markdown files to ~/Documents/ChatGPT History/
-/// organized into year-based folders, then extracts all media files, group chats,
-/// and supplementary data from the original ZIP, auto-configures the Claude Desktop
+/// organized into year-based folders, then writes the export's media (from the media store,
+/// or from the original export when its media wasn't stored) and supplementary data from the
+/// original export (ZIP or folder), auto-configures the Claude Desktop
 /// MCP filesystem server, and generates a START_HERE.md with instructions for Claude.
 ///
+/// With `ExportProfile::Obsidian` the conversation notes, Map-of-Content pages and
+/// links are written by `obsidian::write_vault` instead; everything else is shared.
+///
 /// Structure:
 ///   ~/Documents/ChatGPT History/
 ///     START_HERE.md      ← instructions + context for Claude
 ///     2023/ 2024/ 2025/  ← conversations by year
-///     group-chats/       ← group_chats.json exported as markdown
-///     media/             ← all images from the ZIP
+///     attachments/       ← files uploaded in conversations
+///     audio/             ← voice conversation recordings
+///     generated/         ← DALL-E images, code interpreter output
 ///     data/              ← shared_conversations.json
+///   ~/Documents/ChatGPT History.zip.age  ← encrypted backup, with `ExportOptions::bundle`
 #[tauri::command]
 pub async fn export_conversations(
     state: State<'_, AppState>,
+    options: Option<ExportOptions>,
 ) -> Result<ExportResult, String> {
-    let conversations = {
+    let options = options.unwrap_or_default();
+    let zone = Zone::parse(options.time_zone.as_deref())?;
+
+    let (mut conversations, import_sources) = {
         let conn = state.db.lock().map_err(|e| e.to_string())?;
-        db::get_conversations_for_export(&conn).map_err(|e| e.to_string())?
+        (
+            db::get_conversations_for_export(&conn, options.bucket_by.sort()).map_err(|e| e.to_string())?,
+            db::get_import_sources(&conn).map_err(|e| e.to_string())?,
+        )
     };
+    if let Some(ref account) = options.account {
+        conversations.retain(|c| c.accounts.contains(account));
+    }
 
     if conversations.is_empty() {
-        return Err("No conversations found in database".to_string());
+        return Err(match options.account {
+            Some(ref account) => format!("No conversations found for {account}"),
+            None => "No conversations found in database".to_string(),
+        });
     }
 
-    let zip_path: Option<String> = {
-        let zp = state.zip_path.lock().map_err(|e| e.to_string())?;
-        zp.clone()
-    };
+    let redactor = redaction::redactor(&state, &options.redaction)?;
+    if redactor.is_active() {
+        for conv in &mut conversations {
+            redaction::redact_conversation(&redactor, conv);
+        }
+    }
 
-    let home = std::env::var("HOME").map_err(|_| "Cannot determine home directory".to_string())?;
-    let root = PathBuf::from(&home).join("Documents").join("ChatGPT History");
+    // Each export is re-checked against its checksum; one that moved or changed falls back to
+    // media copied into the store, or is reported so the user can locate it. Media is read
+    // from the store when the user stored it, otherwise from the export itself — exporting
+    // never adds to the store
+    let mut available: Vec<ExportSource> = Vec::new();
+    let mut media_sources: Vec<i64> = Vec::new();
+    let mut unstored: Vec<usize> = Vec::new();
+    let mut missing_sources: Vec<MissingSource> = Vec::new();
+    for row in import_sources
+        .iter()
+        .filter(|s| options.account.is_none() || account_label(s) == options.account.as_deref())
+    {
+        match sources::open_verified(row) {
+            Ok(source) => {
+                if row.media_stored > 0 {
+                    media_sources.push(row.id);
+                } else {
+                    unstored.push(available.len());
+                }
+                available.push(source);
+            }
+            Err(_) if row.media_stored > 0 => media_sources.push(row.id),
+            Err(reason) => missing_sources.push(MissingSource {
+                id: row.id,
+                path: row.path.clone(),
+                account: account_label(row).map(String::from),
+                reason,
+            }),
+        }
+    }
+
+    let home = home_dir()?;
+    let root = archive_root()?;
     std::fs::create_dir_all(&root).map_err(|e| e.to_string())?;
 
     // Remove legacy Projects/ folder if present from an older export
@@ -52,66 +244,81 @@ pub async fn export_conversations(
     if projects_dir.exists() {
         let _ = std::fs::remove_dir_all(&projects_dir);
     }
+    // Likewise media/, replaced by the typed folders below
+    let _ = std::fs::remove_dir_all(root.join("media"));
 
-    // Write conversations into year-based folders
-    let mut files_written = 0;
-    let mut seen: HashMap<String, usize> = HashMap::new();
-
-    for conv in &conversations {
-        let year = conv.created_at
-            .map(|ts| unix_to_year(ts))
-            .unwrap_or_else(|| "Unknown".to_string());
-
-        let subfolder = root.join(&year);
-        std::fs::create_dir_all(&subfolder).map_err(|e| e.to_string())?;
-
-        let title = conv.title.as_deref().unwrap_or("Untitled");
-        let base_slug = slugify(title);
-        let key = format!("{}/{}", subfolder.to_string_lossy(), base_slug);
-        let count = seen.entry(key).or_insert(0);
-        let file_name = if *count == 0 {
-            format!("{}.md", base_slug)
+    // Media goes in first, so notes can link it
+    let (mut media_files, refs) = {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        let mut files: Vec<(MediaOrigin, db::StoredMediaRow)> = Vec::new();
+        for source_id in &media_sources {
+            let stored = db::get_stored_media(&conn, *source_id).map_err(|e| e.to_string())?;
+            files.extend(stored.into_iter().map(|file| (MediaOrigin::Store, file)));
+        }
+        let refs = if unstored.is_empty() {
+            HashMap::new()
         } else {
-            format!("{}-{}.md", base_slug, count)
+            db::get_media_file_refs(&conn).map_err(|e| e.to_string())?
         };
-        *count += 1;
-
-        let date_line = conv.created_at
-            .map(|ts| format!("_{}_\n\n", unix_to_date_str(ts)))
-            .unwrap_or_default();
-
-        let gizmo_line = conv.gizmo_id.as_ref()
-            .map(|g| format!("<!-- gizmo:{} -->\n\n", g))
-            .unwrap_or_default();
+        (files, refs)
+    };
+    for &index in &unstored {
+        let files = scan_source_media(&available[index], &refs)?;
+        media_files.extend(files.into_iter().map(|file| (MediaOrigin::Source(index), file)));
+    }
+    let media = export_media(&state.media, &available, &media_files, &root)?;
 
-        let content = format!(
-            "# {}\n\n{}{}---\n\n{}\n",
-            title,
-            date_line,
-            gizmo_line,
-            conv.full_text.trim()
-        );
+    // Sources are newest first, so the latest export's name and shared links win
+    let mut user_name: Option<String> = None;
+    let excluded = {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        db::get_excluded_ids(&conn).map_err(|e| e.to_string())?
+    };
+    for source in available.iter().rev() {
+        // Name from user.json — used in START_HERE.md only, never written elsewhere
+        user_name = source.account().name.or(user_name);
+        copy_shared_conversations(source, &root, &excluded)?;
+    }
 
-        std::fs::write(subfolder.join(&file_name), content)
-            .map_err(|e| e.to_string())?;
+    let files_written = match options.profile {
+        ExportProfile::Standard => write_standard(&state.db, &root, &conversations, zone, &options, &media, &redactor)?,
+        ExportProfile::Obsidian => {
+            obsidian::write_vault(&state.db, &root, &conversations, zone, &options, &media, &redactor)?
+        }
+    };
 
-        files_written += 1;
+    if options.layout.groups_by_cluster() {
+        write_cluster_readmes(&root, &conversations, zone)?;
     }
 
-    // Extract all assets from the original ZIP
-    let mut media_extracted: usize = 0;
-    let mut group_chats_written: usize = 0;
-    let mut user_name: Option<String> = None;
+    // Always regenerate INDEX.md — it's a pure data file, not user-editable
+    let (model_usage, tool_usage) = {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        (
+            db::get_model_usage(&conn, options.account.as_deref()).map_err(|e| e.to_string())?,
+            db::get_tool_usage(&conn, options.account.as_deref()).map_err(|e| e.to_string())?,
+        )
+    };
+    generate_index(&root, &conversations, zone, options.bucket_by, &model_usage, &tool_usage)?;
 
-    if let Some(ref zp) = zip_path {
-        user_name = read_user_name(zp);
-        media_extracted = extract_media(zp, &root).unwrap_or(0);
-        group_chats_written = export_group_chats(zp, &root).unwrap_or(0);
-        let _ = copy_shared_conversations(zp, &root);
+    // PROFILE.md is regenerated too; it only exists when the export had custom instructions or memory
+    let mut profile_versions = {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        db::get_profile_versions(&conn).map_err(|e| e.to_string())?
+    };
+    for version in &mut profile_versions {
+        version.text = redactor.redact(&version.text);
     }
+    let has_profile = profile::write_profile(&root, &profile_versions, zone)?;
 
-    // Always regenerate INDEX.md — it's a pure data file, not user-editable
-    let _ = generate_index(&root, &conversations);
+    let mut comparisons = {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        db::get_comparisons(&conn).map_err(|e| e.to_string())?
+    };
+    for comparison in &mut comparisons {
+        comparison.conversation_title = redactor.redact_opt(comparison.conversation_title.as_deref());
+    }
+    write_comparisons(&root, &comparisons, zone)?;
 
     // Generate START_HERE.md once — preserved if user edits it
     let readme_path = root.join("START_HERE.md");
@@ -119,12 +326,27 @@ pub async fn export_conversations(
         let _ = generate_readme(
             &root,
             &conversations,
-            user_name.as_deref(),
-            media_extracted > 0,
-            group_chats_written > 0,
+            zone,
+            options.layout,
+            redactor.redact_opt(user_name.as_deref()).as_deref(),
+            ArchiveExtras {
+                media: &media.folders,
+                profile: has_profile,
+            },
         );
     }
 
+    redaction::save_tokens(&state, &redactor)?;
+
+    let bundle_path = match &options.bundle {
+        Some(bundle) => {
+            let dest = bundle.destination(&root);
+            bundle::write_encrypted_bundle(&root, &dest, &bundle.passphrase)?;
+            Some(dest.to_string_lossy().to_string())
+        }
+        None => None,
+    };
+
     // Auto-configure Claude Desktop MCP filesystem server
     let export_path = root.to_string_lossy().to_string();
     let mcp_configured = update_claude_desktop_config(&home, &export_path).is_ok();
@@ -133,225 +355,664 @@ pub async fn export_conversations(
         files_written,
         folder_path: export_path,
         mcp_configured,
-        media_extracted: media_extracted + group_chats_written,
+        media_extracted: media.written,
+        missing_sources,
+        bundle_path,
     })
 }
 
-// ── Asset extraction ───────────────────────────────────────────────────────────
+fn home_dir() -> Result<String, String> {
+    std::env::var("HOME").map_err(|_| "Cannot determine home directory".to_string())
+}
 
-/// Extracts all image/media files from the ZIP into ~/Documents/ChatGPT History/media/
-fn extract_media(zip_path: &str, root: &PathBuf) -> Result<usize, String> {
-    let file = std::fs::File::open(zip_path).map_err(|e| e.to_string())?;
-    let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+/// ~/Documents/ChatGPT History — the folder every archive-writing command writes into.
+pub(crate) fn archive_root() -> Result<PathBuf, String> {
+    Ok(PathBuf::from(home_dir()?).join("Documents").join("ChatGPT History"))
+}
 
-    let media_dir = root.join("media");
-    std::fs::create_dir_all(&media_dir).map_err(|e| e.to_string())?;
+/// Hidden file in the archive root mapping conversation ids to the markdown files they were
+/// last written to, so a file is removed once its conversation is excluded or written under
+/// another name (renamed in ChatGPT, redacted title, another folder).
+const STANDARD_MANIFEST_FILE: &str = ".chatgpt-export.json";
 
-    let mut extracted = 0;
+#[derive(Default, Serialize, Deserialize)]
+struct StandardManifest {
+    /// Conversation id → file path relative to the archive root, `/`-separated.
+    files: BTreeMap<String, String>,
+}
 
-    for i in 0..archive.len() {
-        let mut entry = archive.by_index(i).map_err(|e| e.to_string())?;
+/// Writes one markdown file per conversation into the folders chosen by `options.layout`.
+/// Returns the number of files written.
+fn write_standard(
+    db: &Mutex<Connection>,
+    root: &Path,
+    conversations: &[db::ExportRow],
+    zone: Zone,
+    options: &ExportOptions,
+    media: &ExportedMedia,
+    redactor: &Redactor,
+) -> Result<usize, String> {
+    let manifest_path = root.join(STANDARD_MANIFEST_FILE);
+    let mut manifest: StandardManifest = std::fs::read(&manifest_path)
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default();
+
+    // Files from earlier exports of conversations excluded since then are removed
+    let excluded = {
+        let conn = db.lock().map_err(|e| e.to_string())?;
+        db::get_excluded_ids(&conn).map_err(|e| e.to_string())?
+    };
+    manifest.files.retain(|id, path| {
+        if excluded.contains(id) {
+            let _ = std::fs::remove_file(root.join(&*path));
+        }
+        !excluded.contains(id)
+    });
 
-        if entry.is_dir() || entry.name().contains("__MACOSX") {
-            continue;
+    // Settle every file path first, so an earlier file of a conversation is only removed
+    // when no other conversation is written there now
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut paths: Vec<(PathBuf, String)> = Vec::with_capacity(conversations.len());
+    for conv in conversations {
+        let folder = options.layout.folder_for(conv, zone, options.bucket_by);
+        let base_slug = slugify(conv.title.as_deref().unwrap_or("Untitled"));
+        let key = format!("{}/{}", folder.to_string_lossy(), base_slug);
+        let count = seen.entry(key).or_insert(0);
+        let file_name = if *count == 0 {
+            format!("{}.md", base_slug)
+        } else {
+            format!("{}-{}.md", base_slug, count)
+        };
+        *count += 1;
+        let path = folder.join(&file_name).to_string_lossy().replace('\\', "/");
+        paths.push((folder, path));
+    }
+    let current: HashSet<&str> = paths.iter().map(|(_, path)| path.as_str()).collect();
+    for (conv, (_, path)) in conversations.iter().zip(&paths) {
+        if let Some(earlier) = manifest.files.insert(conv.id.clone(), path.clone()) {
+            if earlier != *path && !current.contains(earlier.as_str()) {
+                let _ = std::fs::remove_file(root.join(earlier));
+            }
         }
+    }
 
-        let filename = std::path::Path::new(entry.name())
-            .file_name()
-            .and_then(|f| f.to_str())
-            .unwrap_or("")
-            .to_string();
+    let mut files_written = 0;
+    for (conv, (folder, path)) in conversations.iter().zip(&paths) {
+        let depth = folder.components().count();
+        std::fs::create_dir_all(root.join(folder)).map_err(|e| e.to_string())?;
+        let title = conv.title.as_deref().unwrap_or("Untitled");
 
-        if filename.is_empty() {
-            continue;
+        let (mut messages, models) = {
+            let conn = db.lock().map_err(|e| e.to_string())?;
+            (
+                db::get_messages(&conn, &conv.id).map_err(|e| e.to_string())?,
+                db::get_model_slugs(&conn, &conv.id).map_err(|e| e.to_string())?,
+            )
+        };
+        if redactor.is_active() {
+            redaction::redact_messages(redactor, &mut messages);
         }
 
-        let ext = std::path::Path::new(&filename)
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("")
-            .to_lowercase();
+        let content = format!(
+            "{}# {}\n\n{}{}---\n\n{}\n",
+            front_matter(conv, &models, zone),
+            title,
+            date_line(conv, zone),
+            share_note(conv),
+            render_transcript(&messages, &conv.full_text, zone, &options.include, &|id| media.markdown(id, depth))
+        );
 
-        match ext.as_str() {
-            "jpg" | "jpeg" | "png" | "gif" | "webp" | "svg" => {
-                let mut buf = Vec::new();
-                std::io::Read::read_to_end(&mut entry, &mut buf)
-                    .map_err(|e| e.to_string())?;
-                std::fs::write(media_dir.join(&filename), buf)
-                    .map_err(|e| e.to_string())?;
-                extracted += 1;
+        std::fs::write(root.join(path), content)
+            .map_err(|e| e.to_string())?;
+
+        files_written += 1;
+    }
+
+    let json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    std::fs::write(&manifest_path, json).map_err(|e| e.to_string())?;
+
+    Ok(files_written)
+}
+
+/// Italic line under the title with the created and last-active dates.
+pub(crate) fn date_line(conv: &db::ExportRow, zone: Zone) -> String {
+    match (conv.created_at, conv.last_active_at) {
+        (Some(created), Some(active)) => format!(
+            "_Created {} · Last active {}_\n\n",
+            dates::date_str(created, zone),
+            dates::date_str(active, zone)
+        ),
+        (Some(created), None) => format!("_{}_\n\n", dates::date_str(created, zone)),
+        _ => String::new(),
+    }
+}
+
+// ── Cluster READMEs ───────────────────────────────────────────────────────────
+
+/// Writes `README.md` into every cluster folder: the instructions clustering found across
+/// its conversations, followed by each member's summary. The file is meant to be pasted
+/// into (or uploaded to) a Claude Project as its starting context.
+fn write_cluster_readmes(root: &Path, conversations: &[db::ExportRow], zone: Zone) -> Result<(), String> {
+    let mut clusters: std::collections::BTreeMap<String, Vec<&db::ExportRow>> =
+        std::collections::BTreeMap::new();
+    for conv in conversations {
+        clusters.entry(cluster_folder(conv)).or_default().push(conv);
+    }
+
+    for (folder, members) in &clusters {
+        let label = members[0].group().map_or(UNCLUSTERED_FOLDER, |group| group.label());
+        let mut content = format!(
+            "# {}\n\n_{} conversations_\n\n\
+             > Drop this folder into a Claude Project: this README gives Claude the context,\n\
+             > the conversation files are the knowledge.\n\n",
+            label,
+            members.len()
+        );
+
+        // Distinct instructions, in first-seen order
+        let mut instructions: Vec<&str> = Vec::new();
+        for conv in members {
+            if let Some(i) = conv.instructions.as_deref().map(str::trim).filter(|i| !i.is_empty()) {
+                if !instructions.contains(&i) {
+                    instructions.push(i);
+                }
             }
-            _ => {}
         }
+        content.push_str("## Instructions\n\n");
+        if instructions.is_empty() {
+            content.push_str("_No custom instructions were found in these conversations._\n\n");
+        } else {
+            for i in &instructions {
+                content.push_str(&format!("- {}\n", i.replace('\n', " ")));
+            }
+            content.push('\n');
+        }
+
+        content.push_str("## Conversations\n\n");
+        for conv in members {
+            let title = conv.title.as_deref().unwrap_or("Untitled");
+            let date = conv.created_at.map(|t| dates::date_str(t, zone)).unwrap_or_default();
+            content.push_str(&format!("### {} · {}\n\n", title, date));
+            match conv.summary.as_deref().filter(|s| !s.trim().is_empty()) {
+                Some(summary) => content.push_str(&format!("{}\n\n", summary.trim())),
+                None => content.push_str("_No summary yet._\n\n"),
+            }
+            if let Some(i) = conv.instructions.as_deref().filter(|i| !i.trim().is_empty()) {
+                content.push_str(&format!("Instructions: {}\n\n", i.trim()));
+            }
+        }
+
+        let dir = root.join(folder);
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        std::fs::write(dir.join("README.md"), content).map_err(|e| e.to_string())?;
     }
 
-    Ok(extracted)
+    Ok(())
 }
 
-// ── Group chats export ─────────────────────────────────────────────────────────
+// ── Front matter ──────────────────────────────────────────────────────────────
+
+/// Web address of a conversation in ChatGPT, keyed by conversation id.
+const CHATGPT_CONVERSATION_URL: &str = "https://chatgpt.com/c/";
+
+/// "Shared publicly at …" note under the title for conversations that have share links.
+pub(crate) fn share_note(conv: &db::ExportRow) -> String {
+    if conv.shares.is_empty() {
+        return String::new();
+    }
+    let links: Vec<String> = conv
+        .shares
+        .iter()
+        .map(|s| if s.anonymous { format!("{} (anonymous)", s.url) } else { s.url.clone() })
+        .collect();
+    format!("_Shared publicly at {}_\n\n", links.join(", "))
+}
 
-#[derive(Deserialize)]
-struct GroupChatsExport {
-    chats: Vec<GroupChat>,
+/// Renders the YAML front matter block that opens every conversation file, so Obsidian,
+/// static site generators and scripts can index the archive without scraping markdown.
+pub(crate) fn front_matter(conv: &db::ExportRow, models: &[String], zone: Zone) -> String {
+    let time = |ts: Option<i64>| ts.map(|t| dates::iso8601(t, zone));
+    let mut fm = String::from("---\n");
+    fm.push_str(&format!("id: {}\n", yaml_value(Some(&conv.id))));
+    fm.push_str(&format!("title: {}\n", yaml_value(Some(conv.title.as_deref().unwrap_or("Untitled")))));
+    fm.push_str(&format!("kind: {}\n", conv.kind.as_str()));
+    if conv.kind == ConversationKind::Group {
+        fm.push_str(&format!("participants: {}\n", yaml_list(&conv.participants)));
+    }
+    fm.push_str(&format!("created: {}\n", yaml_value(time(conv.created_at).as_deref())));
+    fm.push_str(&format!("updated: {}\n", yaml_value(time(conv.updated_at).as_deref())));
+    fm.push_str(&format!("last_active: {}\n", yaml_value(time(conv.last_active_at).as_deref())));
+    fm.push_str(&format!("message_count: {}\n", conv.message_count));
+    fm.push_str(&format!("has_code: {}\n", conv.has_code));
+    fm.push_str(&format!("has_images: {}\n", conv.has_images));
+    fm.push_str(&format!("gizmo_id: {}\n", yaml_value(conv.gizmo_id.as_deref())));
+    fm.push_str(&format!("cluster_label: {}\n", yaml_value(conv.cluster_label.as_deref())));
+    fm.push_str(&format!("project: {}\n", yaml_value(conv.project_name.as_deref())));
+    fm.push_str(&format!("summary: {}\n", yaml_value(conv.summary.as_deref())));
+    fm.push_str(&format!("models: {}\n", yaml_list(models)));
+    let share_urls: Vec<String> = conv.shares.iter().map(|s| s.url.clone()).collect();
+    fm.push_str(&format!("shared: {}\n", yaml_list(&share_urls)));
+    fm.push_str(&format!("accounts: {}\n", yaml_list(&conv.accounts)));
+    // Group chats have no /c/ page of their own
+    let url = (conv.kind == ConversationKind::Chat).then(|| format!("{}{}", CHATGPT_CONVERSATION_URL, conv.id));
+    fm.push_str(&format!("url: {}\n", yaml_value(url.as_deref())));
+    fm.push_str("---\n\n");
+    fm
 }
 
-#[derive(Deserialize)]
-struct GroupChat {
-    name: String,
-    created_at: Option<String>,
-    messages: Vec<GroupChatMessage>,
+/// YAML scalar for an optional string. Emitted as a JSON string, which is a valid
+/// double-quoted YAML scalar, so titles with colons, quotes or newlines stay intact.
+fn yaml_value(value: Option<&str>) -> String {
+    match value {
+        Some(v) => serde_json::to_string(v).unwrap_or_else(|_| "null".to_string()),
+        None => "null".to_string(),
+    }
 }
 
-#[derive(Deserialize)]
-struct GroupChatMessage {
-    role: String,
-    text: String,
+/// YAML flow sequence of strings (JSON array syntax).
+fn yaml_list(values: &[String]) -> String {
+    serde_json::to_string(values).unwrap_or_else(|_| "[]".to_string())
 }
 
-/// Exports group_chats.json as markdown files into ~/Documents/ChatGPT History/group-chats/
-fn export_group_chats(zip_path: &str, root: &PathBuf) -> Result<usize, String> {
-    let file = std::fs::File::open(zip_path).map_err(|e| e.to_string())?;
-    let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+// ── Transcript rendering ──────────────────────────────────────────────────────
 
-    // Find group_chats.json
-    let bytes = {
-        let mut found_idx = None;
-        for i in 0..archive.len() {
-            if let Ok(entry) = archive.by_index(i) {
-                if entry.name().ends_with("group_chats.json") {
-                    found_idx = Some(i);
-                    break;
-                }
-            }
+/// Renders the transcript with a speaker line and local timestamp per message.
+/// Falls back to the flat `full_text` for conversations imported before messages were stored.
+///
+/// `policy` picks the roles shown and whether tool calls and outputs appear; they are
+/// rendered as collapsible sections. `embed` maps a file id (an image, recording or
+/// attachment) to the markdown that shows or links it; ids it returns `None` for are left out.
+pub(crate) fn render_transcript(
+    messages: &[db::MessageRow],
+    full_text: &str,
+    zone: Zone,
+    policy: &InclusionPolicy,
+    embed: &dyn Fn(&str) -> Option<String>,
+) -> String {
+    if messages.is_empty() {
+        return full_text.trim().to_string();
+    }
+
+    let mut body = String::new();
+    for msg in messages.iter().filter(|m| policy.includes(m.kind, &m.role)) {
+        let attached = msg.attachments.iter().map(|a| a.id.as_str()).filter(|id| !msg.assets.iter().any(|a| a == id));
+        let embeds: Vec<String> = msg.assets.iter().map(String::as_str).chain(attached).filter_map(embed).collect();
+        if msg.text.trim().is_empty() && embeds.is_empty() {
+            continue;
         }
-        match found_idx {
-            None => return Ok(0),
-            Some(idx) => {
-                let mut entry = archive.by_index(idx).map_err(|e| e.to_string())?;
-                let mut buf = Vec::new();
-                std::io::Read::read_to_end(&mut entry, &mut buf)
-                    .map_err(|e| e.to_string())?;
-                buf
-            }
+        if msg.kind != MessageKind::Message {
+            body.push_str(&render_tool_section(msg, &embeds));
+            continue;
         }
-    };
+        // Group chats name every author; in regular chats the role is enough
+        let speaker = match (msg.author_name.as_deref(), msg.role.as_str()) {
+            (Some(name), _) => name,
+            (None, "user") => "You",
+            (None, "assistant") => "ChatGPT",
+            (None, "system") => "System",
+            (None, other) => other,
+        };
+        let mut header = format!("**{speaker}**");
+        if let Some(ts) = msg.created_at {
+            header.push_str(&format!(" · {}", dates::datetime_str(ts, zone)));
+        }
+        if let Some(ref feedback) = msg.feedback {
+            header.push_str(if feedback.positive { " · rated good" } else { " · rated bad" });
+        }
+        body.push_str(&header);
+        body.push_str("\n\n");
+        if !msg.text.trim().is_empty() {
+            body.push_str(msg.text.trim());
+            body.push_str("\n\n");
+        }
+        for line in &embeds {
+            body.push_str(line);
+            body.push_str("\n\n");
+        }
+        if let Some(line) = msg.feedback.as_ref().and_then(feedback_note) {
+            body.push_str(&line);
+            body.push_str("\n\n");
+        }
+    }
+    body.trim_end().to_string()
+}
 
-    let export: GroupChatsExport = match serde_json::from_slice(&bytes) {
-        Ok(v) => v,
-        Err(_) => return Ok(0),
+/// The reasons and comment given with a rating, as a quote line; `None` when there were none.
+fn feedback_note(feedback: &db::Feedback) -> Option<String> {
+    let mut parts: Vec<String> = feedback.tags.iter().map(|t| t.replace(['-', '_'], " ")).collect();
+    if let Some(ref text) = feedback.text {
+        parts.push(format!("\"{}\"", text.split_whitespace().collect::<Vec<_>>().join(" ")));
+    }
+    (!parts.is_empty()).then(|| format!("> Feedback: {}", parts.join(" · ")))
+}
+
+/// Renders a tool call or tool output as a collapsible `<details>` section: the code,
+/// query or image prompt sent to the tool, or what it returned, plus any files it produced.
+fn render_tool_section(msg: &db::MessageRow, embeds: &[String]) -> String {
+    let tool = msg.tool.as_deref().unwrap_or("tool");
+    let category = ToolCategory::classify(tool);
+    let summary = match (msg.kind, category) {
+        (MessageKind::ToolOutput, _) => format!("Output · {tool}"),
+        (_, ToolCategory::Other) => format!("Tool call · {tool}"),
+        (_, category) => format!("{} · {}", category.label(), tool),
     };
 
-    if export.chats.is_empty() {
-        return Ok(0);
+    let text = msg.text.trim();
+    let mut section = format!("<details>\n<summary>{summary}</summary>\n\n");
+    if !text.is_empty() {
+        // DALL-E and some plugins receive JSON; show the prompt or query when there is one
+        let json = serde_json::from_str::<serde_json::Value>(text)
+            .ok()
+            .filter(|v| v.is_object() || v.is_array());
+        let prompt = json
+            .as_ref()
+            .and_then(|v| v["prompt"].as_str().or_else(|| v["query"].as_str()));
+        match prompt {
+            Some(prompt) => section.push_str(&format!("> {}\n\n", prompt.replace('\n', "\n> "))),
+            None => {
+                let is_code = msg.content_type.as_deref() == Some("code")
+                    || (msg.kind == MessageKind::ToolCall && category == ToolCategory::CodeInterpreter);
+                let language = if is_code {
+                    "python"
+                } else if json.is_some() {
+                    "json"
+                } else {
+                    "text"
+                };
+                section.push_str(&fenced(text, language));
+            }
+        }
+    }
+
+    let files = sandbox_files(text);
+    if !files.is_empty() {
+        section.push_str("Files (left in ChatGPT's sandbox, not part of the export):\n\n");
+        for file in files {
+            section.push_str(&format!("- `{file}`\n"));
+        }
+        section.push('\n');
+    }
+    for line in embeds {
+        section.push_str(line);
+        section.push_str("\n\n");
+    }
+    section.push_str("</details>\n\n");
+    section
+}
+
+/// Wraps `text` in a code fence longer than any backtick run inside it.
+fn fenced(text: &str, language: &str) -> String {
+    let longest = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{fence}{language}\n{text}\n{fence}\n\n")
+}
+
+/// Paths under `/mnt/data/` — where code interpreter reads and writes files — mentioned in `text`.
+fn sandbox_files(text: &str) -> Vec<String> {
+    let mut files: Vec<String> = Vec::new();
+    for (start, _) in text.match_indices("/mnt/data/") {
+        let path: String = text[start..]
+            .chars()
+            .take_while(|c| !c.is_whitespace() && !matches!(c, '\'' | '"' | ')' | ']' | ',' | '`'))
+            .collect();
+        if path.len() > "/mnt/data/".len() && !files.contains(&path) {
+            files.push(path);
+        }
     }
+    files
+}
 
-    let dir = root.join("group-chats");
-    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+// ── Asset extraction ───────────────────────────────────────────────────────────
 
-    let mut written = 0;
-    let mut seen: HashMap<String, usize> = HashMap::new();
+/// Where `export_media` put each file, by the file id messages use to point at it.
+#[derive(Default)]
+pub(crate) struct ExportedMedia {
+    /// Files written or already up to date.
+    pub written: usize,
+    /// Typed folders that received at least one file.
+    pub folders: BTreeSet<MediaKind>,
+    /// Path relative to the archive root, e.g. `generated/file-AbC123-cat.webp`.
+    paths: HashMap<String, String>,
+}
 
-    for chat in &export.chats {
-        let title = if chat.name.is_empty() { "Untitled Group Chat" } else { &chat.name };
-        let base_slug = slugify(title);
-        let count = seen.entry(base_slug.clone()).or_insert(0);
-        let file_name = if *count == 0 {
-            format!("{}.md", base_slug)
+impl ExportedMedia {
+    /// Markdown showing file `id` (images) or linking to it, from a note `depth` folders below
+    /// the archive root.
+    fn markdown(&self, id: &str, depth: usize) -> Option<String> {
+        let path = self.paths.get(id)?;
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let target = format!("{}{path}", "../".repeat(depth));
+        Some(if is_image(name) {
+            format!("![{name}](<{target}>)")
         } else {
-            format!("{}-{}.md", base_slug, count)
-        };
-        *count += 1;
+            format!("[{name}](<{target}>)")
+        })
+    }
 
-        // Take only YYYY-MM-DD from the ISO timestamp
-        let date_line = chat.created_at.as_deref()
-            .map(|d| format!("_{}_\n\n", &d[..d.len().min(10)]))
-            .unwrap_or_default();
+    /// An Obsidian embed of file `id`; Obsidian shows images, PDFs and audio inline.
+    pub(crate) fn wikilink(&self, id: &str) -> Option<String> {
+        self.paths.get(id).map(|path| format!("![[{path}]]"))
+    }
+}
+
+fn is_image(name: &str) -> bool {
+    let ext = name.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()).unwrap_or_default();
+    matches!(ext.as_str(), "jpg" | "jpeg" | "png" | "gif" | "webp" | "svg")
+}
+
+/// Where `export_media` reads a media file from.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MediaOrigin {
+    /// The media store, by hash.
+    Store,
+    /// The export at this index of the available sources, whose media was not stored.
+    Source(usize),
+}
 
-        let mut body = String::new();
-        for msg in &chat.messages {
-            if msg.text.is_empty() {
-                continue;
+/// Lists the media files of an export whose media is not in the store, with what they are,
+/// leaving out files only excluded conversations use (like `db::get_stored_media`).
+/// Reads each file once to hash and sniff it; nothing is written.
+fn scan_source_media(
+    source: &ExportSource,
+    refs: &HashMap<String, db::MediaFileRefs>,
+) -> Result<Vec<db::StoredMediaRow>, String> {
+    let mut files = Vec::new();
+    source.for_each(is_media_file, |name, reader| {
+        let file_refs = refs.get(media_store::file_id(name));
+        if file_refs.is_some_and(|r| !r.included) {
+            return Ok(());
+        }
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).map_err(|e| format!("Cannot read {name}: {e}"))?;
+        files.push(db::StoredMediaRow {
+            path: name.to_string(),
+            hash: sha256_hex([buf.as_slice()]),
+            mime_type: Some(media_store::sniff(name, &buf).mime_type.to_string()),
+            generated: file_refs.is_some_and(|r| r.generated),
+        });
+        Ok(())
+    })?;
+    Ok(files)
+}
+
+/// Puts the media of the exported sources into typed folders of the archive —
+/// attachments/, audio/ and generated/ (see `MediaKind::classify`) — hard-linked or copied
+/// from the media store, or copied from the export for sources whose media wasn't stored.
+/// Files keep their name from the export, which starts with the file id
+/// notes link them by; when different files share a name, each gets the start of its hash
+/// appended (`photo-3fa9c1d2.png`). The same file in several exports is written once,
+/// from the store when it is there.
+fn export_media(
+    media: &MediaStore,
+    sources: &[ExportSource],
+    files: &[(MediaOrigin, db::StoredMediaRow)],
+    root: &Path,
+) -> Result<ExportedMedia, String> {
+    // Distinct files per name, compared case-insensitively for macOS and Windows
+    let mut by_name: BTreeMap<String, BTreeMap<&str, &(MediaOrigin, db::StoredMediaRow)>> = BTreeMap::new();
+    for entry in files {
+        let file = &entry.1;
+        let filename = file.path.rsplit('/').next().unwrap_or(&file.path);
+        if !filename.is_empty() {
+            let distinct = by_name.entry(filename.to_lowercase()).or_default();
+            let kept = distinct.entry(&file.hash).or_insert(entry);
+            if kept.0 != MediaOrigin::Store && entry.0 == MediaOrigin::Store {
+                *kept = entry;
             }
-            let role_label = match msg.role.as_str() {
-                "user" => "**You**",
-                "assistant" => "**ChatGPT**",
-                other => other,
+        }
+    }
+
+    let mut exported = ExportedMedia::default();
+    // Export entry path → destinations, per source read from below
+    let mut from_sources: BTreeMap<usize, HashMap<&str, Vec<PathBuf>>> = BTreeMap::new();
+    for distinct in by_name.values() {
+        for (hash, (origin, file)) in distinct {
+            let filename = file.path.rsplit('/').next().unwrap_or(&file.path);
+            let name = if distinct.len() == 1 {
+                filename.to_string()
+            } else {
+                match filename.rsplit_once('.') {
+                    Some((stem, ext)) if !stem.is_empty() => format!("{stem}-{}.{ext}", &hash[..8]),
+                    _ => format!("{filename}-{}", &hash[..8]),
+                }
             };
-            body.push_str(&format!("{}: {}\n\n", role_label, msg.text.trim()));
+            let kind = MediaKind::classify(&file.path, file.mime_type.as_deref().unwrap_or(""), file.generated);
+            let dir = root.join(kind.folder());
+            std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            match origin {
+                MediaOrigin::Store => media.link_to(hash, &dir.join(&name))?,
+                MediaOrigin::Source(index) => {
+                    from_sources.entry(*index).or_default().entry(&file.path).or_default().push(dir.join(&name))
+                }
+            }
+            exported.written += 1;
+            exported.folders.insert(kind);
+            exported
+                .paths
+                .entry(media_store::file_id(filename).to_string())
+                .or_insert_with(|| format!("{}/{name}", kind.folder()));
         }
+    }
 
-        let content = format!("# {}\n\n{}---\n\n{}\n", title, date_line, body.trim());
-        std::fs::write(dir.join(&file_name), content).map_err(|e| e.to_string())?;
-        written += 1;
+    for (index, wanted) in &from_sources {
+        sources[*index].for_each(
+            |name| wanted.contains_key(name),
+            |name, reader| {
+                let mut buf = Vec::new();
+                reader.read_to_end(&mut buf).map_err(|e| format!("Cannot read {name}: {e}"))?;
+                for dest in &wanted[name] {
+                    // Unchanged media from an earlier export is left as it is, as in `MediaStore::link_to`
+                    if !std::fs::metadata(dest).is_ok_and(|m| m.len() == buf.len() as u64) {
+                        std::fs::write(dest, &buf).map_err(|e| format!("Cannot write {}: {e}", dest.display()))?;
+                    }
+                }
+                Ok(())
+            },
+        )?;
     }
+    Ok(exported)
+}
 
-    Ok(written)
+/// Whether an export entry is media — a file uploaded to, generated in or recorded during a
+/// conversation — rather than the export's own data (JSON files and chat.html).
+pub(crate) fn is_media_file(name: &str) -> bool {
+    let filename = name.rsplit('/').next().unwrap_or(name);
+    let ext = std::path::Path::new(filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    !filename.is_empty() && filename != ".DS_Store" && ext != "json" && filename != "chat.html"
 }
 
 // ── Supplementary data files ───────────────────────────────────────────────────
 
-/// Copies shared_conversations.json to ~/Documents/ChatGPT History/data/
-fn copy_shared_conversations(zip_path: &str, root: &PathBuf) -> Result<(), String> {
-    let file = std::fs::File::open(zip_path).map_err(|e| e.to_string())?;
-    let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
-
-    for i in 0..archive.len() {
-        let name = {
-            let e = archive.by_index(i).map_err(|e| e.to_string())?;
-            e.name().to_string()
+/// Writes data/model_comparisons.md — the "which answer do you prefer?" choices from
+/// model_comparisons.json, newest first. Nothing is written when there were none.
+fn write_comparisons(root: &Path, comparisons: &[db::ComparisonRow], zone: Zone) -> Result<(), String> {
+    if comparisons.is_empty() {
+        return Ok(());
+    }
+    let mut content = format!(
+        "# Model comparisons\n\n_{} times ChatGPT asked which of two answers was better._\n\n",
+        comparisons.len()
+    );
+    for row in comparisons {
+        let date = row.created_at.map(|t| dates::date_str(t, zone)).unwrap_or_else(|| "Unknown date".to_string());
+        let conversation = match (&row.conversation_title, &row.conversation_id) {
+            (Some(title), _) => format!("**{title}**"),
+            (None, Some(id)) => format!("conversation `{id}`"),
+            (None, None) => "unknown conversation".to_string(),
         };
-        if name.ends_with("shared_conversations.json") {
-            let mut entry = archive.by_index(i).map_err(|e| e.to_string())?;
-            let mut buf = Vec::new();
-            std::io::Read::read_to_end(&mut entry, &mut buf).map_err(|e| e.to_string())?;
-            if buf.len() <= 4 {
-                return Ok(());
-            }
-            let data_dir = root.join("data");
-            std::fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
-            std::fs::write(data_dir.join("shared_conversations.json"), buf)
-                .map_err(|e| e.to_string())?;
-            return Ok(());
-        }
+        let rating = row.rating.as_deref().map(|r| format!(" — {r}")).unwrap_or_default();
+        content.push_str(&format!("- {date} · {conversation}{rating}\n"));
     }
 
-    Ok(())
+    let data_dir = root.join("data");
+    std::fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    std::fs::write(data_dir.join("model_comparisons.md"), content).map_err(|e| e.to_string())
 }
 
-/// Reads the user's name from user.json in the ZIP (does not write PII to disk)
-fn read_user_name(zip_path: &str) -> Option<String> {
-    let file = std::fs::File::open(zip_path).ok()?;
-    let mut archive = zip::ZipArchive::new(file).ok()?;
-    for i in 0..archive.len() {
-        let name = archive.by_index(i).ok()?.name().to_string();
-        if name.ends_with("user.json") {
-            let mut entry = archive.by_index(i).ok()?;
-            let mut buf = Vec::new();
-            std::io::Read::read_to_end(&mut entry, &mut buf).ok()?;
-            let json: serde_json::Value = serde_json::from_slice(&buf).ok()?;
-            return json.get("name")?.as_str().map(String::from);
-        }
+/// Copies shared_conversations.json to ~/Documents/ChatGPT History/data/, minus the
+/// links to excluded conversations. The links themselves are imported by `parse_zip`
+/// and listed in INDEX.md.
+fn copy_shared_conversations(source: &ExportSource, root: &PathBuf, excluded: &HashSet<String>) -> Result<(), String> {
+    let Some(mut buf) = source.read_optional("shared_conversations.json")? else {
+        return Ok(());
+    };
+    if !excluded.is_empty() {
+        let Ok(entries) = serde_json::from_slice::<Vec<serde_json::Value>>(&buf) else {
+            // Can't tell which links are safe to keep
+            return Ok(());
+        };
+        let kept: Vec<_> = entries
+            .into_iter()
+            .filter(|e| !e["conversation_id"].as_str().is_some_and(|id| excluded.contains(id)))
+            .collect();
+        buf = serde_json::to_vec_pretty(&kept).map_err(|e| e.to_string())?;
     }
-    None
+    if buf.len() <= 4 {
+        return Ok(());
+    }
+    let data_dir = root.join("data");
+    std::fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    std::fs::write(data_dir.join("shared_conversations.json"), buf).map_err(|e| e.to_string())
+}
+
+/// Account label of an import source, as stored in `ExportRow::accounts`.
+fn account_label(source: &db::ImportSourceRow) -> Option<&str> {
+    source.account_email.as_deref().or(source.account_name.as_deref())
 }
 
 // ── INDEX.md generation ───────────────────────────────────────────────────────
 
-/// Generates INDEX.md — all conversation titles and dates grouped by year.
+/// Generates INDEX.md — all conversation titles with created and last-active dates,
+/// grouped by the year chosen by `bucket_by`, after a per-model and per-tool usage breakdown.
 /// This is the fast-path for Phase 1: Claude reads one file instead of opening
 /// 20-30 individual markdown files to understand what's in the history.
 /// Always regenerated on export so it stays current.
-fn generate_index(root: &PathBuf, conversations: &[db::ExportRow]) -> Result<(), String> {
+fn generate_index(
+    root: &PathBuf,
+    conversations: &[db::ExportRow],
+    zone: Zone,
+    bucket_by: DateBucket,
+    model_usage: &[db::ModelUsage],
+    tool_usage: &[db::ToolUsage],
+) -> Result<(), String> {
     // Group by year, most recent first
-    let mut by_year: std::collections::BTreeMap<String, Vec<(&str, Option<i64>)>> =
+    let mut by_year: std::collections::BTreeMap<String, Vec<&db::ExportRow>> =
         std::collections::BTreeMap::new();
 
     for conv in conversations {
-        let year = conv.created_at
-            .map(|ts| unix_to_year(ts))
+        let year = bucket_by.timestamp(conv)
+            .map(|ts| dates::year(ts, zone).to_string())
             .unwrap_or_else(|| "Unknown".to_string());
-        let title = conv.title.as_deref().unwrap_or("Untitled");
-        by_year.entry(year).or_default().push((title, conv.created_at));
+        by_year.entry(year).or_default().push(conv);
     }
 
     let total = conversations.len();
@@ -369,13 +1030,55 @@ fn generate_index(root: &PathBuf, conversations: &[db::ExportRow]) -> Result<(),
          > Open individual files only for conversations you want to examine closely.\n\n---\n\n"
     );
 
+    if !model_usage.is_empty() {
+        content.push_str("## Models used\n\n| Model | Conversations | Replies |\n|---|---:|---:|\n");
+        for usage in model_usage {
+            content.push_str(&format!("| {} | {} | {} |\n", usage.model, usage.conversations, usage.messages));
+        }
+        content.push('\n');
+    }
+    if !tool_usage.is_empty() {
+        content.push_str("## Tools used\n\n| Tool | Conversations | Calls |\n|---|---:|---:|\n");
+        for usage in tool_usage {
+            content.push_str(&format!(
+                "| {} | {} | {} |\n",
+                usage.category.label(),
+                usage.conversations,
+                usage.calls
+            ));
+        }
+        content.push('\n');
+    }
+    if !model_usage.is_empty() || !tool_usage.is_empty() {
+        content.push_str("---\n\n");
+    }
+
+    // Every conversation with a public link, for reviewing what has been exposed
+    let shared: Vec<&db::ExportRow> = conversations.iter().filter(|c| !c.shares.is_empty()).collect();
+    if !shared.is_empty() {
+        content.push_str(&format!("## Shared publicly ({} conversations)\n\n", shared.len()));
+        for conv in shared {
+            let title = conv.title.as_deref().unwrap_or("Untitled");
+            let links: Vec<String> = conv.shares.iter().map(|s| s.url.clone()).collect();
+            content.push_str(&format!("- **{}** · {}\n", title, links.join(", ")));
+        }
+        content.push_str("\n---\n\n");
+    }
+
     // Write years in reverse (most recent first — more relevant for active projects)
     for year in by_year.keys().rev() {
         let convs = &by_year[year];
         content.push_str(&format!("## {} ({} conversations)\n\n", year, convs.len()));
-        for (title, ts) in convs {
-            let date = ts.map(|t| unix_to_date_str(t)).unwrap_or_default();
-            content.push_str(&format!("- **{}** · {}\n", title, date));
+        for conv in convs {
+            let title = conv.title.as_deref().unwrap_or("Untitled");
+            let created = conv.created_at.map(|t| dates::date_str(t, zone)).unwrap_or_default();
+            let group = if conv.kind == ConversationKind::Group { " · group chat" } else { "" };
+            match conv.last_active_at.map(|t| dates::date_str(t, zone)) {
+                Some(active) if active != created => content.push_str(&format!(
+                    "- **{}** · {} · last active {}{}\n", title, created, active, group
+                )),
+                _ => content.push_str(&format!("- **{}** · {}{}\n", title, created, group)),
+            }
         }
         content.push('\n');
     }
@@ -385,12 +1088,19 @@ fn generate_index(root: &PathBuf, conversations: &[db::ExportRow]) -> Result<(),
 
 // ── START_HERE.md generation ──────────────────────────────────────────────────
 
+/// Optional parts of the archive, listed in START_HERE.md only when they were written.
+struct ArchiveExtras<'a> {
+    media: &'a BTreeSet<MediaKind>,
+    profile: bool,
+}
+
 fn generate_readme(
     root: &PathBuf,
     conversations: &[db::ExportRow],
+    zone: Zone,
+    layout: FolderLayout,
     user_name: Option<&str>,
-    has_media: bool,
-    has_group_chats: bool,
+    extras: ArchiveExtras,
 ) -> Result<(), String> {
     let all_titles: Vec<String> = conversations.iter()
         .filter_map(|c| c.title.clone())
@@ -414,7 +1124,7 @@ fn generate_readme(
 
     let years: Vec<i32> = conversations.iter()
         .filter_map(|c| c.created_at)
-        .filter_map(|ts| unix_to_year(ts).parse::<i32>().ok())
+        .map(|ts| dates::year(ts, zone))
         .collect();
     let (earliest, latest) = years.iter().fold(
         (i32::MAX, i32::MIN),
@@ -445,7 +1155,7 @@ fn generate_readme(
             included in the export. A shared gizmo ID only means the same custom GPT was used;\n\
             treat it as a weak supporting hint, not a definitive project grouping.\n\
             Use content, titles, and dates as your primary bucketing signals.\n\
-            Each conversation file contains `<!-- gizmo:ID -->` in its header so you can grep for these.\n\n"
+            Each conversation file has `gizmo_id:` in its YAML front matter so you can grep for these.\n\n"
         );
         for (gid, titles) in &gizmo_clusters {
             s.push_str(&format!("### `{}`  ({} conversations)\n\n", gid, titles.len()));
@@ -465,14 +1175,21 @@ fn generate_readme(
         .map(|n| format!("- **Name:** {}", n))
         .unwrap_or_else(|| "- **Name:**".to_string());
 
+    let profile_line = if extras.profile {
+        "  PROFILE.md          ← my ChatGPT custom instructions and memories\n"
+    } else {
+        ""
+    };
+
     let mut extra_folders = String::new();
-    if has_group_chats {
-        extra_folders.push_str("  group-chats/       ← group conversations as markdown\n");
-    }
-    if has_media {
-        extra_folders.push_str("  media/             ← images (uploads & DALL-E generations)\n");
+    for kind in extras.media {
+        extra_folders.push_str(match kind {
+            MediaKind::Attachment => "  attachments/       ← files you uploaded (images, PDFs, spreadsheets…)\n",
+            MediaKind::Audio => "  audio/             ← voice conversation recordings\n",
+            MediaKind::Generated => "  generated/         ← DALL-E images and code interpreter output\n",
+        });
     }
-    extra_folders.push_str("  data/              ← shared conversations index\n");
+    extra_folders.push_str("  data/              ← shared conversations index, model comparisons\n");
 
     let content = format!(
 r#"# ChatGPT History
@@ -492,18 +1209,35 @@ My complete ChatGPT conversation history: **{count} conversations, {year_range}.
 ChatGPT History/
   START_HERE.md       ← you are here
   INDEX.md            ← all titles and dates — read this first in Phase 1
-  2023/ 2024/ 2025/   ← conversations by year, one .md file each
-{extra_folders}```
+{profile_line}{layout_line}{extra_folders}```
 
 Each `.md` file is one conversation:
 
 ```
+---
+id: "…"
+title: "Conversation Title"
+created: YYYY-MM-DDTHH:MM:SS+HH:MM
+models: ["gpt-4o"]
+url: "https://chatgpt.com/c/…"
+…
+---
+
 # Conversation Title
-_YYYY-MM-DD_
+_Created YYYY-MM-DD · Last active YYYY-MM-DD_
 ---
-[full conversation text]
+**You** · YYYY-MM-DD HH:MM
+
+[message]
+
+**ChatGPT** · YYYY-MM-DD HH:MM
+
+[reply]
 ```
 
+The front matter block is machine-readable: grep it for `models:`, `gizmo_id:` or
+`cluster_label:` instead of opening files one by one.
+
 ---
 
 ## Your mission: help me build a working knowledge base
@@ -519,7 +1253,8 @@ Walk me through this in order. Don't skip ahead without my confirmation.
 
 Do this immediately when I connect you:
 
-1. **Read `INDEX.md` first.** It lists every conversation title and date grouped by year.
+1. **Read `INDEX.md` first.** It lists every conversation title and date grouped by year,
+   with a "last active" date for threads that were picked up again later.
    This gives you a full picture of the history in one read — no need to open individual files yet.
 2. From the titles alone, identify 5–8 recurring themes, projects, or topic clusters.
 3. Open **10–15 individual files** for the conversations that look most significant or ambiguous
@@ -640,6 +1375,8 @@ What have I been trying to figure out for a long time?
 "#,
         count = conversations.len(),
         year_range = year_range,
+        profile_line = profile_line,
+        layout_line = layout.describe(),
         extra_folders = extra_folders,
         topics_list = topics_list,
         name_line = name_line,
@@ -739,48 +1476,20 @@ fn update_claude_desktop_config(home: &str, export_path: &str) -> Result<(), Str
     Ok(())
 }
 
-// ── Date helpers ──────────────────────────────────────────────────────────────
-
-fn unix_to_year(ts: i64) -> String {
-    let mut days = ts / 86_400;
-    let mut year = 1970i32;
-    loop {
-        let days_in_year = if is_leap(year) { 366i64 } else { 365i64 };
-        if days < days_in_year { break; }
-        days -= days_in_year;
-        year += 1;
-    }
-    year.to_string()
-}
-
-fn unix_to_date_str(ts: i64) -> String {
-    let mut days = ts / 86_400;
-    let mut year = 1970i32;
-    loop {
-        let days_in_year = if is_leap(year) { 366i64 } else { 365i64 };
-        if days < days_in_year { break; }
-        days -= days_in_year;
-        year += 1;
-    }
-    let month_lengths: [i64; 12] = [
-        31, if is_leap(year) { 29 } else { 28 },
-        31, 30, 31, 30, 31, 31, 30, 31, 30, 31,
-    ];
-    let mut month = 1u32;
-    for &m in &month_lengths {
-        if days < m { break; }
-        days -= m;
-        month += 1;
-    }
-    format!("{}-{:02}-{:02}", year, month, days + 1)
-}
+// ── File naming ───────────────────────────────────────────────────────────────
 
-fn is_leap(year: i32) -> bool {
-    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+/// Readable folder name from a label: keeps case and spaces, drops path separators and
+/// characters Finder/Windows reject, and never starts with a dot.
+pub(crate) fn folder_name(s: &str) -> String {
+    let cleaned: String = s
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control() { ' ' } else { c })
+        .collect();
+    let name = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    let name: String = name.trim_start_matches('.').chars().take(80).collect();
+    if name.trim().is_empty() { "Untitled".to_string() } else { name.trim().to_string() }
 }
 
-// ── File naming ───────────────────────────────────────────────────────────────
-
 fn slugify(s: &str) -> String {
     let slug: String = s
         .chars()
@@ -792,3 +1501,240 @@ fn slugify(s: &str) -> String {
         .join("-");
     slug.chars().take(60).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn media_of_unstored_exports_is_read_from_the_export_without_filling_the_store() {
+        let dir = std::env::temp_dir().join(format!("export-media-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("export/dalle-generations")).unwrap();
+        std::fs::write(dir.join("export/conversations.json"), "[]").unwrap();
+        std::fs::write(dir.join("export/file-abc-notes.txt"), "notes").unwrap();
+        std::fs::write(dir.join("export/dalle-generations/file-gen-cat.webp"), "RIFF....WEBP").unwrap();
+        std::fs::write(dir.join("export/file-private.txt"), "secret").unwrap();
+        let source = ExportSource::open(dir.join("export").to_str().unwrap()).unwrap();
+        let store = MediaStore::new(dir.join("store"));
+
+        // file-private is only used by an excluded conversation
+        let refs = HashMap::from([
+            ("file-abc".to_string(), db::MediaFileRefs { generated: false, included: true }),
+            ("file-private".to_string(), db::MediaFileRefs { generated: false, included: false }),
+        ]);
+        let files: Vec<_> = scan_source_media(&source, &refs)
+            .unwrap()
+            .into_iter()
+            .map(|file| (MediaOrigin::Source(0), file))
+            .collect();
+        let root = dir.join("archive");
+        let exported = export_media(&store, std::slice::from_ref(&source), &files, &root).unwrap();
+
+        assert_eq!(exported.written, 2);
+        assert_eq!(std::fs::read_to_string(root.join("attachments/file-abc-notes.txt")).unwrap(), "notes");
+        assert!(root.join("generated/file-gen-cat.webp").exists());
+        assert!(!root.join("attachments/file-private.txt").exists());
+        assert_eq!(exported.wikilink("file-abc").as_deref(), Some("![[attachments/file-abc-notes.txt]]"));
+        assert!(!dir.join("store").exists(), "exporting must not write to the media store");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn front_matter_of_a_chat() {
+        let conv = db::ExportRow {
+            id: "c1".to_string(),
+            title: Some("Re: \"Rust\" lifetimes: a primer".to_string()),
+            created_at: Some(1_704_065_400),
+            updated_at: Some(1_704_067_200),
+            last_active_at: Some(1_704_067_200),
+            message_count: 4,
+            has_code: true,
+            cluster_label: Some("Rust".to_string()),
+            shares: vec![db::ShareLink { url: "https://chatgpt.com/share/s1".to_string(), anonymous: true }],
+            accounts: vec!["me@example.com".to_string()],
+            ..Default::default()
+        };
+        let models = ["gpt-4o".to_string(), "o1-mini".to_string()];
+        assert_eq!(
+            front_matter(&conv, &models, Zone::Utc),
+            "---\n\
+             id: \"c1\"\n\
+             title: \"Re: \\\"Rust\\\" lifetimes: a primer\"\n\
+             kind: chat\n\
+             created: \"2023-12-31T23:30:00+00:00\"\n\
+             updated: \"2024-01-01T00:00:00+00:00\"\n\
+             last_active: \"2024-01-01T00:00:00+00:00\"\n\
+             message_count: 4\n\
+             has_code: true\n\
+             has_images: false\n\
+             gizmo_id: null\n\
+             cluster_label: \"Rust\"\n\
+             project: null\n\
+             summary: null\n\
+             models: [\"gpt-4o\",\"o1-mini\"]\n\
+             shared: [\"https://chatgpt.com/share/s1\"]\n\
+             accounts: [\"me@example.com\"]\n\
+             url: \"https://chatgpt.com/c/c1\"\n\
+             ---\n\n"
+        );
+    }
+
+    #[test]
+    fn front_matter_of_an_undated_group_chat() {
+        let conv = db::ExportRow {
+            id: "g1".to_string(),
+            title: Some("Trip: plans\nand \"ideas\"".to_string()),
+            kind: ConversationKind::Group,
+            participants: vec!["Sam".to_string(), "Alex".to_string()],
+            ..Default::default()
+        };
+        let fm = front_matter(&conv, &[], Zone::Utc);
+        assert!(fm.contains("title: \"Trip: plans\\nand \\\"ideas\\\"\"\nkind: group\nparticipants: [\"Sam\",\"Alex\"]\n"), "{fm}");
+        assert!(fm.contains("created: null\nupdated: null\nlast_active: null\n"), "{fm}");
+        assert!(fm.contains("models: []\nshared: []\naccounts: []\n"), "{fm}");
+        // Group chats have no conversation page to link to
+        assert!(fm.ends_with("url: null\n---\n\n"), "{fm}");
+        // Only group chats list participants
+        assert!(!front_matter(&db::ExportRow::default(), &[], Zone::Utc).contains("participants"));
+    }
+
+    fn shared(id: &str, title: &str, shares: &[(&str, bool)]) -> db::ExportRow {
+        db::ExportRow {
+            id: id.to_string(),
+            title: Some(title.to_string()),
+            created_at: Some(1_717_200_000),
+            shares: shares
+                .iter()
+                .map(|&(url, anonymous)| db::ShareLink { url: url.to_string(), anonymous })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn share_note_lists_every_link() {
+        assert_eq!(share_note(&shared("c1", "Private", &[])), "");
+        let conv = shared("c1", "Bread", &[("https://chatgpt.com/share/s1", false), ("https://chatgpt.com/share/s2", true)]);
+        assert_eq!(
+            share_note(&conv),
+            "_Shared publicly at https://chatgpt.com/share/s1, https://chatgpt.com/share/s2 (anonymous)_\n\n"
+        );
+    }
+
+    #[test]
+    fn index_lists_shared_conversations() {
+        let root = temp_archive("index");
+        let conversations = [
+            shared("c1", "Bread", &[("https://chatgpt.com/share/s1", false)]),
+            shared("c2", "Taxes", &[]),
+        ];
+        generate_index(&root, &conversations, Zone::Utc, DateBucket::default(), &[], &[]).unwrap();
+        let index = std::fs::read_to_string(root.join("INDEX.md")).unwrap();
+        assert!(
+            index.contains("## Shared publicly (1 conversations)\n\n- **Bread** · https://chatgpt.com/share/s1\n"),
+            "{index}"
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn shared_links_of_excluded_conversations_are_not_copied() {
+        let dir = temp_archive("shares");
+        std::fs::create_dir_all(dir.join("export")).unwrap();
+        std::fs::write(dir.join("export/conversations.json"), "[]").unwrap();
+        std::fs::write(
+            dir.join("export/shared_conversations.json"),
+            serde_json::json!([
+                { "id": "s1", "conversation_id": "c1", "title": "Bread", "is_anonymous": false },
+                { "id": "s2", "conversation_id": "c-secret", "title": "Diagnosis", "is_anonymous": true }
+            ])
+            .to_string(),
+        )
+        .unwrap();
+        let source = ExportSource::open(dir.join("export").to_str().unwrap()).unwrap();
+        let root = dir.join("archive");
+
+        copy_shared_conversations(&source, &root, &HashSet::from(["c-secret".to_string()])).unwrap();
+        let copied = std::fs::read_to_string(root.join("data/shared_conversations.json")).unwrap();
+        assert!(copied.contains("\"s1\"") && !copied.contains("c-secret") && !copied.contains("Diagnosis"), "{copied}");
+
+        // Nothing is written when every link belongs to an excluded conversation
+        let all = HashSet::from(["c1".to_string(), "c-secret".to_string()]);
+        let _ = std::fs::remove_dir_all(&root);
+        copy_shared_conversations(&source, &root, &all).unwrap();
+        assert!(!root.join("data/shared_conversations.json").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn temp_archive(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("export-test-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn database(ids: &[&str]) -> Mutex<Connection> {
+        let conn = Connection::open_in_memory().unwrap();
+        db::init_schema(&conn).unwrap();
+        for id in ids {
+            conn.execute("INSERT INTO conversations (id, title) VALUES (?1, ?1)", [id]).unwrap();
+        }
+        Mutex::new(conn)
+    }
+
+    fn conversation(id: &str, title: Option<String>) -> db::ExportRow {
+        db::ExportRow { id: id.to_string(), title, created_at: Some(1_717_200_000), ..Default::default() }
+    }
+
+    /// Every markdown file under `root`, relative and `/`-separated.
+    fn markdown_files(root: &Path) -> Vec<String> {
+        let mut files = Vec::new();
+        for year in std::fs::read_dir(root).unwrap().flatten().filter(|e| e.path().is_dir()) {
+            for file in std::fs::read_dir(year.path()).unwrap().flatten() {
+                files.push(format!("{}/{}", year.file_name().to_string_lossy(), file.file_name().to_string_lossy()));
+            }
+        }
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn files_of_conversations_excluded_since_an_earlier_export_are_removed() {
+        let root = temp_archive("excluded");
+        let db = database(&["c1", "c2"]);
+        let redactor = Redactor::new(&RedactionPolicy::default(), Vec::new()).unwrap();
+        let options = ExportOptions::default();
+        let other = || conversation("c2", Some("Other".into()));
+        let both = [conversation("c1", Some("Secret".into())), other()];
+        write_standard(&db, &root, &both, Zone::Utc, &options, &ExportedMedia::default(), &redactor).unwrap();
+        assert_eq!(markdown_files(&root), ["2024/other.md", "2024/secret.md"]);
+
+        db.lock().unwrap().execute("UPDATE conversations SET excluded = 1 WHERE id = 'c1'", []).unwrap();
+        write_standard(&db, &root, &[other()], Zone::Utc, &options, &ExportedMedia::default(), &redactor).unwrap();
+        assert_eq!(markdown_files(&root), ["2024/other.md"]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn redaction_replaces_files_named_after_the_real_title() {
+        let root = temp_archive("redacted");
+        let db = database(&["c1"]);
+        let options = ExportOptions::default();
+        let plain = Redactor::new(&RedactionPolicy::default(), Vec::new()).unwrap();
+        let conv = conversation("c1", Some("Trip with Alice".into()));
+        write_standard(&db, &root, &[conv], Zone::Utc, &options, &ExportedMedia::default(), &plain).unwrap();
+        assert_eq!(markdown_files(&root), ["2024/trip-with-alice.md"]);
+
+        let policy = RedactionPolicy { deny: vec!["Alice".to_string()], ..Default::default() };
+        let redactor = Redactor::new(&policy, Vec::new()).unwrap();
+        let conv = conversation("c1", redactor.redact_opt(Some("Trip with Alice")));
+        write_standard(&db, &root, &[conv], Zone::Utc, &options, &ExportedMedia::default(), &redactor).unwrap();
+
+        let files = markdown_files(&root);
+        assert_eq!(files.len(), 1, "{files:?}");
+        assert!(!files[0].contains("alice"), "{files:?}");
+        assert!(!std::fs::read_to_string(root.join(&files[0])).unwrap().contains("Alice"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
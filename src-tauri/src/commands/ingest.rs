@@ -1,120 +1,334 @@
-use crate::pipeline::{json_parser, normalizer, zip_reader};
+use crate::commands::sources;
+use crate::dates::{self, Zone};
+use crate::pipeline::format::{self, ExportFingerprint};
+use crate::pipeline::report::ImportReport;
+use crate::pipeline::source::{self, ExportSource};
+use crate::pipeline::{feedback, group_chats, json_parser, normalizer, shares};
 use crate::store::db;
 use crate::AppState;
 use serde::{Deserialize, Serialize};
+use std::time::Instant;
 use tauri::{ipc::Channel, State};
 
-/// Events emitted over the IPC channel during ZIP ingestion.
+/// Conversations written per transaction. Each batch is committed on its own and the
+/// database lock released in between, so other commands aren't blocked for the whole import
+/// of a multi-GB export and a failure near the end keeps what was written.
+const INGEST_BATCH_SIZE: usize = 500;
+
+/// Events emitted over the IPC channel during ingestion.
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", tag = "event", content = "data")]
 pub enum IngestEvent {
     Started,
-    ExtractingZip,
-    ParsingConversations { processed: u32 },
+    ExtractingZip {
+        #[serde(rename = "bytesRead")]
+        bytes_read: u64,
+        #[serde(rename = "totalBytes")]
+        total_bytes: u64,
+        percent: u8,
+    },
+    ParsingConversations {
+        processed: u32,
+        total: u32,
+        percent: u8,
+    },
     BuildingIndex,
     Complete {
         total: u32,
+        /// `None` when the export carries no timestamps at all.
         #[serde(rename = "earliestYear")]
-        earliest_year: i32,
+        earliest_year: Option<i32>,
         #[serde(rename = "latestYear")]
-        latest_year: i32,
+        latest_year: Option<i32>,
+        #[serde(rename = "elapsedMs")]
+        elapsed_ms: u64,
+        #[serde(rename = "bytesRead")]
+        bytes_read: u64,
+        #[serde(rename = "conversationsPerSec")]
+        conversations_per_sec: f64,
+        #[serde(rename = "megabytesPerSec")]
+        megabytes_per_sec: f64,
+        /// Ratings from message_feedback.json attached to messages.
+        #[serde(rename = "feedbackImported")]
+        feedback_imported: u32,
+        /// Entries from model_comparisons.json.
+        #[serde(rename = "comparisonsImported")]
+        comparisons_imported: u32,
+        /// Group chats from group_chats.json, stored alongside (and counted in) `total`.
+        #[serde(rename = "groupChatsImported")]
+        group_chats_imported: u32,
+        /// Conversations left unchanged because an export imported earlier had a newer version.
+        #[serde(rename = "olderVersionsSkipped")]
+        older_versions_skipped: u32,
+        /// Account from user.json (email, or name when there is none).
+        account: Option<String>,
+        /// Public share links from shared_conversations.json.
+        #[serde(rename = "sharesImported")]
+        shares_imported: u32,
+        /// True when conversations.json was missing or corrupt and chat.html was used instead.
+        #[serde(rename = "recoveredFromHtml")]
+        recovered_from_html: bool,
+        /// Media files copied into the app's media store (only with `store_media`).
+        #[serde(rename = "mediaStored")]
+        media_stored: u32,
+        /// Conversations kept out of exports and API requests by exclusion rules or private marks.
+        excluded: u32,
+        /// Conversations that were skipped or only partially imported, and why.
+        report: ImportReport,
+        /// Detected export layout, with warnings for structures we don't recognise.
+        format: Box<ExportFingerprint>,
     },
     Error {
         message: String,
     },
 }
 
-/// Parses a ChatGPT export ZIP, streams all conversations into SQLite,
-/// and emits typed progress events back to the frontend via Channel.
+/// Parses a ChatGPT export — the ZIP, an extracted folder, a bare conversations.json or
+/// ZIPs nested in any of these (see `ExportSource`) — writes all conversations into
+/// SQLite, and emits typed progress events back to the frontend via Channel.
 ///
 /// Pipeline:
-///   1. Open ZIP, read conversations.json bytes into memory (Vec<u8>)
-///   2. Stream-deserialize the JSON array element by element (no full-array load)
-///   3. Normalize each ConversationExport to a ConversationRecord
-///   4. Insert into SQLite using INSERT OR REPLACE (idempotent)
-///   5. Emit Complete with total count and year range
+///   1. Open the source, read conversations.json bytes into memory (Vec<u8>), reporting bytes read
+///   2. Deserialize each shard element by element — before taking the DB lock. Conversations
+///      that don't parse are skipped or repaired and listed in the import report. If no
+///      conversation can be read at all, recover the same data from chat.html instead
+///   3. Fingerprint the export layout (files, sharding, content and part shapes, metadata
+///      keys) and collect warnings for anything unrecognised
+///   4. Read group_chats.json, message_feedback.json, model_comparisons.json and
+///      shared_conversations.json when present (optional — a file that fails to parse is
+///      listed in the import report rather than failing the import)
+///   5. Normalize each ConversationExport to a ConversationRecord
+///   6. Record the export in `import_sources` (keyed by a hash of its conversation data,
+///      with the account from user.json), then insert into SQLite in batches of
+///      `INGEST_BATCH_SIZE`, each committed with a progress event. Written conversations are
+///      checked against the exclusion rules within their batch, so no commit exposes one that
+///      should be excluded. Ratings, comparisons and share links follow in a final
+///      transaction. Exports merge: a conversation already stored from a newer export keeps
+///      that version, and every export it appeared in is noted in `conversation_sources`
+///   7. With `store_media`, copy the export's media into the app's content-addressed store
+///      so later exports don't need the original file
+///   8. Emit Complete with total count, year range (in `time_zone`, default local),
+///      timing and throughput
 #[tauri::command]
 pub async fn parse_zip(
     path: String,
+    time_zone: Option<String>,
+    store_media: Option<bool>,
     on_event: Channel<IngestEvent>,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
+    let started_at = Instant::now();
+    let zone = Zone::parse(time_zone.as_deref())?;
+
     on_event
         .send(IngestEvent::Started)
         .map_err(|e| e.to_string())?;
 
-    on_event
-        .send(IngestEvent::ExtractingZip)
-        .map_err(|e| e.to_string())?;
+    // Only emit when the percentage moves, so multi-GB exports don't flood the channel
+    let mut last_percent: Option<u8> = None;
+    let mut bytes_read: u64 = 0;
+    let source = ExportSource::open(&path)?;
+    let mut report = ImportReport::default();
+    let shards = source.read_conversation_shards(&mut |read, total| {
+        bytes_read = read;
+        let pct = percent(read, total);
+        if last_percent != Some(pct) {
+            last_percent = Some(pct);
+            let _ = on_event.send(IngestEvent::ExtractingZip {
+                bytes_read: read,
+                total_bytes: total,
+                percent: pct,
+            });
+        }
+    });
 
-    let reader = zip_reader::open_conversations_entry(&path)?;
+    let mut conversations: Vec<json_parser::ConversationExport> = Vec::new();
+    let mut file_hash = String::new();
+    let json_err = match shards {
+        Ok(shards) => {
+            file_hash = source::sha256_hex(shards.iter().map(|shard| shard.bytes.as_slice()));
+            for shard in &shards {
+                conversations.extend(json_parser::parse_conversations(&shard.name, &shard.bytes, &mut report));
+            }
+            // Only fall back when not a single conversation could be read from the JSON
+            match report.skipped.first() {
+                Some(issue) if conversations.is_empty() => Some(issue.reason.clone()),
+                _ => None,
+            }
+        }
+        Err(e) => Some(e),
+    };
 
-    on_event
-        .send(IngestEvent::ParsingConversations { processed: 0 })
-        .map_err(|e| e.to_string())?;
+    let recovered_from_html = json_err.is_some();
+    if let Some(json_err) = json_err {
+        let Some(html) = source.read_optional("chat.html")? else {
+            return Err(json_err);
+        };
+        report = ImportReport::default();
+        conversations = json_parser::conversations_from_chat_html(&html, &mut report)
+            .map_err(|html_err| format!("{json_err} (chat.html fallback: {html_err})"))?;
+        file_hash = source::sha256_hex([html.as_slice()]);
+    }
+    let account = source.account();
 
-    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let format = format::fingerprint(&source.entry_names(), &conversations);
 
-    let mut count: u32 = 0;
-    let mut earliest_year: i32 = i32::MAX;
-    let mut latest_year: i32 = i32::MIN;
+    let group_chats = read_optional(&source, "group_chats.json", group_chats::parse_group_chats, &mut report)?;
+    let group_chats_imported = group_chats.len() as u32;
+    let total = conversations.len() as u32 + group_chats_imported;
 
-    let conversations = json_parser::stream_conversations(reader)
-        .map_err(|e| format!("Failed to parse conversations.json: {e}"))?;
+    let ratings = read_optional(&source, "message_feedback.json", feedback::parse_feedback, &mut report)?;
+    let comparisons = read_optional(&source, "model_comparisons.json", feedback::parse_comparisons, &mut report)?;
+    let share_links = read_optional(&source, "shared_conversations.json", shares::parse_shared_conversations, &mut report)?;
 
-    for export in conversations {
+    on_event
+        .send(IngestEvent::ParsingConversations { processed: 0, total, percent: 0 })
+        .map_err(|e| e.to_string())?;
 
-        // Track year range from create_time (Unix timestamp)
-        if let Some(ts) = export.create_time {
-            // Rough conversion: ts / seconds_per_year + 1970
-            let year = (ts / 31_536_000.0) as i32 + 1970;
-            if year < earliest_year {
-                earliest_year = year;
-            }
-            if year > latest_year {
-                latest_year = year;
-            }
-        }
+    let mut count: u32 = 0;
+    let mut older_versions_skipped: u32 = 0;
+    let mut earliest_year: Option<i32> = None;
+    let mut latest_year: Option<i32> = None;
 
-        let record = normalizer::normalize(export);
+    let (source_id, exclusions) = {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        let source_path = std::fs::canonicalize(&path)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| path.clone());
+        (
+            db::upsert_import_source(&db, &file_hash, &source_path, &account, unix_now())
+                .map_err(|e| e.to_string())?,
+            db::get_exclusions(&db).map_err(|e| e.to_string())?,
+        )
+    };
 
-        db::insert_conversation(&db, &record).map_err(|e| e.to_string())?;
+    // Group chats go through the same path as regular conversations
+    let mut records = conversations.into_iter().map(normalizer::normalize).chain(group_chats).peekable();
+    while records.peek().is_some() {
+        {
+            let mut db = state.db.lock().map_err(|e| e.to_string())?;
+            // Dropping the transaction without commit (any `?` below) rolls back this batch
+            let tx = db.transaction().map_err(|e| e.to_string())?;
+            for record in records.by_ref().take(INGEST_BATCH_SIZE) {
+                // Track year range from create_time (Unix timestamp) in the user's zone
+                if let Some(ts) = record.created_at {
+                    let year = dates::year(ts, zone);
+                    earliest_year = Some(earliest_year.map_or(year, |y| y.min(year)));
+                    latest_year = Some(latest_year.map_or(year, |y| y.max(year)));
+                }
 
-        count += 1;
+                // Merging exports: a conversation already stored from a newer export is kept as is
+                if db::insert_conversation_if_newer(&tx, &record).map_err(|e| e.to_string())? {
+                    db::apply_exclusions(&tx, &record, &exclusions).map_err(|e| e.to_string())?;
+                } else {
+                    older_versions_skipped += 1;
+                }
+                db::insert_conversation_source(&tx, source_id, &record).map_err(|e| e.to_string())?;
 
-        // Emit progress every 50 conversations to avoid flooding the channel
-        if count % 50 == 0 {
-            on_event
-                .send(IngestEvent::ParsingConversations { processed: count })
-                .map_err(|e| e.to_string())?;
+                count += 1;
+            }
+            tx.commit().map_err(|e| e.to_string())?;
         }
+
+        on_event
+            .send(IngestEvent::ParsingConversations {
+                processed: count,
+                total,
+                percent: percent(count as u64, total as u64),
+            })
+            .map_err(|e| e.to_string())?;
     }
 
     on_event
         .send(IngestEvent::BuildingIndex)
         .map_err(|e| e.to_string())?;
+    let excluded = {
+        let mut db = state.db.lock().map_err(|e| e.to_string())?;
+        let tx = db.transaction().map_err(|e| e.to_string())?;
+        for rating in &ratings {
+            db::insert_feedback(&tx, rating).map_err(|e| e.to_string())?;
+        }
+        for comparison in &comparisons {
+            db::insert_comparison(&tx, comparison).map_err(|e| e.to_string())?;
+        }
+        for share in &share_links {
+            db::insert_share(&tx, share).map_err(|e| e.to_string())?;
+        }
 
-    // Clamp year range if no timestamps found
-    if earliest_year == i32::MAX {
-        earliest_year = 2020;
-    }
-    if latest_year == i32::MIN {
-        latest_year = 2024;
-    }
+        // New conversations may point at media another export already put in the store
+        db::update_media_refs(&tx).map_err(|e| e.to_string())?;
+        // Rules or private marks may have changed during the import; the saved ones decide again
+        let excluded = db::update_exclusions(&tx).map_err(|e| e.to_string())? as u32;
+
+        tx.commit().map_err(|e| e.to_string())?;
+        excluded
+    };
+
+    // Optional: keep the media in the app's store so exports work after the ZIP is gone
+    let media_stored = if store_media.unwrap_or(false) {
+        sources::store_media(&state, source_id, &source)? as u32
+    } else {
+        0
+    };
+
+    let elapsed = started_at.elapsed();
+    let secs = elapsed.as_secs_f64().max(0.001);
 
     on_event
         .send(IngestEvent::Complete {
             total: count,
             earliest_year,
             latest_year,
+            elapsed_ms: elapsed.as_millis() as u64,
+            bytes_read,
+            conversations_per_sec: count as f64 / secs,
+            megabytes_per_sec: bytes_read as f64 / 1_048_576.0 / secs,
+            feedback_imported: ratings.len() as u32,
+            comparisons_imported: comparisons.len() as u32,
+            group_chats_imported,
+            older_versions_skipped,
+            account: account.label().map(String::from),
+            shares_imported: share_links.len() as u32,
+            recovered_from_html,
+            media_stored,
+            excluded,
+            report,
+            format: Box::new(format),
         })
         .map_err(|e| e.to_string())?;
 
-    // Store ZIP path so export can extract media and other assets
-    {
-        let mut zp = state.zip_path.lock().map_err(|e| e.to_string())?;
-        *zp = Some(path);
-    }
-
     Ok(())
 }
+
+/// Reads and parses an optional export file. Missing files yield no records; a file that
+/// fails to parse yields none either and is listed in `report` as a skipped shard. Only an
+/// unreadable source is an error.
+fn read_optional<T>(
+    source: &ExportSource,
+    basename: &str,
+    parse: fn(&[u8]) -> Result<Vec<T>, String>,
+    report: &mut ImportReport,
+) -> Result<Vec<T>, String> {
+    let Some(bytes) = source.read_optional(basename)? else {
+        return Ok(Vec::new());
+    };
+    Ok(parse(&bytes).unwrap_or_else(|e| {
+        report.skip(None, basename, e);
+        Vec::new()
+    }))
+}
+
+pub(crate) fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Integer percentage of `done` over `total`, clamped to 0–100 (100 when total is 0).
+fn percent(done: u64, total: u64) -> u8 {
+    if total == 0 {
+        return 100;
+    }
+    (done.saturating_mul(100) / total).min(100) as u8
+}
@@ -2,6 +2,8 @@ use keyring::Entry;
 
 const SERVICE: &str = "com.darrellwhitelaw.chatgpt-to-claude";
 const USER: &str = "anthropic-api-key";
+/// Raw SQLCipher key of conversations.db, as 64 hex digits (see `store::encryption`).
+const DATABASE_KEY_USER: &str = "database-key";
 
 #[tauri::command]
 pub fn get_api_key() -> Result<String, String> {
@@ -20,3 +22,26 @@ pub fn delete_api_key() -> Result<(), String> {
     let entry = Entry::new(SERVICE, USER).map_err(|e| e.to_string())?;
     entry.delete_credential().map_err(|e| e.to_string())
 }
+
+/// The database key, or `None` when the database is not encrypted.
+pub(crate) fn get_database_key() -> Result<Option<String>, String> {
+    let entry = Entry::new(SERVICE, DATABASE_KEY_USER).map_err(|e| e.to_string())?;
+    match entry.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+pub(crate) fn set_database_key(key: &str) -> Result<(), String> {
+    let entry = Entry::new(SERVICE, DATABASE_KEY_USER).map_err(|e| e.to_string())?;
+    entry.set_password(key).map_err(|e| e.to_string())
+}
+
+pub(crate) fn delete_database_key() -> Result<(), String> {
+    let entry = Entry::new(SERVICE, DATABASE_KEY_USER).map_err(|e| e.to_string())?;
+    match entry.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
@@ -1,4 +1,14 @@
+pub mod bundle;
 pub mod cluster;
+pub mod encryption;
+pub mod exclusions;
 pub mod export;
 pub mod ingest;
 pub mod keychain;
+pub mod obsidian;
+pub mod packs;
+pub mod profile;
+pub mod projects;
+pub mod redaction;
+pub mod sources;
+pub mod usage;
@@ -0,0 +1,488 @@
+use crate::commands::export::{date_line, front_matter, render_transcript, share_note, DateBucket, ExportOptions, ExportedMedia};
+use crate::commands::redaction;
+use crate::dates::{self, Zone};
+use crate::redact::Redactor;
+use crate::store::db;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Hidden file in the vault root mapping conversation ids to the note paths they were
+/// first written to. Re-exports keep those file names, so links and backlinks never break
+/// even if a conversation is renamed in ChatGPT. A note whose folder changes (it joined a
+/// project, moved to another year bucket) is moved there with the user's notes in it.
+const MANIFEST_FILE: &str = ".chatgpt-vault.json";
+
+/// Folder for the Map-of-Content pages.
+const MAPS_DIR: &str = "Maps";
+
+/// Everything after this line in a conversation note is carried over on re-export.
+const NOTES_MARKER: &str = "%% Your notes below this line are kept on re-export %%";
+
+/// Related conversations linked from each note.
+const MAX_RELATED: usize = 5;
+
+#[derive(Default, Serialize, Deserialize)]
+struct VaultManifest {
+    /// Conversation id → note path relative to the vault root, `/`-separated.
+    notes: BTreeMap<String, String>,
+}
+
+/// Writes conversations as an Obsidian vault rooted at `root`:
+///
+///   {folder}/{Title} ({id}).md ← one note per conversation: front matter, transcript,
+///                                related conversations, map links and a notes section
+///                                (folder chosen by `options.layout`)
+///   Maps/Home.md               ← links to every map below
+///   Maps/Year 2024.md          ← Map of Content per year
+///   Maps/Cluster - {label}.md  ← per AI cluster (after clustering has run)
+///   Maps/GPT - {gizmo}.md      ← per custom GPT / project gizmo
+///
+/// Images, recordings and attachments are embedded as `![[folder/file]]` from the typed
+/// folders written by `export_media`.
+/// Returns the number of conversation notes written.
+pub fn write_vault(
+    db: &Mutex<Connection>,
+    root: &Path,
+    conversations: &[db::ExportRow],
+    zone: Zone,
+    options: &ExportOptions,
+    media: &ExportedMedia,
+    redactor: &Redactor,
+) -> Result<usize, String> {
+    let bucket_by = options.bucket_by;
+    let manifest_path = root.join(MANIFEST_FILE);
+    let mut manifest: VaultManifest = std::fs::read(&manifest_path)
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default();
+
+    // Notes from earlier exports of conversations excluded since then are removed
+    let excluded = {
+        let conn = db.lock().map_err(|e| e.to_string())?;
+        db::get_excluded_ids(&conn).map_err(|e| e.to_string())?
+    };
+    manifest.notes.retain(|id, path| {
+        if excluded.contains(id) {
+            let _ = std::fs::remove_file(root.join(&*path));
+        }
+        !excluded.contains(id)
+    });
+
+    // --- Step 1: settle every note path before writing, so links can point anywhere ---
+    let mut taken: HashSet<String> = manifest.notes.values().map(|p| p.to_lowercase()).collect();
+    let mut paths: Vec<String> = Vec::with_capacity(conversations.len());
+    for conv in conversations {
+        let title = note_name(conv.title.as_deref().unwrap_or("Untitled"));
+        let short_id: String = conv.id.chars().take(8).collect();
+        let folder = options.layout.folder_for(conv, zone, bucket_by);
+        let folder = folder.to_string_lossy().replace('\\', "/");
+        let path = match manifest.notes.get(&conv.id).cloned() {
+            Some(existing) => {
+                let (old_folder, file_name) = existing.rsplit_once('/').unwrap_or(("", existing.as_str()));
+                // With redaction the name is rebuilt from the redacted title: a note first
+                // written without redaction would otherwise keep the real title in its file name
+                let rename = redactor.is_active() && !file_name.starts_with(&format!("{title} ({short_id}"));
+                if !rename && old_folder == folder {
+                    existing
+                } else {
+                    taken.remove(&existing.to_lowercase());
+                    // A note whose folder changed follows it under the same file name
+                    let kept = format!("{folder}/{file_name}");
+                    let path = if !rename && taken.insert(kept.to_lowercase()) {
+                        kept
+                    } else {
+                        unique_note_path(&mut taken, &folder, &title, &short_id)
+                    };
+                    move_note(root, &existing, &path)?;
+                    manifest.notes.insert(conv.id.clone(), path.clone());
+                    path
+                }
+            }
+            None => {
+                let path = unique_note_path(&mut taken, &folder, &title, &short_id);
+                manifest.notes.insert(conv.id.clone(), path.clone());
+                path
+            }
+        };
+        paths.push(path);
+    }
+
+    // --- Step 2: group conversations for the maps and related links ---
+    let mut by_year: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    let mut by_cluster: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    let mut by_gizmo: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, conv) in conversations.iter().enumerate() {
+        by_year.entry(year_of(conv, zone, bucket_by)).or_default().push(i);
+        if let Some(label) = conv.cluster_label.as_deref().filter(|l| !l.is_empty()) {
+            by_cluster.entry(label.to_string()).or_default().push(i);
+        }
+        if let Some(gizmo) = conv.gizmo_id.as_deref() {
+            by_gizmo.entry(gizmo.to_string()).or_default().push(i);
+        }
+    }
+
+    // --- Step 3: conversation notes ---
+    let mut written = 0;
+    for (i, conv) in conversations.iter().enumerate() {
+        let (mut messages, models) = {
+            let conn = db.lock().map_err(|e| e.to_string())?;
+            (
+                db::get_messages(&conn, &conv.id).map_err(|e| e.to_string())?,
+                db::get_model_slugs(&conn, &conv.id).map_err(|e| e.to_string())?,
+            )
+        };
+        if redactor.is_active() {
+            redaction::redact_messages(redactor, &mut messages);
+        }
+        let title = conv.title.as_deref().unwrap_or("Untitled");
+
+        let mut content = front_matter(conv, &models, zone);
+        content.push_str(&format!("# {}\n\n{}{}---\n\n", title, date_line(conv, zone), share_note(conv)));
+        content.push_str(&render_transcript(&messages, &conv.full_text, zone, &options.include, &|id| media.wikilink(id)));
+        content.push_str("\n\n---\n\n");
+
+        // Related: same cluster (or same GPT when unclustered), closest in time
+        let group = conv
+            .cluster_label
+            .as_ref()
+            .and_then(|l| by_cluster.get(l))
+            .or_else(|| conv.gizmo_id.as_ref().and_then(|g| by_gizmo.get(g)));
+        let related = group.map(|g| closest(i, g, conversations)).unwrap_or_default();
+        if !related.is_empty() {
+            content.push_str("## Related\n\n");
+            for j in related {
+                content.push_str(&format!("- {}\n", wikilink(&paths[j], &conversations[j])));
+            }
+            content.push('\n');
+        }
+
+        let mut maps = vec![format!("[[Year {}]]", year_of(conv, zone, bucket_by))];
+        if let Some(label) = conv.cluster_label.as_deref().filter(|l| !l.is_empty()) {
+            maps.push(format!("[[{}]]", cluster_map(label)));
+        }
+        if let Some(gizmo) = conv.gizmo_id.as_deref() {
+            maps.push(format!("[[{}]]", gizmo_map(gizmo)));
+        }
+        content.push_str(&format!("Maps: {}\n\n", maps.join(" · ")));
+
+        // Notes: regenerated heading, user content below the marker preserved
+        let note_path = root.join(&paths[i]);
+        let kept = std::fs::read_to_string(&note_path)
+            .ok()
+            .and_then(|old| old.split_once(NOTES_MARKER).map(|(_, notes)| notes.to_string()))
+            .unwrap_or_else(|| "\n".to_string());
+        content.push_str(&format!("## Notes\n\n{NOTES_MARKER}{kept}"));
+
+        if let Some(parent) = note_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(&note_path, content).map_err(|e| e.to_string())?;
+        written += 1;
+    }
+
+    // --- Step 4: Map-of-Content pages ---
+    let maps_dir = root.join(MAPS_DIR);
+    std::fs::create_dir_all(&maps_dir).map_err(|e| e.to_string())?;
+
+    let mut written_maps: HashSet<String> = HashSet::from(["Home".to_string()]);
+    let mut home = String::from("---\ntype: moc\n---\n\n# ChatGPT History\n\n## Years\n\n");
+    for (year, members) in by_year.iter().rev() {
+        let name = format!("Year {year}");
+        write_map(&maps_dir, &name, &format!("Conversations from {year}"), members, conversations, &paths, zone)?;
+        home.push_str(&format!("- [[{}]] ({})\n", name, members.len()));
+        written_maps.insert(name);
+    }
+    if !by_cluster.is_empty() {
+        home.push_str("\n## Clusters\n\n");
+        for (label, members) in &by_cluster {
+            let name = cluster_map(label);
+            write_map(&maps_dir, &name, label, members, conversations, &paths, zone)?;
+            home.push_str(&format!("- [[{}]] ({})\n", name, members.len()));
+            written_maps.insert(name);
+        }
+    }
+    if !by_gizmo.is_empty() {
+        home.push_str("\n## Custom GPTs\n\n");
+        for (gizmo, members) in &by_gizmo {
+            let name = gizmo_map(gizmo);
+            write_map(&maps_dir, &name, &format!("Custom GPT `{gizmo}`"), members, conversations, &paths, zone)?;
+            home.push_str(&format!("- [[{}]] ({})\n", name, members.len()));
+            written_maps.insert(name);
+        }
+    }
+    std::fs::write(maps_dir.join("Home.md"), home).map_err(|e| e.to_string())?;
+    remove_stale_maps(&maps_dir, &written_maps)?;
+
+    let json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    std::fs::write(&manifest_path, json).map_err(|e| e.to_string())?;
+
+    Ok(written)
+}
+
+/// `{folder}/{title} ({short_id}).md`, numbered `({short_id}-2)`, `-3`, … when a note of
+/// that name (compared case-insensitively) exists. Marks the path as taken.
+fn unique_note_path(taken: &mut HashSet<String>, folder: &str, title: &str, short_id: &str) -> String {
+    let mut path = format!("{folder}/{title} ({short_id}).md");
+    let mut n = 2;
+    while !taken.insert(path.to_lowercase()) {
+        path = format!("{folder}/{title} ({short_id}-{n}).md");
+        n += 1;
+    }
+    path
+}
+
+/// Moves a note written by an earlier export to `to`, with the user's notes in it. A note
+/// the user deleted is simply written afresh.
+fn move_note(root: &Path, from: &str, to: &str) -> Result<(), String> {
+    let (from, to) = (root.join(from), root.join(to));
+    if !from.exists() {
+        return Ok(());
+    }
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    std::fs::rename(&from, &to).map_err(|e| format!("Cannot move {} to {}: {e}", from.display(), to.display()))
+}
+
+/// Deletes map pages from earlier exports that this one did not write (a cluster or GPT
+/// that is gone, or whose conversations are all excluded now), so no map links to removed
+/// notes. Pages the user made in `Maps/`, without `type: moc` front matter, are kept.
+fn remove_stale_maps(maps_dir: &Path, written: &HashSet<String>) -> Result<(), String> {
+    for entry in std::fs::read_dir(maps_dir).map_err(|e| e.to_string())?.flatten() {
+        let path = entry.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()).and_then(|n| n.strip_suffix(".md")) else {
+            continue;
+        };
+        if written.contains(name) {
+            continue;
+        }
+        let is_map = std::fs::read_to_string(&path).is_ok_and(|content| {
+            content
+                .strip_prefix("---\n")
+                .and_then(|rest| rest.split_once("\n---\n"))
+                .is_some_and(|(front_matter, _)| front_matter.lines().any(|line| line.trim() == "type: moc"))
+        });
+        if is_map {
+            std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes one Map-of-Content page listing `members`, newest first.
+fn write_map(
+    maps_dir: &Path,
+    name: &str,
+    heading: &str,
+    members: &[usize],
+    conversations: &[db::ExportRow],
+    paths: &[String],
+    zone: Zone,
+) -> Result<(), String> {
+    let mut content = format!(
+        "---\ntype: moc\nconversations: {}\n---\n\n# {}\n\n",
+        members.len(),
+        heading
+    );
+    for &i in members.iter().rev() {
+        let conv = &conversations[i];
+        let date = conv.created_at.map(|t| dates::date_str(t, zone)).unwrap_or_default();
+        content.push_str(&format!("- {} · {}\n", wikilink(&paths[i], conv), date));
+    }
+    std::fs::write(maps_dir.join(format!("{name}.md")), content).map_err(|e| e.to_string())
+}
+
+/// Up to `MAX_RELATED` other members of `group`, nearest in creation time to conversation `i`.
+fn closest(i: usize, group: &[usize], conversations: &[db::ExportRow]) -> Vec<usize> {
+    let at = conversations[i].created_at.unwrap_or(0);
+    let mut others: Vec<usize> = group.iter().copied().filter(|&j| j != i).collect();
+    others.sort_by_key(|&j| (conversations[j].created_at.unwrap_or(0) - at).abs());
+    others.truncate(MAX_RELATED);
+    others
+}
+
+/// `[[note name|Title]]` for a conversation note path.
+fn wikilink(path: &str, conv: &db::ExportRow) -> String {
+    let stem = path
+        .rsplit('/')
+        .next()
+        .unwrap_or(path)
+        .trim_end_matches(".md");
+    let alias: String = conv
+        .title
+        .as_deref()
+        .unwrap_or("Untitled")
+        .chars()
+        .map(|c| if matches!(c, '|' | '[' | ']') { ' ' } else { c })
+        .collect();
+    format!("[[{}|{}]]", stem, alias.trim())
+}
+
+fn year_of(conv: &db::ExportRow, zone: Zone, bucket_by: DateBucket) -> String {
+    bucket_by
+        .timestamp(conv)
+        .map(|ts| dates::year(ts, zone).to_string())
+        .unwrap_or_else(|| "Unknown".to_string())
+}
+
+fn cluster_map(label: &str) -> String {
+    format!("Cluster - {}", note_name(label))
+}
+
+fn gizmo_map(gizmo: &str) -> String {
+    format!("GPT - {}", note_name(gizmo))
+}
+
+/// Makes `s` usable as an Obsidian note name: drops characters Obsidian rejects in
+/// file names or link targets and collapses whitespace.
+fn note_name(s: &str) -> String {
+    let cleaned: String = s
+        .chars()
+        .map(|c| {
+            if matches!(c, '\\' | '/' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']')
+                || c.is_control()
+            {
+                ' '
+            } else {
+                c
+            }
+        })
+        .collect();
+    let name = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    let name: String = name.chars().take(80).collect();
+    let name = name.trim_start_matches('.').trim().to_string();
+    if name.is_empty() {
+        "Untitled".to_string()
+    } else {
+        name
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::export::FolderLayout;
+    use crate::redact::RedactionPolicy;
+
+    fn temp_vault(name: &str) -> std::path::PathBuf {
+        let root = std::env::temp_dir().join(format!("vault-test-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn database() -> Mutex<Connection> {
+        let conn = Connection::open_in_memory().unwrap();
+        db::init_schema(&conn).unwrap();
+        Mutex::new(conn)
+    }
+
+    fn manifest(root: &Path) -> VaultManifest {
+        serde_json::from_slice(&std::fs::read(root.join(MANIFEST_FILE)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn redaction_renames_notes_named_after_the_real_title() {
+        let root = temp_vault("redacted");
+        let old_path = "2024/Trip with Alice (c1234567).md";
+        std::fs::create_dir_all(root.join("2024")).unwrap();
+        std::fs::write(root.join(old_path), format!("# Trip with Alice\n\n{NOTES_MARKER}\nMy own notes\n")).unwrap();
+        std::fs::write(
+            root.join(MANIFEST_FILE),
+            serde_json::json!({ "notes": { "c1234567-89": old_path } }).to_string(),
+        )
+        .unwrap();
+
+        let policy = RedactionPolicy { deny: vec!["Alice".to_string()], ..Default::default() };
+        let redactor = Redactor::new(&policy, Vec::new()).unwrap();
+        let conv = db::ExportRow {
+            id: "c1234567-89".to_string(),
+            title: redactor.redact_opt(Some("Trip with Alice")),
+            created_at: Some(1_717_200_000),
+            ..Default::default()
+        };
+        let options = ExportOptions::default();
+        let written = write_vault(&database(), &root, &[conv], Zone::Utc, &options, &ExportedMedia::default(), &redactor)
+            .unwrap();
+        assert_eq!(written, 1);
+
+        let new_path = manifest(&root).notes["c1234567-89"].clone();
+        assert!(!new_path.contains("Alice"), "{new_path}");
+        assert!(new_path.starts_with("2024/") && new_path.ends_with("(c1234567).md"), "{new_path}");
+        assert!(!root.join(old_path).exists());
+        let note = std::fs::read_to_string(root.join(&new_path)).unwrap();
+        assert!(!note.contains("Alice"));
+        assert!(note.ends_with(&format!("{NOTES_MARKER}\nMy own notes\n")));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn maps_of_groups_that_are_gone_are_removed() {
+        let root = temp_vault("maps");
+        let maps = root.join(MAPS_DIR);
+        std::fs::create_dir_all(&maps).unwrap();
+        let stale = "---\ntype: moc\nconversations: 1\n---\n\n# Secret\n\n- [[2023/Secret (c9999999)|Secret]]\n";
+        std::fs::write(maps.join("Cluster - Secret.md"), stale).unwrap();
+        std::fs::write(maps.join("GPT - g-old.md"), stale).unwrap();
+        std::fs::write(maps.join("Year 2023.md"), stale).unwrap();
+        std::fs::write(maps.join("My reading list.md"), "# Mine\n\ntype: moc\n").unwrap();
+        std::fs::write(maps.join("Tagged.md"), "---\ntags: [moc]\n---\n\n# Mine too\n").unwrap();
+
+        let conv = db::ExportRow {
+            id: "c1234567-89".to_string(),
+            title: Some("Packing list".to_string()),
+            created_at: Some(1_717_200_000),
+            cluster_label: Some("Travel".to_string()),
+            ..Default::default()
+        };
+        let redactor = Redactor::new(&RedactionPolicy::default(), Vec::new()).unwrap();
+        let options = ExportOptions::default();
+        write_vault(&database(), &root, &[conv], Zone::Utc, &options, &ExportedMedia::default(), &redactor).unwrap();
+
+        let mut pages: Vec<String> = std::fs::read_dir(&maps)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect();
+        pages.sort();
+        assert_eq!(pages, ["Cluster - Travel.md", "Home.md", "My reading list.md", "Tagged.md", "Year 2024.md"]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn notes_follow_their_folder_under_the_same_file_name() {
+        let root = temp_vault("moved");
+        // Written while the conversation was only in its cluster, under an older title
+        let old_path = "Travel/Old title (c1234567).md";
+        std::fs::create_dir_all(root.join("Travel")).unwrap();
+        std::fs::write(root.join(old_path), format!("# Old title\n\n{NOTES_MARKER}\nMy own notes\n")).unwrap();
+        std::fs::write(
+            root.join(MANIFEST_FILE),
+            serde_json::json!({ "notes": { "c1234567-89": old_path } }).to_string(),
+        )
+        .unwrap();
+
+        let conv = db::ExportRow {
+            id: "c1234567-89".to_string(),
+            title: Some("New title".to_string()),
+            created_at: Some(1_717_200_000),
+            cluster_label: Some("Travel".to_string()),
+            project_name: Some("Travel".to_string()),
+            ..Default::default()
+        };
+        let options = ExportOptions { layout: FolderLayout::Cluster, ..Default::default() };
+        let redactor = Redactor::new(&RedactionPolicy::default(), Vec::new()).unwrap();
+        write_vault(&database(), &root, &[conv], Zone::Utc, &options, &ExportedMedia::default(), &redactor).unwrap();
+
+        let new_path = manifest(&root).notes["c1234567-89"].clone();
+        assert_eq!(new_path, "Project - Travel/Old title (c1234567).md");
+        assert!(!root.join(old_path).exists());
+        let note = std::fs::read_to_string(root.join(&new_path)).unwrap();
+        assert!(note.ends_with(&format!("{NOTES_MARKER}\nMy own notes\n")));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
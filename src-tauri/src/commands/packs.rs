@@ -0,0 +1,418 @@
+use crate::commands::export::{archive_root, folder_name, render_transcript};
+use crate::commands::redaction;
+use crate::dates::{self, Zone};
+use crate::pipeline::traversal::InclusionPolicy;
+use crate::redact::RedactionPolicy;
+use crate::store::db;
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tauri::State;
+
+/// Folder inside the archive root holding one pack per cluster.
+const PACKS_DIR: &str = "Claude Projects";
+
+/// Default upper bound for one knowledge file. Well under a Project's context window,
+/// so Claude can retrieve several files for one question.
+const DEFAULT_FILE_TOKEN_BUDGET: u32 = 50_000;
+
+/// Same heuristic as `normalizer::normalize`: ~4 chars per token.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Options for `generate_project_packs`. Every field has a default.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PackOptions {
+    /// Maximum estimated tokens per knowledge file. Longer conversations are split into parts.
+    pub file_token_budget: u32,
+    /// Optional cap for a whole pack. The most recently active conversations are kept;
+    /// the rest are listed as omitted in the manifest.
+    pub pack_token_budget: Option<u32>,
+    /// IANA zone name or "UTC" for dates; `None` uses the OS zone.
+    pub time_zone: Option<String>,
+    /// Which roles and how much tool activity the packed transcripts show.
+    pub include: InclusionPolicy,
+    /// Secrets and personal data to mask in the packs.
+    pub redaction: RedactionPolicy,
+}
+
+impl Default for PackOptions {
+    fn default() -> Self {
+        PackOptions {
+            file_token_budget: DEFAULT_FILE_TOKEN_BUDGET,
+            pack_token_budget: None,
+            time_zone: None,
+            include: InclusionPolicy::default(),
+            redaction: RedactionPolicy::default(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PackResult {
+    pub packs: usize,
+    pub files_written: usize,
+    pub folder_path: String,
+}
+
+/// `manifest.json` — what each knowledge file of a pack contains.
+#[derive(Serialize)]
+struct PackManifest {
+    cluster: String,
+    file_token_budget: u32,
+    pack_token_budget: Option<u32>,
+    files: Vec<ManifestFile>,
+    /// Conversations left out because the pack budget was reached.
+    omitted: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize)]
+struct ManifestFile {
+    file: String,
+    estimated_tokens: u32,
+    conversations: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize, Clone)]
+struct ManifestEntry {
+    id: String,
+    title: String,
+    created: Option<String>,
+    estimated_tokens: u32,
+    /// `[part, of]` when a conversation was split across files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    part: Option<[usize; 2]>,
+}
+
+/// One conversation (or part of one) ready to be placed into a knowledge file.
+struct Section {
+    entry: ManifestEntry,
+    text: String,
+}
+
+/// Builds a Claude Project "knowledge pack" for every project and AI cluster:
+///
+///   ~/Documents/ChatGPT History/Claude Projects/{Cluster}/ (or Project - {Name}/)
+///     project_instructions.md  ← paste into the Project's custom instructions
+///     knowledge-01.md …        ← upload as Project knowledge; each under the token budget
+///     manifest.json            ← which conversations (and parts) each file holds
+///
+/// Conversations in a project are packed with it rather than with their cluster.
+/// Conversations in neither are not packed.
+#[tauri::command]
+pub async fn generate_project_packs(
+    state: State<'_, AppState>,
+    options: Option<PackOptions>,
+) -> Result<PackResult, String> {
+    let options = options.unwrap_or_default();
+    let zone = Zone::parse(options.time_zone.as_deref())?;
+    let file_budget = options.file_token_budget.max(1_000);
+
+    let mut conversations = {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        db::get_conversations_for_export(&conn, db::ConversationSort::LastActive)
+            .map_err(|e| e.to_string())?
+    };
+    let redactor = redaction::redactor(&state, &options.redaction)?;
+    if redactor.is_active() {
+        for conv in &mut conversations {
+            redaction::redact_conversation(&redactor, conv);
+        }
+    }
+
+    // Most recently active first within each cluster (query order)
+    let mut clusters: BTreeMap<db::Group, Vec<&db::ExportRow>> = BTreeMap::new();
+    for conv in &conversations {
+        if let Some(group) = conv.group() {
+            clusters.entry(group).or_default().push(conv);
+        }
+    }
+
+    if clusters.is_empty() {
+        return Err("No clusters or projects found — run clustering or assign conversations to projects first".to_string());
+    }
+
+    let packs_root = archive_root()?.join(PACKS_DIR);
+    let mut files_written = 0;
+
+    for (group, members) in &clusters {
+        let label = group.label();
+        let dir = packs_root.join(folder_name(&group.folder_label()));
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        clear_knowledge_files(&dir)?;
+
+        // Render sections, respecting the pack budget
+        let mut sections: Vec<Section> = Vec::new();
+        let mut omitted: Vec<ManifestEntry> = Vec::new();
+        let mut pack_tokens: u32 = 0;
+        for conv in members {
+            let mut messages = {
+                let conn = state.db.lock().map_err(|e| e.to_string())?;
+                db::get_messages(&conn, &conv.id).map_err(|e| e.to_string())?
+            };
+            if redactor.is_active() {
+                redaction::redact_messages(&redactor, &mut messages);
+            }
+            let transcript = render_transcript(&messages, &conv.full_text, zone, &options.include, &|_| None);
+            let entry = ManifestEntry {
+                id: conv.id.clone(),
+                title: conv.title.clone().unwrap_or_else(|| "Untitled".to_string()),
+                created: conv.created_at.map(|t| dates::date_str(t, zone)),
+                estimated_tokens: estimate_tokens(&transcript),
+                part: None,
+            };
+
+            if let Some(budget) = options.pack_token_budget {
+                if pack_tokens + entry.estimated_tokens > budget {
+                    omitted.push(entry);
+                    continue;
+                }
+            }
+            pack_tokens += entry.estimated_tokens;
+            sections.extend(conversation_sections(conv, entry, &transcript, file_budget));
+        }
+
+        // Fill knowledge files greedily up to the file budget
+        let mut files: Vec<(String, ManifestFile)> = Vec::new();
+        let mut current = String::new();
+        let mut current_file = ManifestFile { file: String::new(), estimated_tokens: 0, conversations: Vec::new() };
+        for section in sections {
+            let tokens = estimate_tokens(&section.text);
+            if !current.is_empty() && current_file.estimated_tokens + tokens > file_budget {
+                files.push((std::mem::take(&mut current), std::mem::replace(
+                    &mut current_file,
+                    ManifestFile { file: String::new(), estimated_tokens: 0, conversations: Vec::new() },
+                )));
+            }
+            current.push_str(&section.text);
+            current_file.estimated_tokens += tokens;
+            current_file.conversations.push(section.entry);
+        }
+        if !current.is_empty() {
+            files.push((current, current_file));
+        }
+
+        let file_count = files.len();
+        let mut manifest_files = Vec::with_capacity(file_count);
+        for (i, (body, mut file)) in files.into_iter().enumerate() {
+            file.file = format!("knowledge-{:02}.md", i + 1);
+            let content = format!(
+                "# {} — knowledge file {} of {}\n\n\
+                 _Past ChatGPT conversations about {}. See manifest.json for the full contents list._\n\n---\n\n{}",
+                label,
+                i + 1,
+                file_count,
+                label,
+                body
+            );
+            std::fs::write(dir.join(&file.file), content).map_err(|e| e.to_string())?;
+            files_written += 1;
+            manifest_files.push(file);
+        }
+
+        write_project_instructions(&dir, label, members)?;
+        files_written += 1;
+
+        let manifest = PackManifest {
+            cluster: label.to_string(),
+            file_token_budget: file_budget,
+            pack_token_budget: options.pack_token_budget,
+            files: manifest_files,
+            omitted,
+        };
+        let json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+        std::fs::write(dir.join("manifest.json"), json).map_err(|e| e.to_string())?;
+        files_written += 1;
+    }
+    redaction::save_tokens(&state, &redactor)?;
+
+    Ok(PackResult {
+        packs: clusters.len(),
+        files_written,
+        folder_path: packs_root.to_string_lossy().to_string(),
+    })
+}
+
+/// Renders a conversation as one or more knowledge-file sections, each within `file_budget`.
+fn conversation_sections(
+    conv: &db::ExportRow,
+    entry: ManifestEntry,
+    transcript: &str,
+    file_budget: u32,
+) -> Vec<Section> {
+    let summary = conv
+        .summary
+        .as_deref()
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| format!("> {}\n\n", s.trim().replace('\n', " ")))
+        .unwrap_or_default();
+    let date = entry.created.as_deref().map(|d| format!(" · {d}")).unwrap_or_default();
+
+    // Leave room for the heading and summary in every part
+    let overhead = entry.title.len() + summary.len() + 64;
+    let max_chars = (file_budget as usize * CHARS_PER_TOKEN).saturating_sub(overhead).max(1_000);
+    let parts = split_text(transcript, max_chars);
+    let of = parts.len();
+
+    parts
+        .into_iter()
+        .enumerate()
+        .map(|(i, part)| {
+            let (heading, part_no) = if of > 1 {
+                (format!("## {} (part {} of {}){}\n\n", entry.title, i + 1, of, date), Some([i + 1, of]))
+            } else {
+                (format!("## {}{}\n\n", entry.title, date), None)
+            };
+            let text = format!("{}{}{}\n\n---\n\n", heading, if i == 0 { summary.as_str() } else { "" }, part);
+            Section {
+                entry: ManifestEntry {
+                    part: part_no,
+                    estimated_tokens: estimate_tokens(&text),
+                    ..entry.clone()
+                },
+                text,
+            }
+        })
+        .collect()
+}
+
+/// Splits `text` into chunks of at most `max_chars` bytes, preferring paragraph breaks.
+fn split_text(text: &str, max_chars: usize) -> Vec<String> {
+    if text.len() <= max_chars {
+        return vec![text.to_string()];
+    }
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for para in text.split("\n\n") {
+        if !current.is_empty() && current.len() + para.len() + 2 > max_chars {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if para.len() > max_chars {
+            // A single huge paragraph (e.g. pasted logs): hard-split on char boundaries
+            let mut rest = para;
+            while rest.len() > max_chars {
+                let mut cut = max_chars;
+                while !rest.is_char_boundary(cut) {
+                    cut -= 1;
+                }
+                chunks.push(rest[..cut].to_string());
+                rest = &rest[cut..];
+            }
+            current.push_str(rest);
+        } else {
+            if !current.is_empty() {
+                current.push_str("\n\n");
+            }
+            current.push_str(para);
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Writes `project_instructions.md` from the `instructions` clustering extracted,
+/// most frequently seen first.
+fn write_project_instructions(dir: &Path, label: &str, members: &[&db::ExportRow]) -> Result<(), String> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for conv in members {
+        if let Some(text) = conv.instructions.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+            match counts.iter_mut().find(|(t, _)| t.eq_ignore_ascii_case(text)) {
+                Some((_, n)) => *n += 1,
+                None => counts.push((text.to_string(), 1)),
+            }
+        }
+    }
+    counts.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+
+    let mut content = format!(
+        "# Project instructions — {label}\n\n\
+         This project holds {n} of my past ChatGPT conversations about **{label}**, \
+         uploaded as knowledge files. Use them to pick up where I left off: prefer decisions \
+         and conventions from recent conversations, and tell me when older ones disagree.\n\n",
+        n = members.len()
+    );
+    content.push_str("## How I like to work\n\n");
+    if counts.is_empty() {
+        content.push_str("_No explicit instructions were found in these conversations._\n");
+    } else {
+        for (text, n) in &counts {
+            let seen = if *n > 1 { format!(" _(in {n} conversations)_") } else { String::new() };
+            content.push_str(&format!("- {}{}\n", text.replace('\n', " "), seen));
+        }
+    }
+
+    std::fs::write(dir.join("project_instructions.md"), content).map_err(|e| e.to_string())
+}
+
+/// Removes knowledge files from a previous run so a smaller pack doesn't leave stale parts.
+fn clear_knowledge_files(dir: &Path) -> Result<(), String> {
+    for entry in std::fs::read_dir(dir).map_err(|e| e.to_string())?.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with("knowledge-") && name.ends_with(".md") {
+            std::fs::remove_file(entry.path()).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+fn estimate_tokens(text: &str) -> u32 {
+    (text.len() / CHARS_PER_TOKEN) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str) -> ManifestEntry {
+        ManifestEntry { id: "c1".into(), title: title.into(), created: None, estimated_tokens: 0, part: None }
+    }
+
+    #[test]
+    fn split_text_keeps_short_text_whole() {
+        assert_eq!(split_text("", 10), vec![""]);
+        assert_eq!(split_text("0123456789", 10), vec!["0123456789"]);
+    }
+
+    #[test]
+    fn split_text_breaks_at_paragraphs() {
+        // "aaaa\n\nbbbb" is exactly 10 bytes; one more paragraph doesn't fit
+        let chunks = split_text("aaaa\n\nbbbb\n\ncccc", 10);
+        assert_eq!(chunks, vec!["aaaa\n\nbbbb", "cccc"]);
+        assert!(chunks.iter().all(|c| c.len() <= 10));
+    }
+
+    #[test]
+    fn split_text_hard_splits_long_paragraphs_on_char_boundaries() {
+        // 'é' is two bytes, so a cut at byte 5 would land inside one
+        let text = "éééééé";
+        let chunks = split_text(text, 5);
+        assert_eq!(chunks, vec!["éé", "éé", "éé"]);
+        assert_eq!(chunks.concat(), text);
+
+        let chunks = split_text(&format!("intro\n\n{}", "x".repeat(25)), 10);
+        assert_eq!(chunks, vec!["intro", "xxxxxxxxxx", "xxxxxxxxxx", "xxxxx"]);
+    }
+
+    #[test]
+    fn tiny_budgets_are_floored_to_a_thousand_chars() {
+        let conv = db::ExportRow { id: "c1".into(), ..Default::default() };
+        let paragraph = "word ".repeat(100);
+        let transcript = [paragraph.trim_end(); 10].join("\n\n");
+
+        // A one-token budget still packs 1,000 chars per part (two 499-byte paragraphs and
+        // their separator) instead of splitting every character off
+        let sections = conversation_sections(&conv, entry("Title"), &transcript, 1);
+        assert_eq!(sections.len(), 5);
+        assert!(sections.iter().all(|s| s.text.len() < 1_100));
+        assert_eq!(sections[0].entry.part, Some([1, sections.len()]));
+        assert!(sections[0].text.starts_with("## Title (part 1 of"));
+
+        let whole = conversation_sections(&conv, entry("Title"), &transcript, DEFAULT_FILE_TOKEN_BUDGET);
+        assert_eq!(whole.len(), 1);
+        assert_eq!(whole[0].entry.part, None);
+    }
+}
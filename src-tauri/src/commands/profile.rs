@@ -0,0 +1,103 @@
+use crate::dates::{self, Zone};
+use crate::pipeline::profile::ProfileKind;
+use crate::store::db::ProfileVersion;
+use std::path::Path;
+
+/// Writes PROFILE.md: the user's ChatGPT custom instructions and memories, rewritten as a
+/// block for Claude's personal preferences, followed by every version seen and when it
+/// was in effect. Returns false (and writes nothing) when the export carried neither.
+pub(crate) fn write_profile(root: &Path, versions: &[ProfileVersion], zone: Zone) -> Result<bool, String> {
+    if versions.is_empty() {
+        return Ok(false);
+    }
+
+    let of_kind = |kind: ProfileKind| versions.iter().filter(move |v| v.kind == kind);
+    // The version seen most recently is the one in effect at export time
+    let current = |kind: ProfileKind| of_kind(kind).max_by_key(|v| v.last_seen);
+
+    let mut content = String::from(
+        "# Profile\n\n\
+         Imported from your ChatGPT custom instructions and memory. Copy the block below into \
+         Claude → Settings → Profile (\"What personal preferences should Claude consider in responses?\"), \
+         and edit anything that is no longer true.\n\n",
+    );
+
+    // --- Claude preferences block ---
+    content.push_str("## Claude preferences\n\n```\n");
+    if let Some(about) = current(ProfileKind::AboutUser) {
+        content.push_str(&format!("About me:\n{}\n\n", about.text));
+    }
+    if let Some(style) = current(ProfileKind::ResponseStyle) {
+        content.push_str(&format!("How I'd like you to respond:\n{}\n\n", style.text));
+    }
+    let mut memories: Vec<&ProfileVersion> = of_kind(ProfileKind::Memory).collect();
+    memories.sort_by_key(|v| std::cmp::Reverse(v.last_seen));
+    if !memories.is_empty() {
+        content.push_str("Things to keep in mind about me:\n");
+        for memory in &memories {
+            content.push_str(&format!("- {}\n", one_line(&memory.text)));
+        }
+    }
+    let trimmed = content.trim_end().len();
+    content.truncate(trimmed);
+    content.push_str("\n```\n\n");
+
+    // --- History ---
+    for (kind, heading) in [
+        (ProfileKind::AboutUser, "About you — history"),
+        (ProfileKind::ResponseStyle, "Response style — history"),
+    ] {
+        let history: Vec<&ProfileVersion> = of_kind(kind).collect();
+        if history.is_empty() {
+            continue;
+        }
+        content.push_str(&format!("## {heading}\n\n"));
+        // A version is in effect from when it first appears until the next one does
+        for (i, version) in history.iter().enumerate().rev() {
+            let from = date_or_unknown(version.first_seen, zone);
+            let until = match history.get(i + 1) {
+                Some(next) => date_or_unknown(next.first_seen, zone),
+                None => "now".to_string(),
+            };
+            content.push_str(&format!(
+                "### {} → {} · {} conversation{}\n\n{}\n\n",
+                from,
+                until,
+                version.conversations,
+                if version.conversations == 1 { "" } else { "s" },
+                quote(&version.text)
+            ));
+        }
+    }
+
+    if !memories.is_empty() {
+        content.push_str("## Memories\n\n");
+        for memory in &memories {
+            content.push_str(&format!(
+                "- {} _(seen {} – {})_\n",
+                one_line(&memory.text),
+                date_or_unknown(memory.first_seen, zone),
+                date_or_unknown(memory.last_seen, zone)
+            ));
+        }
+    }
+
+    std::fs::write(root.join("PROFILE.md"), content).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+fn date_or_unknown(ts: Option<i64>, zone: Zone) -> String {
+    ts.map(|t| dates::date_str(t, zone))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn quote(text: &str) -> String {
+    text.lines()
+        .map(|l| if l.is_empty() { ">".to_string() } else { format!("> {l}") })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
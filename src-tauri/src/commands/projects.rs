@@ -0,0 +1,120 @@
+use crate::commands::ingest::unix_now;
+use crate::store::db;
+use crate::AppState;
+use rusqlite::Connection;
+use serde::Deserialize;
+use tauri::State;
+
+/// Which conversations a bulk assignment applies to.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum ConversationSelection {
+    Ids { ids: Vec<String> },
+    /// Conversations whose title or transcript contains every word of `query`.
+    Search { query: String },
+    /// Every conversation clustering gave this label.
+    Cluster { label: String },
+}
+
+impl ConversationSelection {
+    fn ids(self, conn: &Connection) -> Result<Vec<String>, String> {
+        match self {
+            ConversationSelection::Ids { ids } => Ok(ids),
+            ConversationSelection::Search { query } => {
+                db::search_conversation_ids(conn, &query).map_err(|e| e.to_string())
+            }
+            ConversationSelection::Cluster { label } => {
+                db::cluster_conversation_ids(conn, &label).map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+/// Every project, by name, with its conversation count.
+#[tauri::command]
+pub async fn get_projects(state: State<'_, AppState>) -> Result<Vec<db::ProjectRow>, String> {
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    db::get_projects(&conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_project(state: State<'_, AppState>, name: String) -> Result<(), String> {
+    let name = project_name(&name)?;
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    if !db::insert_project(&conn, &name, unix_now()).map_err(|e| e.to_string())? {
+        return Err(format!("A project named \"{name}\" already exists"));
+    }
+    Ok(())
+}
+
+/// Renames a project; its conversations move with it. Changing only the case is allowed.
+#[tauri::command]
+pub async fn rename_project(state: State<'_, AppState>, name: String, new_name: String) -> Result<(), String> {
+    let new_name = project_name(&new_name)?;
+    let mut conn = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let current = existing_project(&tx, &name)?;
+    let taken = db::find_project(&tx, &new_name).map_err(|e| e.to_string())?;
+    if taken.is_some_and(|taken| taken != current) {
+        return Err(format!("A project named \"{new_name}\" already exists"));
+    }
+    db::rename_project(&tx, &current, &new_name).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Deletes a project. Its conversations are not deleted; they go back to their AI clusters.
+#[tauri::command]
+pub async fn delete_project(state: State<'_, AppState>, name: String) -> Result<(), String> {
+    let mut conn = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let current = existing_project(&tx, &name)?;
+    db::delete_project(&tx, &current).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Moves the selected conversations into `project` (out of any other project).
+/// Returns how many conversations changed.
+#[tauri::command]
+pub async fn assign_to_project(
+    state: State<'_, AppState>,
+    project: String,
+    selection: ConversationSelection,
+) -> Result<usize, String> {
+    let mut conn = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let project = existing_project(&tx, &project)?;
+    let ids = selection.ids(&tx)?;
+    let changed = db::set_project(&tx, Some(&project), &ids).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(changed)
+}
+
+/// Takes the selected conversations out of their projects, back into their AI clusters.
+/// Returns how many conversations changed.
+#[tauri::command]
+pub async fn unassign_from_project(
+    state: State<'_, AppState>,
+    selection: ConversationSelection,
+) -> Result<usize, String> {
+    let mut conn = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let ids = selection.ids(&tx)?;
+    let changed = db::set_project(&tx, None, &ids).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(changed)
+}
+
+/// `name` trimmed, or an error when nothing is left.
+fn project_name(name: &str) -> Result<String, String> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        return Err("The project needs a name".to_string());
+    }
+    Ok(name)
+}
+
+fn existing_project(conn: &Connection, name: &str) -> Result<String, String> {
+    db::find_project(conn, name)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("There is no project named \"{name}\""))
+}
@@ -0,0 +1,141 @@
+use crate::redact::{RedactionPolicy, Redactor};
+use crate::store::db;
+use crate::AppState;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use tauri::State;
+
+/// Most sample matches `preview_redaction` returns.
+const MAX_SAMPLES: usize = 50;
+/// Characters of context shown on each side of a sample match.
+const SAMPLE_CONTEXT: usize = 30;
+
+/// What a redaction policy would mask, without writing or sending anything.
+#[derive(Serialize)]
+pub struct RedactionReport {
+    pub conversations_scanned: usize,
+    pub conversations_affected: usize,
+    /// Matches per label (`EMAIL`, `SECRET`, custom pattern names, …).
+    pub counts: BTreeMap<String, usize>,
+    pub samples: Vec<RedactionSample>,
+}
+
+/// One match in context, already masked, so the report itself leaks nothing.
+#[derive(Serialize)]
+pub struct RedactionSample {
+    pub conversation_id: String,
+    pub title: Option<String>,
+    pub label: String,
+    pub preview: String,
+}
+
+/// Dry run of `policy` over every conversation's title and transcript.
+#[tauri::command]
+pub async fn preview_redaction(
+    state: State<'_, AppState>,
+    policy: RedactionPolicy,
+) -> Result<RedactionReport, String> {
+    let redactor = redactor(&state, &policy)?;
+    let conversations = {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        db::get_all_conversations(&conn, db::ConversationSort::Created).map_err(|e| e.to_string())?
+    };
+
+    let mut report = RedactionReport {
+        conversations_scanned: conversations.len(),
+        conversations_affected: 0,
+        counts: BTreeMap::new(),
+        samples: Vec::new(),
+    };
+    for conv in &conversations {
+        let mut affected = false;
+        for text in conv.title.iter().chain([&conv.full_text]) {
+            for m in redactor.find(text) {
+                affected = true;
+                *report.counts.entry(m.label.clone()).or_insert(0) += 1;
+                if report.samples.len() < MAX_SAMPLES {
+                    let before: String = text[..m.start].chars().rev().take(SAMPLE_CONTEXT).collect::<Vec<_>>().into_iter().rev().collect();
+                    let after: String = text[m.end..].chars().take(SAMPLE_CONTEXT).collect();
+                    let masked = redactor.redact(&format!("{before}{}{after}", &text[m.start..m.end]));
+                    report.samples.push(RedactionSample {
+                        conversation_id: conv.id.clone(),
+                        title: redactor.redact_opt(conv.title.as_deref()),
+                        label: m.label,
+                        preview: masked.split_whitespace().collect::<Vec<_>>().join(" "),
+                    });
+                }
+            }
+        }
+        if affected {
+            report.conversations_affected += 1;
+        }
+    }
+    // A dry run hands out no tokens
+    redactor.take_new_tokens();
+    Ok(report)
+}
+
+/// Replaces the tokens of tokenized redaction in `text` (e.g. an answer Claude wrote about
+/// a redacted transcript) with the values they stand for. Unknown tokens are left as they are.
+#[tauri::command]
+pub async fn unmask_text(state: State<'_, AppState>, text: String) -> Result<String, String> {
+    let tokens: HashMap<String, String> = {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        db::get_redaction_tokens(&conn)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|t| (t.token, t.value))
+            .collect()
+    };
+    let pattern = regex::Regex::new(r"\[[A-Z0-9 _]+-\d+\]").map_err(|e| e.to_string())?;
+    Ok(pattern
+        .replace_all(&text, |caps: &regex::Captures| {
+            tokens.get(&caps[0]).cloned().unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned())
+}
+
+/// Redacts the text fields of a conversation row. Run before file names are derived from
+/// titles, so new files don't carry redacted text in their names either.
+pub(crate) fn redact_conversation(redactor: &Redactor, conv: &mut db::ExportRow) {
+    conv.title = redactor.redact_opt(conv.title.as_deref());
+    conv.full_text = redactor.redact(&conv.full_text);
+    conv.summary = redactor.redact_opt(conv.summary.as_deref());
+    conv.instructions = redactor.redact_opt(conv.instructions.as_deref());
+    for participant in &mut conv.participants {
+        *participant = redactor.redact(participant);
+    }
+}
+
+/// Redacts message text, attachment names and rating comments.
+pub(crate) fn redact_messages(redactor: &Redactor, messages: &mut [db::MessageRow]) {
+    for msg in messages {
+        msg.text = redactor.redact(&msg.text);
+        msg.author_name = redactor.redact_opt(msg.author_name.as_deref());
+        for attachment in &mut msg.attachments {
+            attachment.name = redactor.redact_opt(attachment.name.as_deref());
+        }
+        if let Some(ref mut feedback) = msg.feedback {
+            feedback.text = redactor.redact_opt(feedback.text.as_deref());
+        }
+    }
+}
+
+/// The redactor for `policy`, carrying on from the tokens saved by earlier runs.
+pub(crate) fn redactor(state: &AppState, policy: &RedactionPolicy) -> Result<Redactor, String> {
+    let tokens = {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        db::get_redaction_tokens(&conn).map_err(|e| e.to_string())?
+    };
+    Redactor::new(policy, tokens)
+}
+
+/// Saves the tokens `redactor` handed out, so what was written or sent stays reversible.
+pub(crate) fn save_tokens(state: &AppState, redactor: &Redactor) -> Result<(), String> {
+    let tokens = redactor.take_new_tokens();
+    if tokens.is_empty() {
+        return Ok(());
+    }
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    db::insert_redaction_tokens(&conn, &tokens).map_err(|e| e.to_string())
+}
@@ -0,0 +1,111 @@
+use crate::commands::export::is_media_file;
+use crate::pipeline::source::ExportSource;
+use crate::store::db;
+use crate::store::media_store::{self, MediaInfo, MediaStore};
+use crate::AppState;
+use serde::Serialize;
+use tauri::State;
+
+/// An import source whose export could not be used, returned by `export_conversations`
+/// so the user can be asked to locate it (see `relocate_import_source`).
+#[derive(Serialize)]
+pub struct MissingSource {
+    pub id: i64,
+    pub path: String,
+    pub account: Option<String>,
+    pub reason: String,
+}
+
+/// Every export imported into the archive, newest first, with its account and how many
+/// conversations it contained — for the account filter and the list of merged exports.
+#[tauri::command]
+pub async fn get_import_sources(state: State<'_, AppState>) -> Result<Vec<db::ImportSourceRow>, String> {
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    db::get_import_sources(&conn).map_err(|e| e.to_string())
+}
+
+/// Points an import source at the new location of its export, after checking that the
+/// file at `path` really is the same export (same conversation data checksum).
+#[tauri::command]
+pub async fn relocate_import_source(
+    state: State<'_, AppState>,
+    source_id: i64,
+    path: String,
+) -> Result<(), String> {
+    let row = find_source(&state, source_id)?;
+    let source = ExportSource::open(&path)?;
+    if !source.has_hash(&row.file_hash) {
+        return Err("This is not the same export: its conversations differ from the one imported".to_string());
+    }
+    let path = std::fs::canonicalize(&path)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or(path);
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    db::update_import_source_path(&conn, source_id, &path).map_err(|e| e.to_string())
+}
+
+/// Copies an import source's media into the app's media store, so later exports no longer
+/// need the original ZIP or folder. Returns the number of files stored.
+#[tauri::command]
+pub async fn store_source_media(state: State<'_, AppState>, source_id: i64) -> Result<usize, String> {
+    let row = find_source(&state, source_id)?;
+    let source = open_verified(&row)?;
+    store_media(&state, source_id, &source)
+}
+
+fn find_source(state: &State<'_, AppState>, source_id: i64) -> Result<db::ImportSourceRow, String> {
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    db::get_import_source(&conn, source_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Unknown import source {source_id}"))
+}
+
+/// Reopens an import source, checking that its path still holds the export that was
+/// imported. The error says why it can't be used.
+pub(crate) fn open_verified(row: &db::ImportSourceRow) -> Result<ExportSource, String> {
+    let source = ExportSource::open(&row.path).map_err(|e| format!("Export not found at {}: {e}", row.path))?;
+    if !source.has_hash(&row.file_hash) {
+        return Err(format!("The file at {} is no longer the export that was imported", row.path));
+    }
+    Ok(source)
+}
+
+/// Copies every media file of `source` into the media store, records it under `source_id`
+/// with what it is, and relinks stored media to the conversations that reference it.
+pub(crate) fn store_media(state: &AppState, source_id: i64, source: &ExportSource) -> Result<usize, String> {
+    let stored = copy_to_store(&state.media, source)?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    for file in &stored {
+        db::insert_stored_media(&tx, source_id, &file.path, &file.hash, file.size).map_err(|e| e.to_string())?;
+        db::insert_media(&tx, &file.hash, file.size, &file.info).map_err(|e| e.to_string())?;
+    }
+    db::update_media_refs(&tx).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(stored.len())
+}
+
+/// A media file of an export, once it is in the store.
+struct StoredFile {
+    path: String,
+    hash: String,
+    size: u64,
+    info: MediaInfo,
+}
+
+/// Runs without the database lock: copying media can take a while for large exports.
+fn copy_to_store(media: &MediaStore, source: &ExportSource) -> Result<Vec<StoredFile>, String> {
+    let mut stored = Vec::new();
+    source.for_each(is_media_file, |name, reader| {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).map_err(|e| format!("Cannot read {name}: {e}"))?;
+        stored.push(StoredFile {
+            path: name.to_string(),
+            hash: media.put(&buf)?,
+            size: buf.len() as u64,
+            info: media_store::sniff(name, &buf),
+        });
+        Ok(())
+    })?;
+    Ok(stored)
+}
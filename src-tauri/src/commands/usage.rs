@@ -0,0 +1,41 @@
+use crate::pipeline::metadata::ToolCategory;
+use crate::store::db;
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+#[derive(Serialize)]
+pub struct UsageSummary {
+    pub models: Vec<db::ModelUsage>,
+    pub tools: Vec<db::ToolUsage>,
+}
+
+/// Filter for `find_conversations_by_usage`. Both fields are optional and combine with AND.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UsageFilter {
+    /// Exact model slug, e.g. "gpt-4o" or "o1-preview".
+    pub model: Option<String>,
+    pub tool: Option<ToolCategory>,
+}
+
+/// Per-model and per-tool usage counts across the imported history.
+#[tauri::command]
+pub async fn get_usage_summary(state: State<'_, AppState>) -> Result<UsageSummary, String> {
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(UsageSummary {
+        models: db::get_model_usage(&conn, None).map_err(|e| e.to_string())?,
+        tools: db::get_tool_usage(&conn, None).map_err(|e| e.to_string())?,
+    })
+}
+
+/// Conversations that used a given model and/or tool category, newest first —
+/// e.g. "which used o1", "which used DALL-E".
+#[tauri::command]
+pub async fn find_conversations_by_usage(
+    state: State<'_, AppState>,
+    filter: UsageFilter,
+) -> Result<Vec<db::ConversationRef>, String> {
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    db::find_conversations_by_usage(&conn, filter.model.as_deref(), filter.tool).map_err(|e| e.to_string())
+}
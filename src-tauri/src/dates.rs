@@ -0,0 +1,186 @@
+use chrono::{DateTime, Datelike, FixedOffset};
+use chrono_tz::Tz;
+
+/// Time zone used to turn Unix timestamps into calendar dates.
+///
+/// ChatGPT stores every timestamp in UTC, but a conversation started at 23:30 on
+/// New Year's Eve belongs in the year the user experienced it. `Local` follows the
+/// OS setting; `Named` accepts any IANA zone (e.g. "Europe/Berlin").
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Zone {
+    #[default]
+    Local,
+    Utc,
+    Named(Tz),
+}
+
+impl Zone {
+    /// Parses a zone name coming from the frontend.
+    /// `None`, `""` and `"local"` select the OS zone; `"UTC"` / `"Z"` select UTC.
+    pub fn parse(name: Option<&str>) -> Result<Zone, String> {
+        match name.map(str::trim) {
+            None | Some("") => Ok(Zone::Local),
+            Some(n) if n.eq_ignore_ascii_case("local") => Ok(Zone::Local),
+            Some(n) if n.eq_ignore_ascii_case("utc") || n == "Z" => Ok(Zone::Utc),
+            Some(n) => n
+                .parse::<Tz>()
+                .map(Zone::Named)
+                .map_err(|_| format!("Unknown time zone: {n}")),
+        }
+    }
+
+    /// `ts` as a date and time in this zone. Timestamps chrono can't represent (beyond
+    /// ±262,000 years) fall back to the Unix epoch.
+    fn datetime(&self, ts: i64) -> DateTime<FixedOffset> {
+        let utc = DateTime::from_timestamp(ts, 0).unwrap_or_default();
+        match self {
+            Zone::Local => utc.with_timezone(&chrono::Local).fixed_offset(),
+            Zone::Utc => utc.fixed_offset(),
+            Zone::Named(tz) => utc.with_timezone(tz).fixed_offset(),
+        }
+    }
+
+    /// Offset from UTC in seconds in effect at `ts` (accounts for DST transitions).
+    pub fn offset_at(&self, ts: i64) -> i64 {
+        self.datetime(ts).offset().local_minus_utc() as i64
+    }
+}
+
+/// Converts a fractional ChatGPT timestamp to whole seconds (rounding toward the past).
+pub fn from_export_time(ts: f64) -> i64 {
+    ts.floor() as i64
+}
+
+/// Parses an ISO 8601 / RFC 3339 timestamp to Unix seconds. Strings without an offset
+/// (`2023-06-01T12:34:56.789012`, as in some export files) are taken as UTC.
+pub fn parse_iso8601(s: &str) -> Option<i64> {
+    let s = s.trim();
+    DateTime::parse_from_rfc3339(s)
+        .map(|dt| dt.timestamp())
+        .ok()
+        .or_else(|| {
+            chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f")
+                .ok()
+                .map(|dt| dt.and_utc().timestamp())
+        })
+}
+
+/// Calendar year of `ts` in `zone`.
+pub fn year(ts: i64, zone: Zone) -> i32 {
+    zone.datetime(ts).year()
+}
+
+/// `YYYY-MM-DD` for `ts` in `zone`.
+pub fn date_str(ts: i64, zone: Zone) -> String {
+    zone.datetime(ts).format("%Y-%m-%d").to_string()
+}
+
+/// `YYYY-MM-DD HH:MM` for `ts` in `zone`.
+pub fn datetime_str(ts: i64, zone: Zone) -> String {
+    zone.datetime(ts).format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// RFC 3339 timestamp for `ts` with the zone's UTC offset, e.g. `2024-03-01T14:05:00+01:00`.
+pub fn iso8601(ts: i64, zone: Zone) -> String {
+    zone.datetime(ts).format("%Y-%m-%dT%H:%M:%S%:z").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use proptest::prelude::*;
+
+    const SECS_PER_DAY: i64 = 86_400;
+
+    /// Unix timestamp of midnight UTC on a date.
+    fn midnight_utc(year: i32, month: u32, day: u32) -> i64 {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap().and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp()
+    }
+
+    fn berlin() -> Zone {
+        Zone::parse(Some("Europe/Berlin")).unwrap()
+    }
+
+    #[test]
+    fn year_boundaries_in_utc() {
+        // 2023-12-31T23:59:59Z / 2024-01-01T00:00:00Z — the old `ts / 31_536_000 + 1970`
+        // formula put both in 2024.
+        assert_eq!(date_str(1_704_067_199, Zone::Utc), "2023-12-31");
+        assert_eq!(year(1_704_067_199, Zone::Utc), 2023);
+        assert_eq!(date_str(1_704_067_200, Zone::Utc), "2024-01-01");
+        assert_eq!(date_str(0, Zone::Utc), "1970-01-01");
+        assert_eq!(date_str(-1, Zone::Utc), "1969-12-31");
+    }
+
+    #[test]
+    fn leap_days() {
+        assert_eq!(date_str(951_782_400, Zone::Utc), "2000-02-29");
+        assert_eq!(date_str(1_709_164_800, Zone::Utc), "2024-02-29");
+        // 2100 is not a leap year
+        assert_eq!(date_str(4_107_456_000, Zone::Utc), "2100-02-28");
+        assert_eq!(date_str(4_107_542_400, Zone::Utc), "2100-03-01");
+    }
+
+    #[test]
+    fn zone_moves_year_boundary() {
+        // 2023-12-31T23:30:00Z is already New Year in Berlin (UTC+1)
+        let ts = 1_704_065_400;
+        assert_eq!(year(ts, Zone::Utc), 2023);
+        assert_eq!(year(ts, berlin()), 2024);
+        assert_eq!(datetime_str(ts, berlin()), "2024-01-01 00:30");
+        // …and still the old year in New York (UTC-5)
+        let ny = Zone::parse(Some("America/New_York")).unwrap();
+        assert_eq!(datetime_str(1_704_067_200, ny), "2023-12-31 19:00");
+    }
+
+    #[test]
+    fn iso8601_includes_offset() {
+        assert_eq!(iso8601(1_704_065_400, Zone::Utc), "2023-12-31T23:30:00+00:00");
+        assert_eq!(iso8601(1_704_065_400, berlin()), "2024-01-01T00:30:00+01:00");
+        let ny = Zone::parse(Some("America/New_York")).unwrap();
+        assert_eq!(iso8601(1_704_067_200, ny), "2023-12-31T19:00:00-05:00");
+    }
+
+    #[test]
+    fn dst_offsets() {
+        // Berlin is UTC+2 in July, UTC+1 in January
+        assert_eq!(berlin().offset_at(1_720_000_000), 7200);
+        assert_eq!(berlin().offset_at(1_704_067_200), 3600);
+    }
+
+    #[test]
+    fn parse_iso8601_with_and_without_offset() {
+        assert_eq!(parse_iso8601("2024-01-01T00:30:00+01:00"), Some(1_704_065_400));
+        assert_eq!(parse_iso8601("2023-12-31T23:30:00.123456"), Some(1_704_065_400));
+        assert_eq!(parse_iso8601("yesterday"), None);
+    }
+
+    #[test]
+    fn parse_zone_names() {
+        assert_eq!(Zone::parse(None).unwrap(), Zone::Local);
+        assert_eq!(Zone::parse(Some("local")).unwrap(), Zone::Local);
+        assert_eq!(Zone::parse(Some("UTC")).unwrap(), Zone::Utc);
+        assert!(Zone::parse(Some("Mars/Olympus_Mons")).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn year_boundary_seconds(y in 1996i32..2200, zone_idx in 0usize..3) {
+            let zone = [Zone::Utc, berlin(), Zone::parse(Some("Pacific/Kiritimati")).unwrap()][zone_idx];
+            // Midnight on Jan 1 local time, and the second before it
+            let utc = midnight_utc(y, 1, 1);
+            let midnight = utc - zone.offset_at(utc);
+            prop_assert_eq!(date_str(midnight, zone), format!("{y}-01-01"));
+            prop_assert_eq!(date_str(midnight - 1, zone), format!("{}-12-31", y - 1));
+            prop_assert_eq!(year(midnight, zone), y);
+        }
+
+        #[test]
+        fn leap_day_exists_iff_leap_year(year in 1000i32..9999) {
+            let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+            let day_before_march = date_str(midnight_utc(year, 3, 1) - SECS_PER_DAY, Zone::Utc);
+            prop_assert_eq!(day_before_march.ends_with("-02-29"), leap);
+        }
+    }
+}
@@ -1,15 +1,20 @@
 mod ai;
 mod commands;
+mod dates;
 pub mod pipeline;
+mod redact;
 mod store;
 
 use rusqlite::Connection;
+use std::path::PathBuf;
 use std::sync::Mutex;
 use tauri::Manager;
 
 pub struct AppState {
     pub db: Mutex<Connection>,
-    pub zip_path: Mutex<Option<String>>,
+    /// Where `db` lives, for re-encrypting it (see `store::encryption::rekey`).
+    pub db_path: PathBuf,
+    pub media: store::media_store::MediaStore,
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -23,11 +28,20 @@ pub fn run() {
             std::fs::create_dir_all(&db_dir)
                 .expect("failed to create app data dir");
             let db_path = db_dir.join("conversations.db");
-            let conn = Connection::open(&db_path)
+            let key = match commands::keychain::get_database_key() {
+                Ok(key) => key,
+                Err(e) if store::encryption::is_encrypted(&db_path) => {
+                    panic!("conversations.db is encrypted, but its key could not be read from the keychain: {e}")
+                }
+                // Without a usable keychain only an unencrypted database opens
+                Err(_) => None,
+            };
+            let conn = store::encryption::open_store(&db_path, key.as_deref())
                 .expect("failed to open database");
             store::db::init_schema(&conn)
                 .expect("failed to initialize schema");
-            app.manage(AppState { db: Mutex::new(conn), zip_path: Mutex::new(None) });
+            let media = store::media_store::MediaStore::new(db_dir.join("media"));
+            app.manage(AppState { db: Mutex::new(conn), db_path, media });
             Ok(())
         })
         .plugin(tauri_plugin_opener::init())
@@ -37,9 +51,29 @@ pub fn run() {
             commands::keychain::get_api_key,
             commands::keychain::set_api_key,
             commands::keychain::delete_api_key,
+            commands::encryption::get_database_encryption,
+            commands::encryption::set_database_encryption,
             commands::cluster::estimate_cost,
             commands::cluster::start_clustering,
             commands::export::export_conversations,
+            commands::packs::generate_project_packs,
+            commands::usage::get_usage_summary,
+            commands::usage::find_conversations_by_usage,
+            commands::sources::get_import_sources,
+            commands::sources::relocate_import_source,
+            commands::sources::store_source_media,
+            commands::redaction::preview_redaction,
+            commands::redaction::unmask_text,
+            commands::exclusions::get_exclusion_rules,
+            commands::exclusions::add_exclusion_rule,
+            commands::exclusions::delete_exclusion_rule,
+            commands::exclusions::set_conversation_private,
+            commands::projects::get_projects,
+            commands::projects::create_project,
+            commands::projects::rename_project,
+            commands::projects::delete_project,
+            commands::projects::assign_to_project,
+            commands::projects::unassign_from_project,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
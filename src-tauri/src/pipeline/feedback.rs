@@ -0,0 +1,200 @@
+use crate::dates;
+use serde_json::Value;
+
+/// A thumbs up/down rating from `message_feedback.json`.
+pub struct FeedbackRecord {
+    pub id: String,
+    pub conversation_id: String,
+    /// Rated message; older exports only carry it as the feedback `id`.
+    pub message_id: String,
+    /// `true` for thumbs up.
+    pub positive: bool,
+    /// Reason chips picked in the feedback dialog, e.g. "not-factually-correct".
+    pub tags: Vec<String>,
+    /// Free-text comment.
+    pub text: Option<String>,
+    pub created_at: Option<i64>,
+}
+
+/// One side-by-side answer comparison from `model_comparisons.json`.
+pub struct ComparisonRecord {
+    pub id: String,
+    pub conversation_id: Option<String>,
+    pub created_at: Option<i64>,
+    /// Which answer was preferred or how they were rated, as recorded by ChatGPT.
+    pub rating: Option<String>,
+    /// The whole entry, kept because its shape varies a lot between exports.
+    pub data: String,
+}
+
+/// Parses `message_feedback.json`. Entries without a conversation or rating are skipped;
+/// a file that isn't a JSON array is an error.
+pub fn parse_feedback(bytes: &[u8]) -> Result<Vec<FeedbackRecord>, String> {
+    let entries: Vec<Value> =
+        serde_json::from_slice(bytes).map_err(|e| format!("Failed to parse message_feedback.json: {e}"))?;
+
+    Ok(entries
+        .iter()
+        .filter_map(|entry| {
+            let id = entry["id"].as_str()?.to_string();
+            let positive = match entry["rating"].as_str()? {
+                "thumbsUp" | "thumbs_up" | "up" => true,
+                "thumbsDown" | "thumbs_down" | "down" => false,
+                _ => return None,
+            };
+            // `content` is a JSON document serialized into a string
+            let content: Value = match &entry["content"] {
+                Value::String(s) => serde_json::from_str(s).unwrap_or(Value::Null),
+                other => other.clone(),
+            };
+            Some(FeedbackRecord {
+                conversation_id: entry["conversation_id"].as_str()?.to_string(),
+                message_id: entry["message_id"].as_str().map(String::from).unwrap_or_else(|| id.clone()),
+                id,
+                positive,
+                tags: content["tags"]
+                    .as_array()
+                    .map(|tags| tags.iter().filter_map(|t| t.as_str().map(String::from)).collect())
+                    .unwrap_or_default(),
+                text: content["text"].as_str().filter(|t| !t.trim().is_empty()).map(String::from),
+                created_at: timestamp(&entry["create_time"]),
+            })
+        })
+        .collect())
+}
+
+/// Parses `model_comparisons.json` (answers ChatGPT asked the user to choose between).
+pub fn parse_comparisons(bytes: &[u8]) -> Result<Vec<ComparisonRecord>, String> {
+    let entries: Vec<Value> =
+        serde_json::from_slice(bytes).map_err(|e| format!("Failed to parse model_comparisons.json: {e}"))?;
+
+    Ok(entries
+        .iter()
+        .filter_map(|entry| {
+            let conversation_id = [
+                &entry["conversation_id"],
+                &entry["metadata"]["conversation_id"],
+                &entry["input"]["conversation_id"],
+            ]
+            .into_iter()
+            .find_map(|v| v.as_str())
+            .map(String::from);
+            let output = &entry["output"];
+            let rating = [
+                &output["feedback_step_2"]["rating"],
+                &output["completion_comparison_rating"],
+                &output["feedback_step_1"]["rating"],
+            ]
+            .into_iter()
+            .find_map(|v| v.as_str())
+            .map(String::from);
+            Some(ComparisonRecord {
+                id: entry["id"].as_str()?.to_string(),
+                conversation_id,
+                created_at: timestamp(&entry["create_time"]),
+                rating,
+                data: entry.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Feedback files use ISO 8601 strings where conversations use Unix seconds; accept both.
+fn timestamp(value: &Value) -> Option<i64> {
+    match value.as_f64() {
+        Some(secs) => Some(dates::from_export_time(secs)),
+        None => dates::parse_iso8601(value.as_str()?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn feedback(entries: Value) -> Vec<FeedbackRecord> {
+        parse_feedback(entries.to_string().as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn ratings_in_every_spelling() {
+        let entries: Vec<Value> = ["thumbsUp", "thumbs_up", "up", "thumbsDown", "thumbs_down", "down", "meh"]
+            .iter()
+            .map(|rating| json!({ "id": "f1", "conversation_id": "c1", "rating": rating }))
+            .collect();
+        let ratings: Vec<bool> = feedback(Value::Array(entries)).iter().map(|f| f.positive).collect();
+        assert_eq!(ratings, [true, true, true, false, false, false]);
+    }
+
+    #[test]
+    fn content_as_a_serialized_string_or_an_object() {
+        let records = feedback(json!([
+            {
+                "id": "f1", "conversation_id": "c1", "message_id": "m1", "rating": "thumbsDown",
+                "content": "{\"tags\": [\"not-factually-correct\"], \"text\": \"Wrong year\"}",
+                "create_time": "2024-06-01T00:00:00.000000"
+            },
+            {
+                "id": "m2", "conversation_id": "c1", "rating": "thumbsUp",
+                "content": { "tags": ["helpful", 3], "text": "  " },
+                "create_time": 1_717_200_000.5
+            },
+            { "id": "m3", "conversation_id": "c1", "rating": "up", "content": "not json" }
+        ]));
+        assert_eq!(records.len(), 3);
+
+        assert_eq!(records[0].message_id, "m1");
+        assert_eq!(records[0].tags, ["not-factually-correct"]);
+        assert_eq!(records[0].text.as_deref(), Some("Wrong year"));
+        assert_eq!(records[0].created_at, Some(1_717_200_000));
+
+        // Older exports only carry the message as the feedback id; blank comments are dropped
+        assert_eq!(records[1].message_id, "m2");
+        assert_eq!(records[1].tags, ["helpful"]);
+        assert_eq!(records[1].text, None);
+        assert_eq!(records[1].created_at, Some(1_717_200_000));
+
+        assert!(records[2].tags.is_empty() && records[2].text.is_none());
+    }
+
+    #[test]
+    fn entries_without_conversation_or_rating_are_skipped() {
+        let records = feedback(json!([
+            { "id": "f1", "rating": "thumbsUp" },
+            { "id": "f2", "conversation_id": "c1" },
+            { "conversation_id": "c1", "rating": "thumbsUp" },
+            { "id": "f4", "conversation_id": "c1", "rating": "thumbsUp" }
+        ]));
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].id, "f4");
+        assert!(parse_feedback(b"{}").is_err());
+    }
+
+    #[test]
+    fn comparisons() {
+        let entries = json!([
+            {
+                "id": "cmp1", "create_time": "2024-06-01T00:00:00Z",
+                "metadata": { "conversation_id": "c1" },
+                "output": { "feedback_step_1": { "rating": "none" }, "feedback_step_2": { "rating": "prefer_left" } }
+            },
+            { "id": "cmp2", "input": { "conversation_id": "c2" }, "output": { "completion_comparison_rating": "same" } },
+            { "id": "cmp3" },
+            { "output": {} }
+        ]);
+        let records = parse_comparisons(entries.to_string().as_bytes()).unwrap();
+        assert_eq!(records.len(), 3);
+
+        assert_eq!(records[0].conversation_id.as_deref(), Some("c1"));
+        assert_eq!(records[0].rating.as_deref(), Some("prefer_left"));
+        assert_eq!(records[0].created_at, Some(1_717_200_000));
+        assert_eq!(serde_json::from_str::<Value>(&records[0].data).unwrap(), entries[0]);
+
+        assert_eq!(records[1].conversation_id.as_deref(), Some("c2"));
+        assert_eq!(records[1].rating.as_deref(), Some("same"));
+
+        assert_eq!(records[2].conversation_id, None);
+        assert_eq!(records[2].rating, None);
+        assert!(parse_comparisons(b"not json").is_err());
+    }
+}
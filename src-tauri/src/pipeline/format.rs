@@ -0,0 +1,285 @@
+use crate::pipeline::json_parser::ConversationExport;
+use crate::pipeline::source::{is_conversations_file, is_shard_file};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Export files the pipeline knows how to read. `conversations-NNN.json` shards are
+/// matched separately; media files are not fingerprinted.
+const KNOWN_FILES: &[&str] = &[
+    "conversations.json",
+    "chat.html",
+    "user.json",
+    "message_feedback.json",
+    "model_comparisons.json",
+    "shared_conversations.json",
+    "group_chats.json",
+];
+
+/// `content.content_type` values seen in real exports.
+const KNOWN_CONTENT_TYPES: &[&str] = &[
+    "text",
+    "multimodal_text",
+    "code",
+    "execution_output",
+    "tether_browsing_display",
+    "tether_quote",
+    "system_error",
+    "user_editable_context",
+    "model_editable_context",
+    "thoughts",
+    "reasoning_recap",
+    "computer_output",
+    "sonic_webpage",
+];
+
+/// `content_type` of object parts inside `content.parts`.
+const KNOWN_PART_TYPES: &[&str] = &[
+    "image_asset_pointer",
+    "audio_asset_pointer",
+    "audio_transcription",
+    "real_time_user_audio_video_asset_pointer",
+    "video_container_asset_pointer",
+];
+
+/// Top-level `message.metadata` keys seen in real exports, whether or not we read them.
+const KNOWN_METADATA_KEYS: &[&str] = &[
+    "model_slug",
+    "default_model_slug",
+    "finish_details",
+    "attachments",
+    "citations",
+    "content_references",
+    "invoked_plugin",
+    "aggregate_result",
+    "is_visually_hidden_from_conversation",
+    "user_context_message_data",
+    "is_user_system_message",
+    "is_complete",
+    "message_type",
+    "message_source",
+    "parent_id",
+    "request_id",
+    "timestamp_",
+    "pad",
+    "command",
+    "args",
+    "status",
+    "kwargs",
+    "gizmo_id",
+    "voice_mode_message",
+    "real_time_audio_has_video",
+    "model_switcher_deny",
+    "rebase_system_message",
+    "search_queries",
+    "search_result_groups",
+    "safe_urls",
+    "citation_format_type",
+    "serialization_metadata",
+    "jit_plugin_data",
+    "selected_github_repos",
+    "sonic_classification_result",
+    "reasoning_status",
+    "exclusive_key",
+    "can_save",
+];
+
+/// Known layouts of a ChatGPT data export, oldest first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FormatVersion {
+    /// One conversations.json; images point at `file-service://` (before March 2025).
+    Legacy,
+    /// One conversations.json using the March 2025 parts schema (`sediment://` asset pointers).
+    Parts2025,
+    /// conversations-000.json, conversations-001.json, … shards (2026+).
+    Sharded2026,
+}
+
+/// What an export looks like, so schema drift shows up as warnings instead of silently
+/// missing data.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportFingerprint {
+    pub version: FormatVersion,
+    /// JSON and HTML files in the export (media excluded), by file name.
+    pub files: Vec<String>,
+    /// Number of conversations JSON files; 1 for an unsharded export.
+    pub shards: u32,
+    /// Message count per `content_type`.
+    pub content_types: BTreeMap<String, u32>,
+    /// Part count per shape: "string", an object part's `content_type`, or the JSON type.
+    pub part_shapes: BTreeMap<String, u32>,
+    /// Files, content types, part shapes and metadata keys we don't recognise.
+    pub warnings: Vec<String>,
+}
+
+/// Fingerprints an export from its entry names and parsed conversations.
+/// Every node in every branch is inspected, not just the ones that end up in transcripts.
+pub fn fingerprint(entry_names: &[String], conversations: &[ConversationExport]) -> ExportFingerprint {
+    let mut files: Vec<String> = entry_names
+        .iter()
+        .map(|name| name.rsplit('/').next().unwrap_or(name))
+        .filter(|name| name.ends_with(".json") || name.ends_with(".html"))
+        .map(String::from)
+        .collect();
+    files.sort();
+    files.dedup();
+    let shards = files.iter().filter(|name| is_conversations_file(name)).count() as u32;
+
+    let mut content_types: BTreeMap<String, u32> = BTreeMap::new();
+    let mut part_shapes: BTreeMap<String, u32> = BTreeMap::new();
+    let mut metadata_keys: BTreeMap<String, u32> = BTreeMap::new();
+    let mut sediment_pointers = false;
+
+    let messages = conversations
+        .iter()
+        .flat_map(|c| c.mapping.values())
+        .filter_map(|node| node.message.as_ref());
+    for msg in messages {
+        if let Some(ref content) = msg.content {
+            *content_types.entry(content.content_type.clone()).or_default() += 1;
+            for part in &content.parts {
+                *part_shapes.entry(part_shape(part)).or_default() += 1;
+                if part["asset_pointer"].as_str().is_some_and(|p| p.starts_with("sediment://")) {
+                    sediment_pointers = true;
+                }
+            }
+        }
+        for key in msg.metadata.as_object().into_iter().flat_map(|m| m.keys()) {
+            *metadata_keys.entry(key.clone()).or_default() += 1;
+        }
+    }
+
+    let mut warnings: Vec<String> = Vec::new();
+    for file in &files {
+        if !KNOWN_FILES.contains(&file.as_str()) && !is_conversations_file(file) {
+            warnings.push(format!("unrecognised file {file}"));
+        }
+    }
+    let mut unknown = |what: &str, counts: &BTreeMap<String, u32>, known: &[&str], except: &[&str]| {
+        for (name, count) in counts {
+            if !known.contains(&name.as_str()) && !except.contains(&name.as_str()) {
+                warnings.push(format!("unknown {what} \"{name}\" ({count}×)"));
+            }
+        }
+    };
+    unknown("content_type", &content_types, KNOWN_CONTENT_TYPES, &[]);
+    unknown("part shape", &part_shapes, KNOWN_PART_TYPES, &["string"]);
+    unknown("metadata key", &metadata_keys, KNOWN_METADATA_KEYS, &[]);
+
+    let version = if files.iter().any(|f| is_shard_file(f)) {
+        FormatVersion::Sharded2026
+    } else if sediment_pointers {
+        FormatVersion::Parts2025
+    } else {
+        FormatVersion::Legacy
+    };
+
+    ExportFingerprint { version, files, shards, content_types, part_shapes, warnings }
+}
+
+/// "string" for text parts, the `content_type` of typed object parts, otherwise the JSON type.
+fn part_shape(part: &Value) -> String {
+    match part {
+        Value::String(_) => "string".to_string(),
+        Value::Object(obj) => match obj.get("content_type").and_then(Value::as_str) {
+            Some(content_type) => content_type.to_string(),
+            None => "object".to_string(),
+        },
+        Value::Null => "null".to_string(),
+        Value::Bool(_) => "bool".to_string(),
+        Value::Number(_) => "number".to_string(),
+        Value::Array(_) => "array".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A conversation with one message holding `parts`.
+    fn conversation(content_type: &str, parts: Value) -> ConversationExport {
+        serde_json::from_value(json!({
+            "id": "c1",
+            "mapping": { "m1": { "id": "m1", "message": {
+                "id": "m1", "author": { "role": "user" }, "create_time": null,
+                "content": { "content_type": content_type, "parts": parts },
+                "metadata": { "some_new_metadata_key": true }
+            }}}
+        }))
+        .unwrap()
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn legacy_export() {
+        let image = json!({ "content_type": "image_asset_pointer", "asset_pointer": "file-service://file-1" });
+        let fp = fingerprint(
+            &names(&["conversations.json", "chat.html", "user.json", "file-1.png"]),
+            &[conversation("multimodal_text", json!(["Look", image]))],
+        );
+        assert_eq!(fp.version, FormatVersion::Legacy);
+        assert_eq!(fp.files, vec!["chat.html", "conversations.json", "user.json"]);
+        assert_eq!(fp.shards, 1);
+        assert_eq!(fp.content_types["multimodal_text"], 1);
+        assert_eq!(fp.part_shapes["string"], 1);
+        assert_eq!(fp.part_shapes["image_asset_pointer"], 1);
+        assert_eq!(fp.warnings, vec!["unknown metadata key \"some_new_metadata_key\" (1×)"]);
+    }
+
+    #[test]
+    fn parts_2025_export() {
+        let image = json!({ "content_type": "image_asset_pointer", "asset_pointer": "sediment://file_1" });
+        let fp = fingerprint(&names(&["conversations.json"]), &[conversation("multimodal_text", json!([image]))]);
+        assert_eq!(fp.version, FormatVersion::Parts2025);
+        assert_eq!(fp.shards, 1);
+    }
+
+    #[test]
+    fn sharded_2026_export() {
+        let fp = fingerprint(
+            &names(&["export/conversations-000.json", "export/conversations-001.json", "export/user.json"]),
+            &[conversation("text", json!(["Hi"]))],
+        );
+        assert_eq!(fp.version, FormatVersion::Sharded2026);
+        assert_eq!(fp.shards, 2);
+        assert_eq!(fp.files, vec!["conversations-000.json", "conversations-001.json", "user.json"]);
+    }
+
+    #[test]
+    fn only_numbered_shards_mark_a_sharded_export() {
+        let fp = fingerprint(
+            &names(&["conversations.json", "conversations-backup.json", "conversations-.json"]),
+            &[conversation("text", json!(["Hi"]))],
+        );
+        assert_eq!(fp.version, FormatVersion::Legacy);
+        assert_eq!(fp.shards, 1);
+    }
+
+    #[test]
+    fn warns_on_unknown_files_content_types_part_shapes_and_metadata_keys() {
+        let fp = fingerprint(
+            &names(&["conversations.json", "something_new.json"]),
+            &[
+                conversation("hologram", json!(["Hi"])),
+                conversation("multimodal_text", json!([{ "content_type": "smell_pointer" }, 3, null])),
+            ],
+        );
+        assert_eq!(
+            fp.warnings,
+            vec![
+                "unrecognised file something_new.json",
+                "unknown content_type \"hologram\" (1×)",
+                "unknown part shape \"null\" (1×)",
+                "unknown part shape \"number\" (1×)",
+                "unknown part shape \"smell_pointer\" (1×)",
+                "unknown metadata key \"some_new_metadata_key\" (2×)",
+            ]
+        );
+    }
+}
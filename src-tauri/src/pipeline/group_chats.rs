@@ -0,0 +1,207 @@
+use crate::dates;
+use crate::pipeline::normalizer::{ConversationKind, ConversationRecord, MessageRecord};
+use crate::pipeline::traversal::MessageKind;
+use serde_json::Value;
+
+/// Parses `group_chats.json` into conversation records tagged `ConversationKind::Group`,
+/// so group chats are stored, clustered and exported like any other conversation.
+///
+/// The file is `{"chats": [...]}`; each chat has a name, participants and messages with
+/// a role, text, author and timestamp. The schema is young and fields move around, so it
+/// is read leniently: timestamps may be ISO strings or Unix seconds, authors may be a
+/// string or an object, and a chat without an id gets a stable one derived from its
+/// name and start time so re-importing replaces instead of duplicating it.
+pub fn parse_group_chats(bytes: &[u8]) -> Result<Vec<ConversationRecord>, String> {
+    let export: Value =
+        serde_json::from_slice(bytes).map_err(|e| format!("Failed to parse group_chats.json: {e}"))?;
+    let chats = export["chats"]
+        .as_array()
+        .or_else(|| export.as_array())
+        .ok_or("group_chats.json has no chats array")?;
+
+    Ok(chats.iter().map(group_chat_record).collect())
+}
+
+fn group_chat_record(chat: &Value) -> ConversationRecord {
+    let title = chat["name"]
+        .as_str()
+        .or_else(|| chat["title"].as_str())
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or("Untitled Group Chat")
+        .to_string();
+    let created_at = timestamp(&chat["created_at"]).or_else(|| timestamp(&chat["create_time"]));
+    let updated_at = timestamp(&chat["updated_at"]).or_else(|| timestamp(&chat["update_time"]));
+    let id = chat["id"]
+        .as_str()
+        .filter(|id| !id.is_empty())
+        .map(String::from)
+        .unwrap_or_else(|| format!("group-{:016x}", fnv1a(&format!("{title}\n{}", created_at.unwrap_or(0)))));
+
+    let mut participants: Vec<String> = chat["participants"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(author_name)
+        .collect();
+
+    let mut messages: Vec<MessageRecord> = Vec::new();
+    let mut full_text = String::new();
+    for (i, msg) in chat["messages"].as_array().into_iter().flatten().enumerate() {
+        let text = msg["text"]
+            .as_str()
+            .or_else(|| msg["content"].as_str())
+            .unwrap_or("")
+            .to_string();
+        if text.trim().is_empty() {
+            continue;
+        }
+        let role = msg["role"].as_str().unwrap_or("user").to_string();
+        let author = author_name(&msg["author"])
+            .or_else(|| author_name(&msg["author_name"]))
+            .or_else(|| author_name(&msg["name"]));
+        if let Some(ref name) = author {
+            if role != "assistant" && !participants.contains(name) {
+                participants.push(name.clone());
+            }
+        }
+
+        full_text.push_str(&text);
+        full_text.push('\n');
+        messages.push(MessageRecord {
+            id: msg["id"].as_str().map(String::from).unwrap_or_else(|| format!("{id}-{i}")),
+            role,
+            author_name: author,
+            kind: MessageKind::Message,
+            tool: None,
+            content_type: "text".to_string(),
+            created_at: timestamp(&msg["created_at"]).or_else(|| timestamp(&msg["create_time"])),
+            text,
+            model_slug: msg["model_slug"].as_str().map(String::from),
+            assets: Vec::new(),
+            finish_type: None,
+            attachments: Vec::new(),
+            citations: Vec::new(),
+        });
+    }
+
+    let message_times = messages.iter().filter_map(|m| m.created_at);
+    let first_message_at = message_times.clone().min();
+    let last_message_at = message_times.max();
+
+    ConversationRecord {
+        id,
+        kind: ConversationKind::Group,
+        title,
+        created_at: created_at.or(first_message_at),
+        updated_at,
+        first_message_at,
+        last_message_at,
+        message_count: messages.len() as u32,
+        has_images: false,
+        has_code: full_text.contains("```"),
+        // Rough token estimate: ~4 chars per token
+        token_estimate: (full_text.len() / 4) as u32,
+        full_text,
+        gizmo_id: None,
+        messages,
+        profile: Vec::new(),
+        tool_calls: Vec::new(),
+        participants,
+    }
+}
+
+/// A participant or author given as a plain string or as `{"name": …}` / `{"display_name": …}`.
+fn author_name(value: &Value) -> Option<String> {
+    value
+        .as_str()
+        .or_else(|| value["display_name"].as_str())
+        .or_else(|| value["name"].as_str())
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+}
+
+fn timestamp(value: &Value) -> Option<i64> {
+    match value.as_f64() {
+        Some(secs) => Some(dates::from_export_time(secs)),
+        None => dates::parse_iso8601(value.as_str()?),
+    }
+}
+
+/// 64-bit FNV-1a — a hash that stays the same across builds, for derived ids.
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_chats_with_mixed_field_shapes() {
+        let chats = parse_group_chats(
+            br#"{"chats": [{
+                "id": "g-1",
+                "name": "Trip planning",
+                "created_at": "2024-03-01T10:00:00Z",
+                "participants": ["Ana", {"display_name": "Ben"}],
+                "messages": [
+                    {"role": "user", "author": {"name": "Ana"}, "text": "Where to?", "created_at": 1709287200},
+                    {"role": "user", "author_name": "Cleo", "content": "Lisbon!", "create_time": "2024-03-01T10:05:00Z"},
+                    {"role": "assistant", "author": "ChatGPT", "text": "```\nitinerary\n```"},
+                    {"role": "user", "author": "Ana", "text": "   "}
+                ]
+            }]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(chats.len(), 1);
+        let chat = &chats[0];
+        assert_eq!(chat.id, "g-1");
+        assert_eq!(chat.kind, ConversationKind::Group);
+        assert_eq!(chat.title, "Trip planning");
+        assert_eq!(chat.created_at, Some(1_709_287_200));
+        // Authors not listed as participants are added; the assistant is not a participant
+        assert_eq!(chat.participants, vec!["Ana", "Ben", "Cleo"]);
+        assert_eq!(chat.message_count, 3);
+        let authors: Vec<Option<&str>> = chat.messages.iter().map(|m| m.author_name.as_deref()).collect();
+        assert_eq!(authors, vec![Some("Ana"), Some("Cleo"), Some("ChatGPT")]);
+        assert_eq!(chat.messages[1].id, "g-1-1");
+        assert_eq!(chat.first_message_at, Some(1_709_287_200));
+        assert_eq!(chat.last_message_at, Some(1_709_287_500));
+        assert!(chat.has_code);
+    }
+
+    #[test]
+    fn chats_without_an_id_get_a_stable_one() {
+        let json = br#"[{"title": "", "create_time": 1700000000.7, "messages": []}]"#;
+        let first = parse_group_chats(json).unwrap();
+        let again = parse_group_chats(json).unwrap();
+        assert!(first[0].id.starts_with("group-"));
+        assert_eq!(first[0].id, again[0].id);
+        assert_eq!(first[0].title, "Untitled Group Chat");
+        assert_eq!(first[0].created_at, Some(1_700_000_000));
+
+        let other = parse_group_chats(br#"[{"title": "Other", "create_time": 1700000000}]"#).unwrap();
+        assert_ne!(first[0].id, other[0].id);
+    }
+
+    #[test]
+    fn created_at_falls_back_to_the_first_message() {
+        let chats = parse_group_chats(
+            br#"{"chats": [{"id": "g", "messages": [
+                {"text": "later", "created_at": 200},
+                {"text": "earlier", "created_at": 100}
+            ]}]}"#,
+        )
+        .unwrap();
+        assert_eq!(chats[0].created_at, Some(100));
+        assert_eq!(chats[0].messages[0].role, "user");
+    }
+
+    #[test]
+    fn rejects_files_without_chats() {
+        assert!(parse_group_chats(b"not json").err().unwrap().contains("Failed to parse"));
+        assert!(parse_group_chats(br#"{"groups": []}"#).err().unwrap().contains("no chats array"));
+    }
+}
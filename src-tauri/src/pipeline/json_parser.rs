@@ -1,6 +1,9 @@
-use serde::Deserialize;
+use crate::pipeline::report::ImportReport;
+use serde::de::{SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
 use std::collections::HashMap;
-use std::io::Read;
+use std::fmt;
 
 /// A single conversation exported from ChatGPT.
 /// All fields that can be null or absent in conversations.json are `Option<T>`.
@@ -34,6 +37,9 @@ pub struct Message {
     pub author: Author,
     pub create_time: Option<f64>,
     pub content: Option<Content>,
+    /// Tool the message is addressed to ("all" for normal chat, "bio" for memory writes).
+    #[serde(default)]
+    pub recipient: Option<String>,
     /// Absorb all metadata shapes — do NOT define a strict struct here.
     #[serde(default)]
     pub metadata: serde_json::Value,
@@ -48,26 +54,247 @@ pub struct Author {
 }
 
 /// Message content with a type discriminator and mixed-type parts.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Default, Deserialize, Clone)]
 pub struct Content {
     pub content_type: String,
     /// parts can be strings, objects, or mixed — use Value to absorb all formats
     /// (ChatGPT schema changed in March 2025; Value handles both old and new layouts).
     #[serde(default)]
     pub parts: Vec<serde_json::Value>,
+    /// `user_editable_context` only: "about you" custom instructions.
+    #[serde(default)]
+    pub user_profile: Option<String>,
+    /// `user_editable_context` only: "how to respond" custom instructions.
+    #[serde(default)]
+    pub user_instructions: Option<String>,
+    /// `model_editable_context` only: the memory snapshot visible to the model.
+    #[serde(default)]
+    pub model_set_context: Option<String>,
+    /// `code` and `execution_output`: the code sent to a tool, or its stdout.
+    #[serde(default)]
+    pub text: Option<String>,
+    /// `code` only: language hint ("python", "unknown").
+    #[serde(default)]
+    pub language: Option<String>,
+    /// `tether_browsing_display` only: the rendered search or page result.
+    #[serde(default)]
+    pub result: Option<String>,
+}
+
+/// Parses one conversations JSON array (a `conversations.json` shard) element by element.
+///
+/// Each element is first read as a `serde_json::Value` and only then converted to
+/// `ConversationExport`, so one conversation with an unexpected shape is skipped or repaired
+/// instead of failing the whole import. Everything skipped or repaired is recorded in
+/// `report` under `shard`. A shard that is truncated or syntactically broken keeps the
+/// conversations read before the damage.
+pub fn parse_conversations(shard: &str, bytes: &[u8], report: &mut ImportReport) -> Vec<ConversationExport> {
+    let mut conversations: Vec<ConversationExport> = Vec::new();
+    let mut de = serde_json::Deserializer::from_slice(bytes);
+    let elements = Elements { shard, conversations: &mut conversations, report: &mut *report };
+    if let Err(e) = de.deserialize_seq(elements) {
+        let reason = if conversations.is_empty() {
+            format!("Failed to parse {shard}: {e}")
+        } else {
+            format!("{shard} is corrupt after {} conversations: {e}", conversations.len())
+        };
+        report.skip(None, shard, reason);
+    }
+    conversations
+}
+
+/// Visits the top-level array, converting each element as soon as it is read so the
+/// shard is never held in memory as a full `Value` tree.
+struct Elements<'a> {
+    shard: &'a str,
+    conversations: &'a mut Vec<ConversationExport>,
+    report: &'a mut ImportReport,
+}
+
+impl<'de> Visitor<'de> for Elements<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of conversations")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(value) = seq.next_element::<Value>()? {
+            match ConversationExport::deserialize(&value) {
+                Ok(conversation) => self.conversations.push(conversation),
+                Err(e) => match recover_conversation(&value) {
+                    Some((conversation, repairs)) => {
+                        let reason = if repairs.is_empty() { e.to_string() } else { repairs.join("; ") };
+                        self.report.recover(conversation.id.clone(), self.shard, reason);
+                        self.conversations.push(conversation);
+                    }
+                    None => self.report.skip(None, self.shard, format!("conversation without an id: {e}")),
+                },
+            }
+        }
+        Ok(())
+    }
 }
 
-/// Deserializes all `ConversationExport` elements from a reader containing a top-level
-/// JSON array (the format OpenAI uses in conversations.json).
+/// Rebuilds a conversation that failed strict deserialization, field by field.
+/// Fields of the wrong type are treated as absent and message nodes that don't parse are
+/// dropped (the branch walk stops at the gap). Returns `None` when there is no usable id.
+fn recover_conversation(value: &Value) -> Option<(ConversationExport, Vec<String>)> {
+    let mut repairs: Vec<String> = Vec::new();
+    let id = match &value["id"] {
+        Value::String(id) if !id.is_empty() => id.clone(),
+        Value::Number(id) => {
+            repairs.push(format!("numeric id {id} read as a string"));
+            id.to_string()
+        }
+        _ => value["conversation_id"].as_str().filter(|id| !id.is_empty())?.to_string(),
+    };
+
+    let mut mapping: HashMap<String, MessageNode> = HashMap::new();
+    let mut dropped = 0;
+    match &value["mapping"] {
+        Value::Object(nodes) => {
+            for (key, node) in nodes {
+                match MessageNode::deserialize(node) {
+                    Ok(node) => {
+                        mapping.insert(key.clone(), node);
+                    }
+                    Err(_) => dropped += 1,
+                }
+            }
+        }
+        Value::Null => {}
+        _ => repairs.push("mapping is not an object; no messages imported".to_string()),
+    }
+    if dropped > 0 {
+        repairs.push(format!("dropped {dropped} malformed message node(s)"));
+    }
+
+    let string = |v: &Value| v.as_str().map(String::from);
+    let conversation = ConversationExport {
+        id,
+        title: string(&value["title"]),
+        create_time: value["create_time"].as_f64(),
+        update_time: value["update_time"].as_f64(),
+        mapping,
+        current_node: string(&value["current_node"]),
+        gizmo_id: string(&value["gizmo_id"]),
+    };
+    Some((conversation, repairs))
+}
+
+/// Recovers the conversations array embedded in the export's `chat.html`.
 ///
-/// Note: `serde_json::Deserializer::into_iter()` is a StreamDeserializer for NDJSON
-/// (multiple separate top-level values). It does NOT iterate array elements — it tries
-/// to deserialize the whole `[...]` as one `ConversationExport`, fails, and returns 0.
-/// The correct approach for a single top-level array is `from_reader::<Vec<T>>`.
-/// conversations.json bytes are already in-memory (Vec<u8> from zip_reader), so there
-/// is no additional memory cost from collecting into Vec<ConversationExport>.
-pub fn stream_conversations<R: Read>(
-    reader: R,
-) -> Result<Vec<ConversationExport>, serde_json::Error> {
-    serde_json::from_reader(reader)
+/// The HTML viewer ships the same data as conversations.json in a script tag
+/// (`var jsonData = [...];`). Used when the JSON shards are missing or corrupt.
+/// Conversations inside are parsed as tolerantly as a JSON shard.
+pub fn conversations_from_chat_html(html: &[u8], report: &mut ImportReport) -> Result<Vec<ConversationExport>, String> {
+    const MARKER: &[u8] = b"jsonData";
+    let start = html
+        .windows(MARKER.len())
+        .position(|w| w == MARKER)
+        .ok_or("chat.html does not contain embedded conversation data")?;
+    let open = html[start..]
+        .iter()
+        .position(|&b| b == b'[')
+        .map(|i| start + i)
+        .ok_or("chat.html conversation data is malformed")?;
+
+    // Deserializing a single sequence ignores the `;</script>…` tail after the array
+    Ok(parse_conversations("chat.html", &html[open..], report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NODE: &str = r#"{"id": "m1", "parent": null, "children": [], "message": {
+        "id": "m1", "author": {"role": "user"}, "create_time": null,
+        "content": {"content_type": "text", "parts": ["Hi"]}}}"#;
+
+    fn parse(json: &str) -> (Vec<ConversationExport>, ImportReport) {
+        let mut report = ImportReport::default();
+        let conversations = parse_conversations("conversations-001.json", json.as_bytes(), &mut report);
+        (conversations, report)
+    }
+
+    #[test]
+    fn parses_well_formed_conversations_without_issues() {
+        let (conversations, report) = parse(&format!(
+            r#"[{{"id": "c1", "title": "Hello", "create_time": 1.5, "mapping": {{"m1": {NODE}}}, "current_node": "m1"}}]"#
+        ));
+        assert_eq!(conversations.len(), 1);
+        assert_eq!(conversations[0].mapping.len(), 1);
+        assert!(report.skipped.is_empty() && report.recovered.is_empty());
+    }
+
+    #[test]
+    fn recovers_a_numeric_id() {
+        let (conversations, report) = parse(r#"[{"id": 42, "title": "Numbered", "mapping": {}}]"#);
+        assert_eq!(conversations[0].id, "42");
+        assert_eq!(conversations[0].title.as_deref(), Some("Numbered"));
+        assert_eq!(report.recovered.len(), 1);
+        assert_eq!(report.recovered[0].conversation_id.as_deref(), Some("42"));
+        assert!(report.recovered[0].reason.contains("numeric id"), "{}", report.recovered[0].reason);
+    }
+
+    #[test]
+    fn drops_malformed_nodes_and_keeps_the_rest() {
+        // A node whose message has no author, and a title of the wrong type
+        let (conversations, report) = parse(&format!(
+            r#"[{{"id": "c1", "title": 7, "mapping": {{
+                "m1": {NODE},
+                "m2": {{"id": "m2", "parent": "m1", "message": {{"id": "m2", "content": null}}}}
+            }}, "current_node": "m1"}}]"#
+        ));
+        let conversation = &conversations[0];
+        assert_eq!(conversation.title, None);
+        assert_eq!(conversation.mapping.keys().collect::<Vec<_>>(), vec!["m1"]);
+        assert_eq!(conversation.current_node.as_deref(), Some("m1"));
+        assert_eq!(report.recovered[0].reason, "dropped 1 malformed message node(s)");
+        assert_eq!(report.recovered[0].shard, "conversations-001.json");
+    }
+
+    #[test]
+    fn skips_conversations_without_an_id() {
+        let (conversations, report) = parse(r#"[{"title": "No id"}, {"id": "c2"}, {"conversation_id": "c3", "mapping": 1}]"#);
+        let ids: Vec<&str> = conversations.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["c2", "c3"]);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].conversation_id, None);
+        assert!(report.recovered[0].reason.contains("mapping is not an object"));
+    }
+
+    #[test]
+    fn a_truncated_shard_keeps_what_came_before() {
+        let (conversations, report) = parse(r#"[{"id": "c1"}, {"id": "c2"}, {"id": "c3", "title": "Cut o"#);
+        assert_eq!(conversations.len(), 2);
+        assert_eq!(report.skipped.len(), 1);
+        let issue = &report.skipped[0];
+        assert_eq!(issue.conversation_id, None);
+        assert_eq!(issue.shard, "conversations-001.json");
+        assert!(issue.reason.starts_with("conversations-001.json is corrupt after 2 conversations"), "{}", issue.reason);
+
+        let (conversations, report) = parse("{not an array");
+        assert!(conversations.is_empty());
+        assert!(report.skipped[0].reason.starts_with("Failed to parse conversations-001.json"));
+    }
+
+    #[test]
+    fn recovers_conversations_from_chat_html() {
+        let html = format!(
+            r#"<html><script>var jsonData = [{{"id": "c1", "mapping": {{"m1": {NODE}}}, "current_node": "m1"}}];
+            function render() {{ return [1, 2]; }}</script></html>"#
+        );
+        let mut report = ImportReport::default();
+        let conversations = conversations_from_chat_html(html.as_bytes(), &mut report).unwrap();
+        assert_eq!(conversations.len(), 1);
+        assert_eq!(conversations[0].id, "c1");
+        assert!(report.skipped.is_empty());
+
+        let err = conversations_from_chat_html(b"<html>No data</html>", &mut report).unwrap_err();
+        assert!(err.contains("does not contain"), "{err}");
+        let err = conversations_from_chat_html(b"var jsonData = null;", &mut report).unwrap_err();
+        assert!(err.contains("malformed"), "{err}");
+    }
 }
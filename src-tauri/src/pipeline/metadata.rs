@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The commonly used fields of `Message.metadata`, parsed leniently.
+///
+/// The raw metadata stays a `serde_json::Value` in `json_parser::Message` because its shape
+/// changes between export versions; this view picks out what we store. Every field is
+/// optional and a field with an unexpected type is treated as absent rather than an error.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MessageMetadata {
+    /// Model that produced an assistant message, e.g. "gpt-4o", "o1-preview".
+    pub model_slug: Option<String>,
+    /// Model selected in the picker when it differs from the one that answered (auto-switching).
+    pub default_model_slug: Option<String>,
+    /// `finish_details.type`: "stop", "max_tokens", "interrupted", …
+    pub finish_type: Option<String>,
+    /// Files the user attached to the message.
+    pub attachments: Vec<Attachment>,
+    /// Sources cited by a browsing or search answer.
+    pub citations: Vec<Citation>,
+    /// Plugin namespace for legacy plugin calls (`invoked_plugin.namespace`).
+    pub invoked_plugin: Option<String>,
+    /// File ids of images a code-interpreter run produced (`aggregate_result.messages[].image_url`).
+    pub generated_images: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: String,
+    pub name: Option<String>,
+    pub mime_type: Option<String>,
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Citation {
+    pub title: Option<String>,
+    pub url: String,
+}
+
+impl MessageMetadata {
+    pub fn from_value(metadata: &Value) -> MessageMetadata {
+        let string = |v: &Value| v.as_str().filter(|s| !s.is_empty()).map(String::from);
+
+        let attachments = metadata["attachments"]
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|a| {
+                        Some(Attachment {
+                            id: string(&a["id"])?,
+                            name: string(&a["name"]),
+                            mime_type: string(&a["mime_type"]),
+                            size: a["size"].as_u64(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Older exports: citations[].metadata.{title,url}; newer: content_references[].items[]
+        let mut citations: Vec<Citation> = Vec::new();
+        let mut cite = |title: Option<String>, url: Option<String>| {
+            if let Some(url) = url {
+                if !citations.iter().any(|c| c.url == url) {
+                    citations.push(Citation { title, url });
+                }
+            }
+        };
+        for c in metadata["citations"].as_array().into_iter().flatten() {
+            let meta = &c["metadata"];
+            cite(string(&meta["title"]), string(&meta["url"]));
+        }
+        for r in metadata["content_references"].as_array().into_iter().flatten() {
+            for item in r["items"].as_array().into_iter().flatten() {
+                cite(string(&item["title"]), string(&item["url"]));
+            }
+        }
+
+        let generated_images = metadata["aggregate_result"]["messages"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|m| m["image_url"].as_str())
+            .filter_map(|url| url.split("://").nth(1))
+            .filter(|id| !id.is_empty())
+            .map(String::from)
+            .collect();
+
+        MessageMetadata {
+            model_slug: string(&metadata["model_slug"]),
+            default_model_slug: string(&metadata["default_model_slug"]),
+            finish_type: string(&metadata["finish_details"]["type"]),
+            attachments,
+            citations,
+            invoked_plugin: string(&metadata["invoked_plugin"]["namespace"]),
+            generated_images,
+        }
+    }
+}
+
+/// Broad category of a tool invocation, used for filtering and reporting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ToolCategory {
+    Browsing,
+    CodeInterpreter,
+    ImageGeneration,
+    FileSearch,
+    Memory,
+    Canvas,
+    Plugin,
+    Other,
+}
+
+impl ToolCategory {
+    /// Classifies a tool name as it appears in `author.name` / `recipient`.
+    pub fn classify(tool: &str) -> ToolCategory {
+        let namespace = tool.split('.').next().unwrap_or(tool);
+        match namespace {
+            "browser" | "web" | "search" => ToolCategory::Browsing,
+            "python" | "jupyter" => ToolCategory::CodeInterpreter,
+            "dalle" | "t2uay3k" | "image_gen" => ToolCategory::ImageGeneration,
+            "myfiles_browser" | "file_search" => ToolCategory::FileSearch,
+            "bio" => ToolCategory::Memory,
+            "canmore" => ToolCategory::Canvas,
+            _ => ToolCategory::Other,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ToolCategory::Browsing => "browsing",
+            ToolCategory::CodeInterpreter => "codeInterpreter",
+            ToolCategory::ImageGeneration => "imageGeneration",
+            ToolCategory::FileSearch => "fileSearch",
+            ToolCategory::Memory => "memory",
+            ToolCategory::Canvas => "canvas",
+            ToolCategory::Plugin => "plugin",
+            ToolCategory::Other => "other",
+        }
+    }
+
+    /// Human-readable name for INDEX.md.
+    pub fn label(self) -> &'static str {
+        match self {
+            ToolCategory::Browsing => "Browsing",
+            ToolCategory::CodeInterpreter => "Code interpreter",
+            ToolCategory::ImageGeneration => "DALL-E / image generation",
+            ToolCategory::FileSearch => "File search",
+            ToolCategory::Memory => "Memory",
+            ToolCategory::Canvas => "Canvas",
+            ToolCategory::Plugin => "Plugins",
+            ToolCategory::Other => "Other tools",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<ToolCategory> {
+        [
+            ToolCategory::Browsing,
+            ToolCategory::CodeInterpreter,
+            ToolCategory::ImageGeneration,
+            ToolCategory::FileSearch,
+            ToolCategory::Memory,
+            ToolCategory::Canvas,
+            ToolCategory::Plugin,
+            ToolCategory::Other,
+        ]
+        .into_iter()
+        .find(|c| c.as_str() == s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_the_stored_fields() {
+        let meta = MessageMetadata::from_value(&json!({
+            "model_slug": "gpt-4o",
+            "default_model_slug": "auto",
+            "finish_details": { "type": "stop" },
+            "attachments": [
+                { "id": "file-1", "name": "notes.pdf", "mime_type": "application/pdf", "size": 2048 },
+                { "name": "no id is skipped" }
+            ],
+            "invoked_plugin": { "namespace": "weather" },
+            "aggregate_result": { "messages": [
+                { "image_url": "file-service://file-img" },
+                { "message_type": "stream" }
+            ]}
+        }));
+        assert_eq!(meta.model_slug.as_deref(), Some("gpt-4o"));
+        assert_eq!(meta.default_model_slug.as_deref(), Some("auto"));
+        assert_eq!(meta.finish_type.as_deref(), Some("stop"));
+        assert_eq!(
+            meta.attachments,
+            vec![Attachment {
+                id: "file-1".into(),
+                name: Some("notes.pdf".into()),
+                mime_type: Some("application/pdf".into()),
+                size: Some(2048),
+            }]
+        );
+        assert_eq!(meta.invoked_plugin.as_deref(), Some("weather"));
+        assert_eq!(meta.generated_images, vec!["file-img"]);
+    }
+
+    #[test]
+    fn merges_old_and_new_citations_without_duplicates() {
+        let meta = MessageMetadata::from_value(&json!({
+            "citations": [{ "metadata": { "title": "Docs", "url": "https://a.example" } }],
+            "content_references": [{ "items": [
+                { "title": "Docs again", "url": "https://a.example" },
+                { "url": "https://b.example" }
+            ]}]
+        }));
+        assert_eq!(
+            meta.citations,
+            vec![
+                Citation { title: Some("Docs".into()), url: "https://a.example".into() },
+                Citation { title: None, url: "https://b.example".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn unexpected_types_and_empty_strings_are_absent() {
+        let meta = MessageMetadata::from_value(&json!({
+            "model_slug": "",
+            "finish_details": "stop",
+            "attachments": { "id": "not-a-list" },
+            "citations": [{ "metadata": { "title": "No url" } }]
+        }));
+        assert_eq!(meta, MessageMetadata::default());
+        assert_eq!(MessageMetadata::from_value(&Value::Null), MessageMetadata::default());
+    }
+
+    #[test]
+    fn classifies_tools_by_namespace() {
+        assert_eq!(ToolCategory::classify("browser"), ToolCategory::Browsing);
+        assert_eq!(ToolCategory::classify("web.run"), ToolCategory::Browsing);
+        assert_eq!(ToolCategory::classify("python"), ToolCategory::CodeInterpreter);
+        assert_eq!(ToolCategory::classify("dalle.text2im"), ToolCategory::ImageGeneration);
+        assert_eq!(ToolCategory::classify("bio"), ToolCategory::Memory);
+        assert_eq!(ToolCategory::classify("canmore.create_textdoc"), ToolCategory::Canvas);
+        assert_eq!(ToolCategory::classify("unknown_tool"), ToolCategory::Other);
+        for category in [ToolCategory::Plugin, ToolCategory::FileSearch, ToolCategory::Other] {
+            assert_eq!(ToolCategory::parse(category.as_str()), Some(category));
+        }
+        assert_eq!(ToolCategory::parse("nope"), None);
+    }
+}
@@ -1,4 +1,11 @@
+pub mod feedback;
+pub mod format;
+pub mod group_chats;
 pub mod json_parser;
+pub mod metadata;
 pub mod normalizer;
+pub mod profile;
+pub mod report;
+pub mod shares;
+pub mod source;
 pub mod traversal;
-pub mod zip_reader;
@@ -1,75 +1,228 @@
-use crate::pipeline::json_parser::{Content, ConversationExport};
-use crate::pipeline::traversal::linearize_messages;
+use crate::dates;
+use crate::pipeline::json_parser::{Content, ConversationExport, Message};
+use crate::pipeline::metadata::{Attachment, Citation, MessageMetadata, ToolCategory};
+use crate::pipeline::profile::{extract_profile, ProfileEntry};
+use crate::pipeline::traversal::{should_include_message, walk_branch, InclusionPolicy, MessageKind};
+
+/// Whether a conversation is a regular one-on-one chat or a group chat.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConversationKind {
+    #[default]
+    Chat,
+    /// From group_chats.json — several people and ChatGPT in one thread.
+    Group,
+}
+
+impl ConversationKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConversationKind::Chat => "chat",
+            ConversationKind::Group => "group",
+        }
+    }
+
+    /// Rows written before `kind` was stored (NULL) are regular chats.
+    pub fn parse(s: Option<&str>) -> ConversationKind {
+        match s {
+            Some("group") => ConversationKind::Group,
+            _ => ConversationKind::Chat,
+        }
+    }
+}
 
 /// Flat record ready for SQLite insert.
+#[derive(Default)]
 pub struct ConversationRecord {
     pub id: String,
+    pub kind: ConversationKind,
     pub title: String,
     pub created_at: Option<i64>,
+    /// `update_time` from the export — bumped whenever the thread is continued.
+    pub updated_at: Option<i64>,
+    pub first_message_at: Option<i64>,
+    pub last_message_at: Option<i64>,
     pub message_count: u32,
     pub has_images: bool,
     pub has_code: bool,
     pub token_estimate: u32,
     pub full_text: String,
     pub gizmo_id: Option<String>,
+    pub messages: Vec<MessageRecord>,
+    /// Custom instructions and memories seen in this conversation (see `extract_profile`).
+    pub profile: Vec<ProfileEntry>,
+    /// Tools invoked on the current branch, in order.
+    pub tool_calls: Vec<ToolCall>,
+    /// Display names of the people in a group chat; empty for regular chats.
+    pub participants: Vec<String>,
+}
+
+/// One message of the branch, in conversation order — chat turns plus the tool calls and
+/// outputs an `InclusionPolicy` may show at export.
+pub struct MessageRecord {
+    pub id: String,
+    pub role: String,
+    /// Who wrote the message in a group chat; `None` in regular chats.
+    pub author_name: Option<String>,
+    pub kind: MessageKind,
+    /// Tool addressed (`ToolCall`) or answering (`ToolOutput`), e.g. "python", "dalle.text2im".
+    pub tool: Option<String>,
+    pub content_type: String,
+    pub created_at: Option<i64>,
+    pub text: String,
+    /// `metadata.model_slug` on assistant messages (e.g. "gpt-4o", "o1-preview").
+    pub model_slug: Option<String>,
+    /// File ids of images referenced by this message (see `extract_asset_ids`).
+    pub assets: Vec<String>,
+    /// `metadata.finish_details.type` ("stop", "max_tokens", "interrupted", …).
+    pub finish_type: Option<String>,
+    pub attachments: Vec<Attachment>,
+    pub citations: Vec<Citation>,
+}
+
+/// One tool invocation: an assistant message addressed to a tool (`recipient`).
+pub struct ToolCall {
+    pub message_id: String,
+    /// Tool name as ChatGPT records it, e.g. "python", "dalle.text2im", "web.run".
+    pub tool: String,
+    pub category: ToolCategory,
+    pub created_at: Option<i64>,
 }
 
 /// Normalizes a `ConversationExport` to a `ConversationRecord`.
 ///
-/// Uses `linearize_messages` (IMP-06) to walk current_node → parent chain for
+/// Walks the current_node → parent chain (IMP-06, see `linearize_messages`) for
 /// correct chronological order and branch selection — not mapping.values() iteration
-/// which would produce wrong order and include all branches.
+/// which would produce wrong order and include all branches. Tool calls are read from
+/// the whole branch; the transcript keeps only the messages `should_include_message` accepts.
 pub fn normalize(export: ConversationExport) -> ConversationRecord {
+    let profile = extract_profile(&export);
+
     let title = export
         .title
         .filter(|t| !t.is_empty())
         .unwrap_or_else(|| "Untitled".to_string());
 
-    let created_at = export.create_time.map(|t| t as i64);
+    let created_at = export.create_time.map(dates::from_export_time);
+    let updated_at = export.update_time.map(dates::from_export_time);
 
-    let messages = if let Some(ref current_node) = export.current_node {
-        linearize_messages(&export.mapping, current_node)
+    let branch = if let Some(ref current_node) = export.current_node {
+        walk_branch(&export.mapping, current_node)
     } else {
         vec![] // No current_node — skip traversal, store empty
     };
+    let tool_calls = extract_tool_calls(&branch);
+    // Store everything any export policy could show; the transcript stats use the default one
+    let everything = InclusionPolicy::everything();
+    let messages: Vec<Message> = branch.into_iter().filter(|m| everything.includes_message(m)).collect();
 
-    let message_count = messages.len() as u32;
+    let mut message_count = 0;
     let mut has_images = false;
     let mut has_code = false;
     let mut full_text = String::new();
+    let mut message_records = Vec::with_capacity(messages.len());
 
     for msg in &messages {
         if let Some(ref content) = msg.content {
-            let extracted = extract_text(content);
-            if !extracted.is_empty() {
-                full_text.push_str(&extracted);
-                full_text.push('\n');
-            }
-            if content.content_type == "multimodal_text" {
-                has_images = true;
+            let kind = MessageKind::of(msg);
+            let extracted = match kind {
+                MessageKind::Message => extract_text(content),
+                MessageKind::ToolCall | MessageKind::ToolOutput => extract_tool_text(content),
+            };
+            if should_include_message(msg) {
+                message_count += 1;
+                if !extracted.is_empty() {
+                    full_text.push_str(&extracted);
+                    full_text.push('\n');
+                }
+                if content.content_type == "multimodal_text" {
+                    has_images = true;
+                }
             }
             if content.content_type == "code" {
                 has_code = true;
             }
+            let metadata = MessageMetadata::from_value(&msg.metadata);
+            let mut assets = extract_asset_ids(content);
+            assets.extend(metadata.generated_images.iter().cloned());
+            message_records.push(MessageRecord {
+                id: msg.id.clone(),
+                role: msg.author.role.clone(),
+                author_name: None,
+                kind,
+                tool: match kind {
+                    MessageKind::Message => None,
+                    MessageKind::ToolCall => msg.recipient.clone(),
+                    MessageKind::ToolOutput => msg.author.name.clone(),
+                },
+                content_type: content.content_type.clone(),
+                created_at: msg.create_time.map(dates::from_export_time),
+                text: extracted,
+                model_slug: metadata.model_slug,
+                assets,
+                finish_type: metadata.finish_type,
+                attachments: metadata.attachments,
+                citations: metadata.citations,
+            });
         }
     }
 
+    let message_times = message_records.iter().filter_map(|m| m.created_at);
+    let first_message_at = message_times.clone().min();
+    let last_message_at = message_times.max();
+
     // Rough token estimate: ~4 chars per token
     let token_estimate = (full_text.len() / 4) as u32;
 
     ConversationRecord {
         id: export.id,
+        kind: ConversationKind::Chat,
         title,
         created_at,
+        updated_at,
+        first_message_at,
+        last_message_at,
         message_count,
         has_images,
         has_code,
         token_estimate,
         full_text,
         gizmo_id: export.gizmo_id,
+        messages: message_records,
+        profile,
+        tool_calls,
+        participants: Vec::new(),
     }
 }
 
+/// Collects tool invocations from a branch: assistant messages with a `recipient` other
+/// than "all". Calls to a namespace that answered with `invoked_plugin` metadata are
+/// reported as plugins.
+fn extract_tool_calls(branch: &[Message]) -> Vec<ToolCall> {
+    let plugins: Vec<String> = branch
+        .iter()
+        .filter_map(|m| MessageMetadata::from_value(&m.metadata).invoked_plugin)
+        .collect();
+
+    branch
+        .iter()
+        .filter(|m| m.author.role == "assistant")
+        .filter_map(|m| {
+            let tool = m.recipient.as_deref().filter(|r| !r.is_empty() && *r != "all")?;
+            let namespace = tool.split('.').next().unwrap_or(tool);
+            let category = match ToolCategory::classify(tool) {
+                ToolCategory::Other if plugins.iter().any(|p| p == namespace) => ToolCategory::Plugin,
+                category => category,
+            };
+            Some(ToolCall {
+                message_id: m.id.clone(),
+                tool: tool.to_string(),
+                category,
+                created_at: m.create_time.map(dates::from_export_time),
+            })
+        })
+        .collect()
+}
+
 /// Extracts plain-text strings from Content parts.
 /// Parts can be strings, objects, or null — only String variants are included.
 fn extract_text(content: &Content) -> String {
@@ -80,3 +233,29 @@ fn extract_text(content: &Content) -> String {
         .collect::<Vec<_>>()
         .join(" ")
 }
+
+/// Extracts the payload of a tool call or tool output: code, stdout, or a browsing result,
+/// falling back to the string parts (DALL-E prompts, quotes, plugin calls).
+fn extract_tool_text(content: &Content) -> String {
+    [&content.text, &content.result]
+        .into_iter()
+        .flatten()
+        .find(|t| !t.trim().is_empty())
+        .cloned()
+        .unwrap_or_else(|| extract_text(content))
+}
+
+/// Extracts the file ids of image parts (`{"content_type": "image_asset_pointer", ...}`).
+///
+/// Pointers look like `file-service://file-AbC123` (pre-2025) or `sediment://file_00ab…`;
+/// the id after `://` is also the prefix of the media file name inside the export ZIP.
+fn extract_asset_ids(content: &Content) -> Vec<String> {
+    content
+        .parts
+        .iter()
+        .filter_map(|part| part.get("asset_pointer")?.as_str())
+        .filter_map(|pointer| pointer.split("://").nth(1))
+        .filter(|id| !id.is_empty())
+        .map(String::from)
+        .collect()
+}
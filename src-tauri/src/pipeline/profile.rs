@@ -0,0 +1,218 @@
+use crate::dates;
+use crate::pipeline::json_parser::{ConversationExport, Message};
+
+/// What a piece of profile content is, as stored in `profile_entries.kind`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfileKind {
+    /// "What would you like ChatGPT to know about you?"
+    AboutUser,
+    /// "How would you like ChatGPT to respond?"
+    ResponseStyle,
+    /// One saved memory ("bio" tool).
+    Memory,
+}
+
+impl ProfileKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProfileKind::AboutUser => "about_user",
+            ProfileKind::ResponseStyle => "response_style",
+            ProfileKind::Memory => "memory",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<ProfileKind> {
+        match s {
+            "about_user" => Some(ProfileKind::AboutUser),
+            "response_style" => Some(ProfileKind::ResponseStyle),
+            "memory" => Some(ProfileKind::Memory),
+            _ => None,
+        }
+    }
+}
+
+/// Custom-instruction or memory text observed in one conversation.
+pub struct ProfileEntry {
+    pub kind: ProfileKind,
+    pub text: String,
+    /// When this conversation saw it — message time, falling back to the conversation's.
+    pub seen_at: Option<i64>,
+}
+
+/// Collects custom instructions and memories from every node of a conversation.
+///
+/// These live in messages `linearize_messages` drops: the hidden system/user message at
+/// the root carrying `user_editable_context` (or `metadata.user_context_message_data` in
+/// older exports), `model_editable_context` with the memory snapshot, and assistant
+/// messages addressed to the `bio` tool when ChatGPT saves a new memory. The whole
+/// mapping is scanned, not just the current branch — the instructions sit above the fork.
+pub fn extract_profile(export: &ConversationExport) -> Vec<ProfileEntry> {
+    let conversation_time = export.create_time.map(dates::from_export_time);
+    let mut entries: Vec<ProfileEntry> = Vec::new();
+    let mut push = |kind: ProfileKind, text: &str, seen_at: Option<i64>| {
+        let text = text.trim();
+        if !text.is_empty() && !entries.iter().any(|e| e.kind == kind && e.text == text) {
+            entries.push(ProfileEntry { kind, text: text.to_string(), seen_at });
+        }
+    };
+
+    for node in export.mapping.values() {
+        let Some(ref msg) = node.message else { continue };
+        let seen_at = msg.create_time.map(dates::from_export_time).or(conversation_time);
+
+        if let Some(ref content) = msg.content {
+            match content.content_type.as_str() {
+                "user_editable_context" => {
+                    if let Some(ref profile) = content.user_profile {
+                        push(ProfileKind::AboutUser, &fenced_body(profile), seen_at);
+                    }
+                    if let Some(ref instructions) = content.user_instructions {
+                        push(ProfileKind::ResponseStyle, &fenced_body(instructions), seen_at);
+                    }
+                }
+                "model_editable_context" => {
+                    for memory in content.model_set_context.as_deref().map(memory_lines).unwrap_or_default() {
+                        push(ProfileKind::Memory, &memory, seen_at);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let context = &msg.metadata["user_context_message_data"];
+        if let Some(about) = context["about_user_message"].as_str() {
+            push(ProfileKind::AboutUser, about, seen_at);
+        }
+        if let Some(style) = context["about_model_message"].as_str() {
+            push(ProfileKind::ResponseStyle, style, seen_at);
+        }
+
+        if is_memory_write(msg) {
+            let text = msg
+                .content
+                .iter()
+                .flat_map(|c| c.parts.iter())
+                .filter_map(|p| p.as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            push(ProfileKind::Memory, &text, seen_at);
+        }
+    }
+
+    entries
+}
+
+/// An assistant message addressed to the memory tool — its text is the memory saved.
+fn is_memory_write(msg: &Message) -> bool {
+    msg.author.role == "assistant" && msg.recipient.as_deref() == Some("bio")
+}
+
+/// ChatGPT wraps custom instructions in a preamble and a ``` fence; keep only the user's text.
+fn fenced_body(s: &str) -> String {
+    match (s.find("```"), s.rfind("```")) {
+        (Some(start), Some(end)) if end > start => s[start + 3..end].trim().to_string(),
+        _ => s.trim().to_string(),
+    }
+}
+
+/// Splits a memory snapshot (`1. [2024-05-01]. Prefers Rust.` per line) into memories.
+fn memory_lines(snapshot: &str) -> Vec<String> {
+    snapshot
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            let line = line
+                .split_once(". ")
+                .filter(|(n, _)| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+                .map_or(line, |(_, rest)| rest);
+            let line = line
+                .strip_prefix('[')
+                .and_then(|rest| rest.split_once(']'))
+                .map_or(line, |(_, rest)| rest.trim_start_matches('.'));
+            line.trim().to_string()
+        })
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn export(mapping: serde_json::Value) -> ConversationExport {
+        serde_json::from_value(json!({ "id": "c1", "title": "T", "create_time": 1700000000.5, "mapping": mapping }))
+            .unwrap()
+    }
+
+    fn texts(entries: &[ProfileEntry], kind: ProfileKind) -> Vec<&str> {
+        entries.iter().filter(|e| e.kind == kind).map(|e| e.text.as_str()).collect()
+    }
+
+    #[test]
+    fn extracts_instructions_memories_and_bio_writes_from_every_node() {
+        let entries = extract_profile(&export(json!({
+            "root": { "id": "root", "children": ["ctx"] },
+            "ctx": { "id": "ctx", "parent": "root", "children": ["mem", "old"], "message": {
+                "id": "ctx", "author": { "role": "user" }, "create_time": 1700000100.0,
+                "content": {
+                    "content_type": "user_editable_context",
+                    "user_profile": "The user provided the following information about themselves:\n```I live in Lisbon.```",
+                    "user_instructions": "```Answer briefly.```"
+                }
+            }},
+            "mem": { "id": "mem", "parent": "ctx", "children": [], "message": {
+                "id": "mem", "author": { "role": "user" },
+                "content": { "content_type": "model_editable_context", "model_set_context": "1. [2024-05-01]. Prefers Rust.\n2. Has a cat." }
+            }},
+            // An abandoned branch, still scanned: legacy metadata and a memory write
+            "old": { "id": "old", "parent": "ctx", "children": [], "message": {
+                "id": "old", "author": { "role": "assistant" }, "recipient": "bio",
+                "content": { "content_type": "text", "parts": ["Is learning Portuguese."] },
+                "metadata": { "user_context_message_data": { "about_user_message": "I live in Lisbon." } }
+            }}
+        })));
+
+        assert_eq!(texts(&entries, ProfileKind::AboutUser), vec!["I live in Lisbon."]);
+        assert_eq!(texts(&entries, ProfileKind::ResponseStyle), vec!["Answer briefly."]);
+        let mut memories = texts(&entries, ProfileKind::Memory);
+        memories.sort();
+        assert_eq!(memories, vec!["Has a cat.", "Is learning Portuguese.", "Prefers Rust."]);
+
+        // Message time, falling back to the conversation's
+        let style = entries.iter().find(|e| e.kind == ProfileKind::ResponseStyle).unwrap();
+        assert_eq!(style.seen_at, Some(1_700_000_100));
+        let cat = entries.iter().find(|e| e.text == "Has a cat.").unwrap();
+        assert_eq!(cat.seen_at, Some(1_700_000_000));
+    }
+
+    #[test]
+    fn ignores_regular_messages() {
+        let entries = extract_profile(&export(json!({
+            "a": { "id": "a", "children": [], "message": {
+                "id": "a", "author": { "role": "assistant" }, "recipient": "python",
+                "content": { "content_type": "text", "parts": ["print(1)"] }
+            }}
+        })));
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn fenced_body_keeps_the_text_between_the_outer_fences() {
+        assert_eq!(fenced_body("Preamble:\n```\nBe terse.\n```"), "Be terse.");
+        assert_eq!(fenced_body("```Use ```code``` blocks```"), "Use ```code``` blocks");
+        assert_eq!(fenced_body("  no fence  "), "no fence");
+        // A lone fence is not a pair
+        assert_eq!(fenced_body("``` unterminated"), "``` unterminated");
+    }
+
+    #[test]
+    fn memory_lines_strip_numbers_and_dates() {
+        let snapshot = "1. [2024-05-01]. Prefers Rust.\n\n2. Has a cat.\n[2023-01-02] Works remotely.\nv2. is a version";
+        assert_eq!(
+            memory_lines(snapshot),
+            vec!["Prefers Rust.", "Has a cat.", "Works remotely.", "v2. is a version"]
+        );
+        assert!(memory_lines("  \n").is_empty());
+    }
+}
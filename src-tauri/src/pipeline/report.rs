@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// Conversations an import skipped or could only partially read, returned with
+/// `IngestEvent::Complete` so the user knows exactly what is missing from the archive.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    /// Conversations (or whole shards, when `conversation_id` is `None`) that were not imported.
+    pub skipped: Vec<ImportIssue>,
+    /// Conversations imported with some of their data repaired or dropped.
+    pub recovered: Vec<ImportIssue>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportIssue {
+    pub conversation_id: Option<String>,
+    /// File the conversation came from, e.g. "conversations-002.json".
+    pub shard: String,
+    pub reason: String,
+}
+
+impl ImportReport {
+    pub fn skip(&mut self, conversation_id: Option<String>, shard: &str, reason: impl Into<String>) {
+        self.skipped.push(ImportIssue { conversation_id, shard: shard.to_string(), reason: reason.into() });
+    }
+
+    pub fn recover(&mut self, conversation_id: String, shard: &str, reason: impl Into<String>) {
+        self.recovered.push(ImportIssue {
+            conversation_id: Some(conversation_id),
+            shard: shard.to_string(),
+            reason: reason.into(),
+        });
+    }
+}
@@ -0,0 +1,76 @@
+use serde::Deserialize;
+
+/// Public share links are served from this prefix followed by the share id.
+pub const SHARE_URL_PREFIX: &str = "https://chatgpt.com/share/";
+
+/// One entry of `shared_conversations.json`.
+#[derive(Deserialize)]
+struct SharedConversationExport {
+    id: String,
+    conversation_id: Option<String>,
+    title: Option<String>,
+    #[serde(default)]
+    is_anonymous: bool,
+}
+
+/// A public share link, tied back to the conversation it was created from.
+pub struct ShareRecord {
+    pub id: String,
+    pub conversation_id: String,
+    pub title: Option<String>,
+    pub url: String,
+    /// Shared without the user's name.
+    pub anonymous: bool,
+}
+
+/// Parses `shared_conversations.json`. Entries without a conversation id are skipped.
+pub fn parse_shared_conversations(bytes: &[u8]) -> Result<Vec<ShareRecord>, String> {
+    let entries: Vec<SharedConversationExport> = serde_json::from_slice(bytes)
+        .map_err(|e| format!("Failed to parse shared_conversations.json: {e}"))?;
+
+    Ok(entries
+        .into_iter()
+        .filter_map(|entry| {
+            Some(ShareRecord {
+                url: format!("{SHARE_URL_PREFIX}{}", entry.id),
+                conversation_id: entry.conversation_id.filter(|id| !id.is_empty())?,
+                id: entry.id,
+                title: entry.title,
+                anonymous: entry.is_anonymous,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_shared_conversations_json() {
+        let json = r#"[
+            {"id": "6651a2b3-1111-8000-9000-aaaaaaaaaaaa", "conversation_id": "664f0c1d-2222-8000-9000-bbbbbbbbbbbb",
+             "title": "Sourdough starter", "is_anonymous": true},
+            {"id": "6651a2b3-3333-8000-9000-cccccccccccc", "conversation_id": "664f0c1d-4444-8000-9000-dddddddddddd",
+             "title": null, "is_anonymous": false},
+            {"id": "6651a2b3-5555-8000-9000-eeeeeeeeeeee", "conversation_id": null, "title": "Deleted", "is_anonymous": true},
+            {"id": "6651a2b3-6666-8000-9000-ffffffffffff", "conversation_id": "", "title": "Blank"},
+            {"id": "6651a2b3-7777-8000-9000-000000000000", "conversation_id": "664f0c1d-8888-8000-9000-111111111111",
+             "title": "Older export without is_anonymous"}
+        ]"#;
+        let shares = parse_shared_conversations(json.as_bytes()).unwrap();
+        assert_eq!(shares.len(), 3);
+
+        assert_eq!(shares[0].id, "6651a2b3-1111-8000-9000-aaaaaaaaaaaa");
+        assert_eq!(shares[0].conversation_id, "664f0c1d-2222-8000-9000-bbbbbbbbbbbb");
+        assert_eq!(shares[0].url, "https://chatgpt.com/share/6651a2b3-1111-8000-9000-aaaaaaaaaaaa");
+        assert_eq!(shares[0].title.as_deref(), Some("Sourdough starter"));
+        assert!(shares[0].anonymous);
+
+        assert_eq!(shares[1].title, None);
+        assert!(!shares[1].anonymous);
+        assert!(!shares[2].anonymous);
+
+        assert!(parse_shared_conversations(b"{\"id\": \"x\"}").is_err());
+    }
+}
@@ -0,0 +1,468 @@
+use sha2::{Digest, Sha256};
+use std::io::{Cursor, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Read granularity for decompressing conversation entries (one progress callback per block).
+const READ_BLOCK_SIZE: usize = 4 * 1024 * 1024;
+
+/// ZIPs inside ZIPs are followed this many levels deep.
+const MAX_NESTING: usize = 3;
+
+trait ReadSeek: Read + Seek {}
+impl<T: Read + Seek> ReadSeek for T {}
+
+type Archive = zip::ZipArchive<Box<dyn ReadSeek>>;
+
+/// A ChatGPT export, wherever its files live.
+///
+/// Users hand us the export in several shapes:
+///
+/// - the ZIP as downloaded;
+/// - a folder they already extracted it into;
+/// - just `conversations.json` (or one `conversations-NNN.json` shard);
+/// - a ZIP or folder containing further ZIPs — a download that was re-zipped, or an
+///   export that arrived in several parts. Put every part in one folder and pick the folder.
+///
+/// All of them are flattened into one list of entries named by their path inside the
+/// export, so the pipeline never needs to know which shape it was given. Files under
+/// `__MACOSX/` and `._*` Apple double files are skipped.
+pub struct ExportSource {
+    containers: Vec<Container>,
+    entries: Vec<Entry>,
+}
+
+enum Container {
+    /// An extracted export; entry names are paths relative to this folder.
+    Directory(PathBuf),
+    /// A ZIP on disk.
+    Zip(PathBuf),
+    /// A ZIP inside another ZIP, decompressed once when the source is opened (the format
+    /// needs random access) and kept in memory, so reading from it again is cheap.
+    NestedZip(Arc<[u8]>),
+    /// A bare JSON file.
+    File(PathBuf),
+}
+
+struct Entry {
+    name: String,
+    size: u64,
+    container: usize,
+}
+
+/// One conversations JSON file from the export, held in memory.
+pub struct ConversationShard {
+    /// File name without its directory, e.g. "conversations-001.json".
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+/// The ChatGPT account an export belongs to, from its `user.json`.
+#[derive(Debug, Default, Clone)]
+pub struct Account {
+    pub email: Option<String>,
+    pub name: Option<String>,
+}
+
+impl Account {
+    /// How the account is identified in filters: its email, or its name for exports without one.
+    pub fn label(&self) -> Option<&str> {
+        self.email.as_deref().or(self.name.as_deref())
+    }
+}
+
+impl ExportSource {
+    /// Opens a ZIP, an export folder or a bare JSON file.
+    pub fn open(path: &str) -> Result<ExportSource, String> {
+        let path = Path::new(path);
+        let mut source = ExportSource { containers: Vec::new(), entries: Vec::new() };
+
+        if path.is_dir() {
+            source.add_directory(path)?;
+        } else if is_zip(path)? {
+            source.add_zip(path)?;
+        } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) {
+            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            // Whatever the file was renamed to, it is read as the conversations array
+            let name = if is_conversations_file(file_name) { file_name } else { "conversations.json" };
+            let size = std::fs::metadata(path).map_err(|e| format!("Cannot open {}: {e}", path.display()))?.len();
+            source.containers.push(Container::File(path.to_path_buf()));
+            source.entries.push(Entry { name: name.to_string(), size, container: 0 });
+        } else {
+            return Err("Unsupported input: choose the export ZIP, its extracted folder or conversations.json".to_string());
+        }
+
+        Ok(source)
+    }
+
+    /// Paths of every file in the export, including those inside nested ZIPs.
+    pub fn entry_names(&self) -> Vec<String> {
+        self.entries.iter().map(|e| e.name.clone()).collect()
+    }
+
+    /// Reads every `conversations.json` / `conversations-NNN.json` into memory, in shard order.
+    ///
+    /// `on_progress` is called with `(bytes_read, total_bytes)` as the files are read,
+    /// where `total_bytes` is the sum of their uncompressed sizes.
+    pub fn read_conversation_shards(
+        &self,
+        on_progress: &mut dyn FnMut(u64, u64),
+    ) -> Result<Vec<ConversationShard>, String> {
+        let is_shard = |name: &str| is_conversations_file(basename(name));
+        if !self.entries.iter().any(|e| is_shard(&e.name)) {
+            return Err("conversations.json not found in export".to_string());
+        }
+        let total_bytes: u64 = self.entries.iter().filter(|e| is_shard(&e.name)).map(|e| e.size).sum();
+
+        let mut bytes_read: u64 = 0;
+        on_progress(0, total_bytes);
+
+        let mut shards: Vec<ConversationShard> = Vec::new();
+        self.for_each(is_shard, |name, reader| {
+            let mut buf = Vec::new();
+            let mut block = vec![0u8; READ_BLOCK_SIZE];
+            loop {
+                let n = reader.read(&mut block).map_err(|e| format!("Cannot read {name}: {e}"))?;
+                if n == 0 {
+                    break;
+                }
+                buf.extend_from_slice(&block[..n]);
+                bytes_read += n as u64;
+                on_progress(bytes_read, total_bytes);
+            }
+            shards.push(ConversationShard { name: basename(name).to_string(), bytes: buf });
+            Ok(())
+        })?;
+
+        // Parse in order: a legacy conversations.json first, then conversations-000, 001, 002, …
+        // by number ('-' sorts before '.', and shard numbers may not be zero-padded)
+        shards.sort_by_key(|shard| (shard_number(&shard.name), shard.name.clone()));
+        Ok(shards)
+    }
+
+    /// Reads a single top-level export file such as `message_feedback.json` or `chat.html`,
+    /// wherever it sits in the export. Returns `None` when the export doesn't contain it.
+    pub fn read_optional(&self, file_name: &str) -> Result<Option<Vec<u8>>, String> {
+        let Some(wanted) = self.entries.iter().find(|e| basename(&e.name) == file_name) else {
+            return Ok(None);
+        };
+        let mut buf = None;
+        self.for_each(
+            |name| name == wanted.name,
+            |name, reader| {
+                if buf.is_none() {
+                    let mut bytes = Vec::new();
+                    reader.read_to_end(&mut bytes).map_err(|e| format!("Cannot read {name}: {e}"))?;
+                    buf = Some(bytes);
+                }
+                Ok(())
+            },
+        )?;
+        Ok(buf)
+    }
+
+    /// The account from `user.json`; empty when the export doesn't include it (a bare
+    /// conversations.json) or it can't be read.
+    pub fn account(&self) -> Account {
+        let user: serde_json::Value = self
+            .read_optional("user.json")
+            .ok()
+            .flatten()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        let field = |key: &str| user[key].as_str().map(str::trim).filter(|v| !v.is_empty()).map(String::from);
+        Account { email: field("email"), name: field("name") }
+    }
+
+    /// Whether this is the export recorded with `hash` by `parse_zip`: the hash of its
+    /// conversation data, or of chat.html for exports that had to be recovered from it.
+    pub fn has_hash(&self, hash: &str) -> bool {
+        if let Ok(shards) = self.read_conversation_shards(&mut |_, _| {}) {
+            if sha256_hex(shards.iter().map(|shard| shard.bytes.as_slice())) == hash {
+                return true;
+            }
+        }
+        matches!(self.read_optional("chat.html"), Ok(Some(html)) if sha256_hex([html.as_slice()]) == hash)
+    }
+
+    /// Calls `visit` with a reader for every entry whose name passes `wanted`. Each ZIP is
+    /// opened once per call, not once per entry.
+    pub fn for_each(
+        &self,
+        wanted: impl Fn(&str) -> bool,
+        mut visit: impl FnMut(&str, &mut dyn Read) -> Result<(), String>,
+    ) -> Result<(), String> {
+        for (index, container) in self.containers.iter().enumerate() {
+            let names: Vec<&str> = self
+                .entries
+                .iter()
+                .filter(|e| e.container == index && wanted(&e.name))
+                .map(|e| e.name.as_str())
+                .collect();
+            if names.is_empty() {
+                continue;
+            }
+
+            let mut archive = match container {
+                Container::Directory(root) => {
+                    for name in names {
+                        let mut file = std::fs::File::open(root.join(name))
+                            .map_err(|e| format!("Cannot read {name}: {e}"))?;
+                        visit(name, &mut file)?;
+                    }
+                    continue;
+                }
+                Container::File(path) => {
+                    for name in names {
+                        let mut file = std::fs::File::open(path).map_err(|e| format!("Cannot read {name}: {e}"))?;
+                        visit(name, &mut file)?;
+                    }
+                    continue;
+                }
+                Container::Zip(path) => open_zip(path)?,
+                Container::NestedZip(bytes) => open_nested(bytes.clone(), "nested ZIP")?,
+            };
+            for name in names {
+                let mut entry = archive.by_name(name).map_err(|e| format!("Cannot read {name}: {e}"))?;
+                visit(name, &mut entry)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds every file under `root`. ZIPs found in the folder become containers of their own.
+    fn add_directory(&mut self, root: &Path) -> Result<(), String> {
+        let container = self.containers.len();
+        self.containers.push(Container::Directory(root.to_path_buf()));
+
+        let mut pending = vec![root.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            let listing = std::fs::read_dir(&dir).map_err(|e| format!("Cannot read {}: {e}", dir.display()))?;
+            for item in listing {
+                let path = item.map_err(|e| e.to_string())?.path();
+                let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+                if file_name == "__MACOSX" || file_name.starts_with("._") {
+                    continue;
+                }
+                if path.is_dir() {
+                    pending.push(path);
+                } else if is_zip(&path)? {
+                    self.add_zip(&path)?;
+                } else {
+                    let relative = path.strip_prefix(root).map_err(|e| e.to_string())?;
+                    let name = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+                    let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                    self.entries.push(Entry { name, size, container });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the entries of the ZIP at `path`, then of any ZIPs it contains.
+    fn add_zip(&mut self, path: &Path) -> Result<(), String> {
+        self.add_archive(open_zip(path)?, Container::Zip(path.to_path_buf()), 0)
+    }
+
+    /// Adds the entries of `archive`, read through `container`, then recurses into the ZIPs
+    /// it contains. `depth` counts the ZIPs `archive` is nested in.
+    fn add_archive(&mut self, mut archive: Archive, container: Container, depth: usize) -> Result<(), String> {
+        let index = self.containers.len();
+        self.containers.push(container);
+
+        let mut inner_zips: Vec<String> = Vec::new();
+        for i in 0..archive.len() {
+            let entry = archive.by_index(i).map_err(|e| e.to_string())?;
+            let name = entry.name().to_string();
+            if entry.is_dir() || name.starts_with("__MACOSX/") || name.contains("/__MACOSX/") || basename(&name).starts_with("._") {
+                continue;
+            }
+            if name.to_lowercase().ends_with(".zip") && depth < MAX_NESTING {
+                inner_zips.push(name);
+            } else {
+                self.entries.push(Entry { name, size: entry.size(), container: index });
+            }
+        }
+
+        for name in inner_zips {
+            let bytes: Arc<[u8]> = {
+                let mut entry = archive.by_name(&name).map_err(|e| format!("Cannot read {name}: {e}"))?;
+                let mut buf = Vec::with_capacity(entry.size() as usize);
+                entry.read_to_end(&mut buf).map_err(|e| format!("Cannot read {name}: {e}"))?;
+                buf.into()
+            };
+            let inner = open_nested(bytes.clone(), &name)?;
+            self.add_archive(inner, Container::NestedZip(bytes), depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Hex SHA-256 of `chunks` read back to back. Hashing the conversation data rather than
+/// the file means the same export is recognised whether it arrives zipped, re-zipped or extracted.
+pub fn sha256_hex<'a>(chunks: impl IntoIterator<Item = &'a [u8]>) -> String {
+    let mut hasher = Sha256::new();
+    for chunk in chunks {
+        hasher.update(chunk);
+    }
+    hasher.finalize().iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// `conversations.json` or one of the `conversations-NNN.json` shards.
+pub fn is_conversations_file(file_name: &str) -> bool {
+    file_name == "conversations.json" || is_shard_file(file_name)
+}
+
+/// A `conversations-NNN.json` shard of a sharded export; `NNN` is any run of digits.
+pub fn is_shard_file(file_name: &str) -> bool {
+    file_name
+        .strip_prefix("conversations-")
+        .and_then(|rest| rest.strip_suffix(".json"))
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Sort key placing `conversations.json` (`None`) before the shards, which follow by number.
+fn shard_number(file_name: &str) -> Option<u64> {
+    file_name.strip_prefix("conversations-")?.strip_suffix(".json")?.parse().ok()
+}
+
+fn basename(name: &str) -> &str {
+    name.rsplit('/').next().unwrap_or(name)
+}
+
+/// Recognises ZIPs by their magic number, so renamed downloads still open.
+fn is_zip(path: &Path) -> Result<bool, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("Cannot open {}: {e}", path.display()))?;
+    let mut magic = [0u8; 4];
+    Ok(file.read_exact(&mut magic).is_ok() && magic == *b"PK\x03\x04")
+}
+
+fn open_zip(path: &Path) -> Result<Archive, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("Cannot open ZIP: {e}"))?;
+    zip::ZipArchive::new(Box::new(file) as Box<dyn ReadSeek>).map_err(|e| format!("Invalid ZIP: {e}"))
+}
+
+/// Opens a nested ZIP from its decompressed bytes; `name` is its path in the outer ZIP.
+fn open_nested(bytes: Arc<[u8]>, name: &str) -> Result<Archive, String> {
+    zip::ZipArchive::new(Box::new(Cursor::new(bytes)) as Box<dyn ReadSeek>).map_err(|e| format!("Invalid ZIP {name}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    /// A fresh, empty folder under the system temp dir.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("source-test-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn zip_bytes(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, bytes) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(bytes).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn shard_names(source: &ExportSource) -> Vec<String> {
+        source.read_conversation_shards(&mut |_, _| {}).unwrap().into_iter().map(|s| s.name).collect()
+    }
+
+    #[test]
+    fn reads_an_extracted_folder_and_the_zips_in_it() {
+        let dir = temp_dir("folder");
+        std::fs::create_dir_all(dir.join("export/__MACOSX")).unwrap();
+        std::fs::write(dir.join("export/conversations-001.json"), "[]").unwrap();
+        std::fs::write(dir.join("export/user.json"), r#"{"email": " me@example.com ", "name": ""}"#).unwrap();
+        std::fs::write(dir.join("export/__MACOSX/conversations-009.json"), "junk").unwrap();
+        std::fs::write(dir.join("export/._conversations-008.json"), "junk").unwrap();
+        // The second part of a split export, still zipped
+        std::fs::write(dir.join("part-2.zip"), zip_bytes(&[("conversations-000.json", b"[1]")])).unwrap();
+
+        let source = ExportSource::open(dir.to_str().unwrap()).unwrap();
+        let mut names = source.entry_names();
+        names.sort();
+        assert_eq!(names, vec!["conversations-000.json", "export/conversations-001.json", "export/user.json"]);
+        assert_eq!(shard_names(&source), vec!["conversations-000.json", "conversations-001.json"]);
+        assert_eq!(source.account().label(), Some("me@example.com"));
+        assert_eq!(source.read_optional("chat.html").unwrap(), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn shards_sort_after_a_legacy_file_and_by_number() {
+        let dir = temp_dir("order");
+        for name in ["conversations-10.json", "conversations-9.json", "conversations.json", "conversations-backup.json"] {
+            std::fs::write(dir.join(name), "[]").unwrap();
+        }
+        let source = ExportSource::open(dir.to_str().unwrap()).unwrap();
+        assert_eq!(shard_names(&source), vec!["conversations.json", "conversations-9.json", "conversations-10.json"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reads_a_bare_json_file_under_any_name() {
+        let dir = temp_dir("bare");
+        let path = dir.join("my backup.JSON");
+        std::fs::write(&path, r#"[{"id": "c1"}]"#).unwrap();
+
+        let source = ExportSource::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(source.entry_names(), vec!["conversations.json"]);
+        let shards = source.read_conversation_shards(&mut |_, _| {}).unwrap();
+        assert_eq!(shards[0].bytes, br#"[{"id": "c1"}]"#);
+        assert!(source.account().label().is_none());
+
+        std::fs::write(dir.join("notes.txt"), "hello").unwrap();
+        let err = ExportSource::open(dir.join("notes.txt").to_str().unwrap()).err().unwrap();
+        assert!(err.starts_with("Unsupported input"), "{err}");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reads_nested_zips_and_recognises_their_hash() {
+        let dir = temp_dir("nested");
+        let inner = zip_bytes(&[("conversations.json", b"[]"), ("chat.html", b"<html>")]);
+        let path = dir.join("download.zip");
+        std::fs::write(&path, zip_bytes(&[("Export.zip", &inner), ("__MACOSX/._Export.zip", b"junk")])).unwrap();
+
+        let source = ExportSource::open(path.to_str().unwrap()).unwrap();
+        let mut names = source.entry_names();
+        names.sort();
+        assert_eq!(names, vec!["chat.html", "conversations.json"]);
+        assert_eq!(source.read_optional("chat.html").unwrap().as_deref(), Some(&b"<html>"[..]));
+        assert!(source.has_hash(&sha256_hex([&b"[]"[..]])));
+        assert!(source.has_hash(&sha256_hex([&b"<html>"[..]])));
+        assert!(!source.has_hash(&sha256_hex([&b"[1]"[..]])));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn follows_nested_zips_up_to_max_nesting() {
+        let dir = temp_dir("depth");
+        // `levels` ZIPs wrapped around the one holding the shard
+        let wrapped = |levels: usize| {
+            let mut zip = zip_bytes(&[("conversations.json", b"[]")]);
+            for level in 0..levels {
+                zip = zip_bytes(&[(&format!("level-{level}.zip"), &zip)]);
+            }
+            zip
+        };
+
+        let path = dir.join("deep.zip");
+        std::fs::write(&path, wrapped(MAX_NESTING)).unwrap();
+        let source = ExportSource::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(source.entry_names(), vec!["conversations.json"]);
+
+        std::fs::write(&path, wrapped(MAX_NESTING + 1)).unwrap();
+        let source = ExportSource::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(source.entry_names(), vec!["level-0.zip"]);
+        assert!(source.read_conversation_shards(&mut |_, _| {}).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
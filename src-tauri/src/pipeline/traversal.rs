@@ -1,4 +1,5 @@
 use crate::pipeline::json_parser::{Message, MessageNode};
+use serde::Deserialize;
 use std::collections::HashMap;
 
 /// Reconstructs the conversation's message order by walking the node-graph
@@ -13,6 +14,18 @@ use std::collections::HashMap;
 pub fn linearize_messages(
     mapping: &HashMap<String, MessageNode>,
     current_node: &str,
+) -> Vec<Message> {
+    walk_branch(mapping, current_node)
+        .into_iter()
+        .filter(should_include_message)
+        .collect()
+}
+
+/// Every message on the branch ending at `current_node`, in chronological order —
+/// including the system, tool and hidden messages `linearize_messages` filters out.
+pub fn walk_branch(
+    mapping: &HashMap<String, MessageNode>,
+    current_node: &str,
 ) -> Vec<Message> {
     let mut messages: Vec<Message> = Vec::new();
     let mut node_id = Some(current_node.to_string());
@@ -29,9 +42,7 @@ pub fn linearize_messages(
         };
 
         if let Some(ref msg) = node.message {
-            if should_include_message(msg) {
-                messages.push(msg.clone());
-            }
+            messages.push(msg.clone());
         }
 
         node_id = node.parent.clone();
@@ -42,26 +53,131 @@ pub fn linearize_messages(
     messages
 }
 
-/// Returns true if a message should be included in the linearized output.
-/// Only user and assistant messages with non-empty content are included.
-/// System, tool, memory, and tether_browsing messages are excluded.
-pub fn should_include_message(msg: &Message) -> bool {
-    let role = msg.author.role.as_str();
-    if role != "user" && role != "assistant" {
-        return false;
+/// What a message on the branch is, as stored in `messages.kind`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageKind {
+    /// Ordinary chat turn (user, assistant, or a visible system message).
+    Message,
+    /// Assistant message addressed to a tool (`recipient` other than "all"):
+    /// code sent to the interpreter, a search query, a DALL-E prompt.
+    ToolCall,
+    /// A tool's reply (`author.role == "tool"`): stdout, search results, generated images.
+    ToolOutput,
+}
+
+impl MessageKind {
+    pub fn of(msg: &Message) -> MessageKind {
+        if msg.author.role == "tool" {
+            MessageKind::ToolOutput
+        } else if msg.author.role == "assistant"
+            && msg.recipient.as_deref().is_some_and(|r| !r.is_empty() && r != "all")
+        {
+            MessageKind::ToolCall
+        } else {
+            MessageKind::Message
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MessageKind::Message => "message",
+            MessageKind::ToolCall => "tool_call",
+            MessageKind::ToolOutput => "tool_output",
+        }
+    }
+
+    /// Rows written before `kind` was stored (NULL) are ordinary messages.
+    pub fn parse(s: Option<&str>) -> MessageKind {
+        match s {
+            Some("tool_call") => MessageKind::ToolCall,
+            Some("tool_output") => MessageKind::ToolOutput,
+            _ => MessageKind::Message,
+        }
+    }
+}
+
+/// How much tool activity a transcript shows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ToolDetail {
+    /// No tool sections — the transcript reads like the ChatGPT UI.
+    #[default]
+    Hidden,
+    /// Tool invocations only: the code run, the query searched, the image prompt.
+    Calls,
+    /// Invocations plus their output: stdout, search results, generated files.
+    Full,
+}
+
+/// Which messages of a branch end up in a transcript.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InclusionPolicy {
+    /// Author roles of ordinary messages to keep ("user", "assistant", "system").
+    pub roles: Vec<String>,
+    pub tool_detail: ToolDetail,
+}
+
+impl Default for InclusionPolicy {
+    fn default() -> Self {
+        InclusionPolicy {
+            roles: vec!["user".to_string(), "assistant".to_string()],
+            tool_detail: ToolDetail::Hidden,
+        }
+    }
+}
+
+impl InclusionPolicy {
+    /// The widest policy — what ingest stores, so any narrower one can be applied at export.
+    pub fn everything() -> Self {
+        InclusionPolicy {
+            roles: vec!["user".to_string(), "assistant".to_string(), "system".to_string()],
+            tool_detail: ToolDetail::Full,
+        }
+    }
+
+    /// Whether a message of `kind` written by `role` is kept.
+    pub fn includes(&self, kind: MessageKind, role: &str) -> bool {
+        match kind {
+            MessageKind::Message => self.roles.iter().any(|r| r == role),
+            MessageKind::ToolCall => self.tool_detail != ToolDetail::Hidden,
+            MessageKind::ToolOutput => self.tool_detail == ToolDetail::Full,
+        }
     }
-    let Some(ref content) = msg.content else {
-        return false;
-    };
-    // Exclude empty parts arrays
-    if content.parts.is_empty() {
-        return false;
+
+    /// Whether `msg` is kept: its kind and role pass the policy and it has something to show.
+    pub fn includes_message(&self, msg: &Message) -> bool {
+        let kind = MessageKind::of(msg);
+        if !self.includes(kind, &msg.author.role) {
+            return false;
+        }
+        let Some(ref content) = msg.content else {
+            return false;
+        };
+        // Custom-instruction and other context messages ChatGPT never shows
+        if msg.metadata["is_visually_hidden_from_conversation"].as_bool() == Some(true) {
+            return false;
+        }
+        // Code, execution output and browsing results carry their payload outside `parts`
+        if kind != MessageKind::Message
+            && [&content.text, &content.result].iter().any(|t| t.as_deref().is_some_and(|t| !t.trim().is_empty()))
+        {
+            return true;
+        }
+        // Exclude messages where all parts are null or empty string
+        content.parts.iter().any(|p| {
+            p.as_str().map(|s| !s.is_empty()).unwrap_or(
+                // Non-string parts (image objects, etc.) count as non-empty
+                !p.is_null(),
+            )
+        })
     }
-    // Exclude messages where all parts are null or empty string
-    content.parts.iter().any(|p| {
-        p.as_str().map(|s| !s.is_empty()).unwrap_or(
-            // Non-string parts (image objects, etc.) count as non-empty
-            !p.is_null(),
-        )
-    })
+}
+
+/// Returns true if a message should be included in the linearized output.
+/// Only user and assistant chat messages with non-empty content are included (the default
+/// `InclusionPolicy`). System, tool, memory, and tether_browsing messages are excluded,
+/// as are assistant messages addressed to a tool.
+pub fn should_include_message(msg: &Message) -> bool {
+    InclusionPolicy::default().includes_message(msg)
 }
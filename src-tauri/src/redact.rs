@@ -0,0 +1,354 @@
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Built-in detectors for text that should not leave the machine as-is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Detector {
+    /// API keys and tokens in well-known formats, private key blocks, and values assigned
+    /// to `password=`, `api_key:` and the like.
+    Secret,
+    Email,
+    Phone,
+    /// Card numbers that pass the Luhn check.
+    CreditCard,
+    /// IBANs that pass the mod-97 check.
+    Iban,
+}
+
+impl Detector {
+    fn label(self) -> &'static str {
+        match self {
+            Detector::Secret => "SECRET",
+            Detector::Email => "EMAIL",
+            Detector::Phone => "PHONE",
+            Detector::CreditCard => "CARD",
+            Detector::Iban => "IBAN",
+        }
+    }
+
+    /// Patterns for this detector; a pattern with a capture group masks only the group.
+    fn patterns(self) -> &'static [&'static str] {
+        match self {
+            Detector::Secret => &[
+                r"\bsk-(?:proj-|ant-)?[A-Za-z0-9_-]{20,}",
+                r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b",
+                r"\bgh[pousr]_[A-Za-z0-9]{36,}\b",
+                r"\bgithub_pat_[A-Za-z0-9_]{22,}\b",
+                r"\bxox[abprs]-[A-Za-z0-9-]{10,}",
+                r"\bAIza[0-9A-Za-z_-]{35}\b",
+                r"\b[rs]k_(?:live|test)_[A-Za-z0-9]{16,}\b",
+                r"\beyJ[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}",
+                r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----",
+                r#"(?i)\b(?:password|passwd|pwd|secret|api[_-]?key|access[_-]?token|auth[_-]?token)["']?\s*[:=]\s*["']?([^\s"',;]{6,})"#,
+            ],
+            Detector::Email => &[r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b"],
+            Detector::Phone => &[
+                r"(?:\+\d{1,3}[\s.-]?)?\(?\b\d{3}\)?[\s.-]\d{3}[\s.-]\d{4}\b",
+                r"\+\d{1,3}(?:[\s.-]?\d{2,4}){2,5}\b",
+            ],
+            Detector::CreditCard => &[r"\b\d(?:[ -]?\d){12,18}\b"],
+            Detector::Iban => &[r"\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]){11,30}\b"],
+        }
+    }
+
+    /// Extra checks that weed out look-alikes (order numbers, timestamps, …).
+    fn accepts(self, text: &str) -> bool {
+        let digits: Vec<u32> = text.chars().filter_map(|c| c.to_digit(10)).collect();
+        match self {
+            Detector::Phone => (8..=15).contains(&digits.len()),
+            Detector::CreditCard => (13..=19).contains(&digits.len()) && luhn(&digits),
+            Detector::Iban => iban_checksum(text),
+            Detector::Secret | Detector::Email => true,
+        }
+    }
+
+    pub const ALL: [Detector; 5] = [
+        Detector::Secret,
+        Detector::Email,
+        Detector::Phone,
+        Detector::CreditCard,
+        Detector::Iban,
+    ];
+}
+
+/// How a match is replaced.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RedactionMode {
+    /// `[REDACTED EMAIL]` — nothing links two mentions of the same value.
+    #[default]
+    Mask,
+    /// `[EMAIL-3]` — the same value always gets the same token, recorded in the token vault
+    /// so masked text can be turned back (see `commands::redaction::unmask_text`).
+    Tokenize,
+}
+
+/// A user-defined pattern, e.g. an employee id format.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CustomPattern {
+    /// Shown in the mask or token, e.g. "EMPLOYEE ID".
+    pub name: String,
+    pub regex: String,
+}
+
+/// What to redact. Chosen in the UI and passed with every command that writes or sends
+/// transcripts, so disk output and API payloads are masked the same way.
+/// The default redacts nothing.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RedactionPolicy {
+    pub detectors: Vec<Detector>,
+    pub patterns: Vec<CustomPattern>,
+    /// Values never redacted (compared case-insensitively), e.g. a public work email.
+    pub allow: Vec<String>,
+    /// Words or phrases always redacted wherever they appear, e.g. a name or street address.
+    pub deny: Vec<String>,
+    pub mode: RedactionMode,
+}
+
+/// One stretch of text a redactor would replace.
+#[derive(Debug, PartialEq)]
+pub struct Match {
+    pub start: usize,
+    pub end: usize,
+    /// Detector label or custom pattern name, e.g. `EMAIL`.
+    pub label: String,
+}
+
+struct Rule {
+    label: String,
+    regex: Regex,
+    detector: Option<Detector>,
+}
+
+/// Tokens handed out so far: value → token, per label, and the tokens added in this run.
+#[derive(Default)]
+struct Vault {
+    tokens: HashMap<(String, String), String>,
+    next: HashMap<String, u32>,
+    added: Vec<TokenEntry>,
+}
+
+/// A vault entry: `token` stands for `value`.
+pub struct TokenEntry {
+    pub token: String,
+    pub label: String,
+    pub value: String,
+}
+
+/// Applies a `RedactionPolicy` to text.
+pub struct Redactor {
+    rules: Vec<Rule>,
+    allow: Vec<String>,
+    mode: RedactionMode,
+    vault: Mutex<Vault>,
+}
+
+impl Redactor {
+    /// Compiles `policy`. `tokens` are the vault entries from earlier runs, so tokenized
+    /// values keep their token; an invalid custom regex is an error naming the pattern.
+    pub fn new(policy: &RedactionPolicy, tokens: Vec<TokenEntry>) -> Result<Redactor, String> {
+        let mut rules = Vec::new();
+        for detector in Detector::ALL.into_iter().filter(|d| policy.detectors.contains(d)) {
+            for pattern in detector.patterns() {
+                rules.push(Rule {
+                    label: detector.label().to_string(),
+                    regex: Regex::new(pattern).map_err(|e| e.to_string())?,
+                    detector: Some(detector),
+                });
+            }
+        }
+        for custom in &policy.patterns {
+            let regex = Regex::new(&custom.regex).map_err(|e| format!("Invalid pattern \"{}\": {e}", custom.name))?;
+            rules.push(Rule { label: custom.name.trim().to_uppercase(), regex, detector: None });
+        }
+        let deny: Vec<String> = policy
+            .deny
+            .iter()
+            .map(|d| d.trim())
+            .filter(|d| !d.is_empty())
+            .map(regex::escape)
+            .collect();
+        if !deny.is_empty() {
+            let regex = Regex::new(&format!(r"(?i)\b(?:{})\b", deny.join("|"))).map_err(|e| e.to_string())?;
+            rules.push(Rule { label: "PRIVATE".to_string(), regex, detector: None });
+        }
+
+        let mut vault = Vault::default();
+        for entry in tokens {
+            let n = entry.token.rsplit('-').next().and_then(|n| n.trim_end_matches(']').parse().ok()).unwrap_or(0);
+            let next = vault.next.entry(entry.label.clone()).or_insert(0);
+            *next = (*next).max(n);
+            vault.tokens.insert((entry.label, entry.value), entry.token);
+        }
+
+        Ok(Redactor {
+            rules,
+            allow: policy.allow.iter().map(|a| a.trim().to_lowercase()).collect(),
+            mode: policy.mode,
+            vault: Mutex::new(vault),
+        })
+    }
+
+    /// Whether the policy redacts anything at all.
+    pub fn is_active(&self) -> bool {
+        !self.rules.is_empty()
+    }
+
+    /// Everything in `text` the policy would replace, in order and without overlaps
+    /// (the earlier, then longer, match wins).
+    pub fn find(&self, text: &str) -> Vec<Match> {
+        let mut found: Vec<Match> = Vec::new();
+        for rule in &self.rules {
+            for caps in rule.regex.captures_iter(text) {
+                let Some(m) = caps.get(1).or_else(|| caps.get(0)) else { continue };
+                if m.as_str().is_empty()
+                    || rule.detector.is_some_and(|d| !d.accepts(m.as_str()))
+                    || self.allow.contains(&m.as_str().to_lowercase())
+                {
+                    continue;
+                }
+                found.push(Match { start: m.start(), end: m.end(), label: rule.label.clone() });
+            }
+        }
+        found.sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+        let mut kept: Vec<Match> = Vec::with_capacity(found.len());
+        for m in found {
+            if kept.last().is_none_or(|last| m.start >= last.end) {
+                kept.push(m);
+            }
+        }
+        kept
+    }
+
+    /// `text` with every match replaced by its mask or token.
+    pub fn redact(&self, text: &str) -> String {
+        if !self.is_active() {
+            return text.to_string();
+        }
+        let mut out = String::with_capacity(text.len());
+        let mut at = 0;
+        for m in self.find(text) {
+            out.push_str(&text[at..m.start]);
+            out.push_str(&self.replacement(&m.label, &text[m.start..m.end]));
+            at = m.end;
+        }
+        out.push_str(&text[at..]);
+        out
+    }
+
+    pub fn redact_opt(&self, text: Option<&str>) -> Option<String> {
+        text.map(|t| self.redact(t))
+    }
+
+    /// The mask or token for `value`; tokenizing hands out the next number for its label
+    /// the first time a value is seen.
+    pub fn replacement(&self, label: &str, value: &str) -> String {
+        if self.mode == RedactionMode::Mask {
+            return format!("[REDACTED {label}]");
+        }
+        let mut vault = self.vault.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (label.to_string(), value.to_string());
+        if let Some(token) = vault.tokens.get(&key) {
+            return token.clone();
+        }
+        let next = vault.next.entry(label.to_string()).or_insert(0);
+        *next += 1;
+        let token = format!("[{label}-{next}]");
+        vault.tokens.insert(key, token.clone());
+        vault.added.push(TokenEntry { token: token.clone(), label: label.to_string(), value: value.to_string() });
+        token
+    }
+
+    /// Vault entries created since `new`, to be saved so the tokens stay reversible.
+    pub fn take_new_tokens(&self) -> Vec<TokenEntry> {
+        std::mem::take(&mut self.vault.lock().unwrap_or_else(|e| e.into_inner()).added)
+    }
+}
+
+fn luhn(digits: &[u32]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| if i % 2 == 1 { if d * 2 > 9 { d * 2 - 9 } else { d * 2 } } else { d })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// ISO 13616 check: move the first four characters to the end, turn letters into numbers
+/// (A = 10 … Z = 35) and the result mod 97 must be 1.
+fn iban_checksum(text: &str) -> bool {
+    let compact: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if !(15..=34).contains(&compact.len()) {
+        return false;
+    }
+    let mut remainder: u32 = 0;
+    for c in compact[4..].iter().chain(&compact[..4]) {
+        let Some(value) = c.to_digit(36) else { return false };
+        remainder = if value < 10 { (remainder * 10 + value) % 97 } else { (remainder * 100 + value) % 97 };
+    }
+    remainder == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor(detectors: &[Detector], mode: RedactionMode) -> Redactor {
+        let policy = RedactionPolicy { detectors: detectors.to_vec(), mode, ..Default::default() };
+        Redactor::new(&policy, Vec::new()).unwrap()
+    }
+
+    #[test]
+    fn masks_builtin_formats() {
+        let r = redactor(&Detector::ALL, RedactionMode::Mask);
+        let text = "key sk-proj-abcdefghijklmnopqrstuvwx, mail me at jo@example.com or call +1 415 555 0132. \
+                    Card 4111 1111 1111 1111, IBAN DE89 3704 0044 0532 0130 00, password: hunter22";
+        assert_eq!(
+            r.redact(text),
+            "key [REDACTED SECRET], mail me at [REDACTED EMAIL] or call [REDACTED PHONE]. \
+             Card [REDACTED CARD], IBAN [REDACTED IBAN], password: [REDACTED SECRET]"
+        );
+    }
+
+    #[test]
+    fn leaves_look_alikes_alone() {
+        let r = redactor(&Detector::ALL, RedactionMode::Mask);
+        let text = "Order 4111 1111 1111 1112 shipped 2024-03-05 at 1712345678, ref GB00 1234 5678 9012 34";
+        assert_eq!(r.redact(text), text);
+    }
+
+    #[test]
+    fn tokens_are_stable_and_resume_from_the_vault() {
+        let r = redactor(&[Detector::Email], RedactionMode::Tokenize);
+        assert_eq!(r.redact("a@x.io, b@x.io, a@x.io"), "[EMAIL-1], [EMAIL-2], [EMAIL-1]");
+        let saved = r.take_new_tokens();
+        assert_eq!(saved.len(), 2);
+
+        let policy = RedactionPolicy { detectors: vec![Detector::Email], mode: RedactionMode::Tokenize, ..Default::default() };
+        let again = Redactor::new(&policy, saved).unwrap();
+        assert_eq!(again.redact("c@x.io then b@x.io"), "[EMAIL-3] then [EMAIL-2]");
+    }
+
+    #[test]
+    fn custom_patterns_and_lists() {
+        let policy = RedactionPolicy {
+            detectors: vec![Detector::Email],
+            patterns: vec![CustomPattern { name: "employee id".to_string(), regex: r"\bEMP-\d{5}\b".to_string() }],
+            allow: vec!["Support@Example.com".to_string()],
+            deny: vec!["Jane Roe".to_string()],
+            mode: RedactionMode::Mask,
+        };
+        let r = Redactor::new(&policy, Vec::new()).unwrap();
+        assert_eq!(
+            r.redact("jane roe (EMP-12345) wrote to support@example.com"),
+            "[REDACTED PRIVATE] ([REDACTED EMPLOYEE ID]) wrote to support@example.com"
+        );
+        let bad = RedactionPolicy { patterns: vec![CustomPattern { name: "x".into(), regex: "(".into() }], ..Default::default() };
+        assert!(Redactor::new(&bad, Vec::new()).is_err());
+    }
+}
@@ -1,35 +1,657 @@
-use crate::pipeline::normalizer::ConversationRecord;
-use rusqlite::{params, Connection, Result};
+use crate::pipeline::feedback::{ComparisonRecord, FeedbackRecord};
+use crate::pipeline::metadata::{Attachment, ToolCategory};
+use crate::pipeline::normalizer::{ConversationKind, ConversationRecord, MessageRecord, ToolCall};
+use crate::pipeline::profile::{ProfileEntry, ProfileKind};
+use crate::pipeline::shares::ShareRecord;
+use crate::pipeline::source::Account;
+use crate::pipeline::traversal::MessageKind;
+use crate::redact::TokenEntry;
+use crate::store::exclusions::{Candidate, ExclusionRule, Exclusions};
+use crate::store::media_store::{file_id, MediaInfo};
+use crate::store::migrations;
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use std::collections::{HashMap, HashSet};
 
-pub fn init_schema(conn: &Connection) -> Result<()> {
-    conn.execute_batch(include_str!("schema.sql"))?;
-    // Migration: add gizmo_id column for existing databases (safe to run multiple times)
-    let _ = conn.execute("ALTER TABLE conversations ADD COLUMN gizmo_id TEXT", []);
-    Ok(())
+/// Creates or upgrades the schema (see `migrations`). Fails for a database written by a
+/// newer version of the app.
+pub fn init_schema(conn: &Connection) -> std::result::Result<(), String> {
+    migrations::migrate(conn)
+}
+
+/// Conversation ids seen in any export of account `?1` — its email, or its name for exports
+/// without one (see `Account::label`).
+const ACCOUNT_CONVERSATIONS_SQL: &str = "SELECT cs.conversation_id FROM conversation_sources cs
+     JOIN import_sources s ON s.id = cs.source_id
+     WHERE COALESCE(s.account_email, s.account_name) = ?1";
+
+/// Conversations kept out of exports and API requests (see `update_exclusions`).
+const EXCLUDED_SQL: &str = "SELECT id FROM conversations WHERE excluded = 1";
+
+/// SQL expression for when a conversation was last worked on: the later of `update_time`
+/// and the newest message, falling back to `created_at` for rows imported before either was stored.
+const LAST_ACTIVE_SQL: &str = "COALESCE(NULLIF(MAX(IFNULL(updated_at, 0), IFNULL(last_message_at, 0)), 0), created_at)";
+
+/// Sort order for conversation queries.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConversationSort {
+    /// Oldest first by creation time.
+    #[default]
+    Created,
+    /// Most recently active first.
+    LastActive,
+}
+
+impl ConversationSort {
+    fn order_by(self) -> String {
+        match self {
+            ConversationSort::Created => "created_at ASC".to_string(),
+            ConversationSort::LastActive => format!("{LAST_ACTIVE_SQL} DESC"),
+        }
+    }
 }
 
-/// Inserts or replaces a conversation record in SQLite.
-/// Uses INSERT OR REPLACE for idempotent re-runs (re-importing the same ZIP is safe).
+/// Inserts a conversation record in SQLite, or updates the imported columns of an existing
+/// one (re-importing the same ZIP is safe). Columns set after import — project, cluster,
+/// summary, instructions, exclusion — are kept, so a newer version of a conversation stays
+/// where the user and clustering put it.
+///
+/// The statement is prepared once per connection and cached, so calling this in a loop
+/// inside a transaction (see `commands::ingest::parse_zip`) does not re-parse the SQL.
 pub fn insert_conversation(conn: &Connection, record: &ConversationRecord) -> Result<()> {
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO conversations
+            (id, title, created_at, updated_at, first_message_at, last_message_at,
+             message_count, has_images, has_code, token_estimate, full_text, gizmo_id,
+             kind, participants)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+         ON CONFLICT(id) DO UPDATE SET
+            title = excluded.title, created_at = excluded.created_at,
+            updated_at = excluded.updated_at, first_message_at = excluded.first_message_at,
+            last_message_at = excluded.last_message_at, message_count = excluded.message_count,
+            has_images = excluded.has_images, has_code = excluded.has_code,
+            token_estimate = excluded.token_estimate, full_text = excluded.full_text,
+            gizmo_id = excluded.gizmo_id, kind = excluded.kind, participants = excluded.participants",
+    )?;
+    stmt.execute(params![
+        record.id,
+        record.title,
+        record.created_at,
+        record.updated_at,
+        record.first_message_at,
+        record.last_message_at,
+        record.message_count,
+        record.has_images as i32,
+        record.has_code as i32,
+        record.token_estimate,
+        record.full_text,
+        record.gizmo_id,
+        record.kind.as_str(),
+        json_or_null(&record.participants),
+    ])?;
+    insert_messages(conn, &record.id, &record.messages)?;
+    insert_profile_entries(conn, &record.id, &record.profile)?;
+    insert_tool_calls(conn, &record.id, &record.tool_calls)?;
+    Ok(())
+}
+
+/// Replaces the stored transcript of a conversation with `messages`, keeping their order.
+fn insert_messages(conn: &Connection, conversation_id: &str, messages: &[MessageRecord]) -> Result<()> {
+    conn.prepare_cached("DELETE FROM messages WHERE conversation_id = ?1")?
+        .execute(params![conversation_id])?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO messages (conversation_id, position, id, role, created_at, text, model_slug, assets,
+                               finish_type, attachments, citations, kind, tool, content_type, author_name)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+    )?;
+    for (position, msg) in messages.iter().enumerate() {
+        stmt.execute(params![
+            conversation_id,
+            position as i64,
+            msg.id,
+            msg.role,
+            msg.created_at,
+            msg.text,
+            msg.model_slug,
+            // JSON arrays; NULL for the common text-only message
+            json_or_null(&msg.assets),
+            msg.finish_type,
+            json_or_null(&msg.attachments),
+            json_or_null(&msg.citations),
+            msg.kind.as_str(),
+            msg.tool,
+            msg.content_type,
+            msg.author_name,
+        ])?;
+    }
+    Ok(())
+}
+
+/// Serializes a non-empty list as JSON for a TEXT column; empty lists are stored as NULL.
+fn json_or_null<T: serde::Serialize>(items: &[T]) -> Option<String> {
+    (!items.is_empty()).then(|| serde_json::to_string(items).unwrap_or_default())
+}
+
+/// Replaces the tool invocations recorded for a conversation.
+fn insert_tool_calls(conn: &Connection, conversation_id: &str, calls: &[ToolCall]) -> Result<()> {
+    conn.prepare_cached("DELETE FROM tool_calls WHERE conversation_id = ?1")?
+        .execute(params![conversation_id])?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO tool_calls (conversation_id, position, message_id, tool, category, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for (position, call) in calls.iter().enumerate() {
+        stmt.execute(params![
+            conversation_id,
+            position as i64,
+            call.message_id,
+            call.tool,
+            call.category.as_str(),
+            call.created_at,
+        ])?;
+    }
+    Ok(())
+}
+
+/// Replaces the custom-instruction and memory observations recorded for a conversation.
+fn insert_profile_entries(conn: &Connection, conversation_id: &str, entries: &[ProfileEntry]) -> Result<()> {
+    conn.prepare_cached("DELETE FROM profile_entries WHERE conversation_id = ?1")?
+        .execute(params![conversation_id])?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT OR IGNORE INTO profile_entries (conversation_id, kind, text, seen_at) VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for entry in entries {
+        stmt.execute(params![conversation_id, entry.kind.as_str(), entry.text, entry.seen_at])?;
+    }
+    Ok(())
+}
+
+/// Inserts or replaces a message rating from message_feedback.json.
+pub fn insert_feedback(conn: &Connection, feedback: &FeedbackRecord) -> Result<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO message_feedback
+            (id, conversation_id, message_id, positive, tags, text, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?
+    .execute(params![
+        feedback.id,
+        feedback.conversation_id,
+        feedback.message_id,
+        feedback.positive as i32,
+        json_or_null(&feedback.tags),
+        feedback.text,
+        feedback.created_at,
+    ])?;
+    Ok(())
+}
+
+/// Inserts or replaces an answer comparison from model_comparisons.json.
+pub fn insert_comparison(conn: &Connection, comparison: &ComparisonRecord) -> Result<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO model_comparisons (id, conversation_id, created_at, rating, data)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?
+    .execute(params![
+        comparison.id,
+        comparison.conversation_id,
+        comparison.created_at,
+        comparison.rating,
+        comparison.data,
+    ])?;
+    Ok(())
+}
+
+/// Inserts or replaces a public share link from shared_conversations.json.
+pub fn insert_share(conn: &Connection, share: &ShareRecord) -> Result<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO shared_links (id, conversation_id, title, url, anonymous)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?
+    .execute(params![share.id, share.conversation_id, share.title, share.url, share.anonymous as i32])?;
+    Ok(())
+}
+
+/// Stores `record` unless the archive already holds a newer version of the same conversation
+/// from another export (latest version wins). Returns whether it was written.
+pub fn insert_conversation_if_newer(conn: &Connection, record: &ConversationRecord) -> Result<bool> {
+    let stored: Option<i64> = conn
+        .prepare_cached("SELECT MAX(IFNULL(updated_at, 0), IFNULL(last_message_at, 0)) FROM conversations WHERE id = ?1")?
+        .query_row(params![record.id], |row| row.get(0))
+        .optional()?;
+    if stored.is_some_and(|stored| stored > last_active(record)) {
+        return Ok(false);
+    }
+    insert_conversation(conn, record)?;
+    Ok(true)
+}
+
+/// Version of a conversation for latest-version-wins: the later of `update_time` and its newest message.
+fn last_active(record: &ConversationRecord) -> i64 {
+    record.updated_at.unwrap_or(0).max(record.last_message_at.unwrap_or(0))
+}
+
+/// Records an imported export and returns its id. Re-importing an export with the same
+/// `file_hash` updates its path, account and import time instead of adding a row.
+pub fn upsert_import_source(
+    conn: &Connection,
+    file_hash: &str,
+    path: &str,
+    account: &Account,
+    imported_at: i64,
+) -> Result<i64> {
+    conn.prepare_cached(
+        "INSERT INTO import_sources (file_hash, path, account_email, account_name, imported_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT (file_hash) DO UPDATE SET path = excluded.path, account_email = excluded.account_email,
+             account_name = excluded.account_name, imported_at = excluded.imported_at
+         RETURNING id",
+    )?
+    .query_row(params![file_hash, path, account.email, account.name, imported_at], |row| row.get(0))
+}
+
+/// Notes that `record` was present in import source `source_id`, with the version it carried.
+pub fn insert_conversation_source(conn: &Connection, source_id: i64, record: &ConversationRecord) -> Result<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO conversation_sources (conversation_id, source_id, last_active_at)
+         VALUES (?1, ?2, ?3)",
+    )?
+    .execute(params![record.id, source_id, last_active(record)])?;
+    Ok(())
+}
+
+/// An imported export, for listing and filtering by account.
+#[derive(serde::Serialize)]
+pub struct ImportSourceRow {
+    pub id: i64,
+    pub path: String,
+    pub file_hash: String,
+    pub account_email: Option<String>,
+    pub account_name: Option<String>,
+    pub imported_at: i64,
+    /// Conversations this export contained (including ones a newer export superseded).
+    pub conversations: i64,
+    /// Media files copied into the app's media store; when non-zero, exports no longer need
+    /// the original file.
+    pub media_stored: i64,
+}
+
+const IMPORT_SOURCE_SQL: &str = "SELECT s.id, s.path, s.file_hash, s.account_email, s.account_name, s.imported_at,
+        (SELECT COUNT(*) FROM conversation_sources cs WHERE cs.source_id = s.id),
+        (SELECT COUNT(*) FROM stored_media sm WHERE sm.source_id = s.id)
+     FROM import_sources s";
+
+fn import_source_row(row: &rusqlite::Row) -> Result<ImportSourceRow> {
+    Ok(ImportSourceRow {
+        id: row.get(0)?,
+        path: row.get(1)?,
+        file_hash: row.get(2)?,
+        account_email: row.get(3)?,
+        account_name: row.get(4)?,
+        imported_at: row.get(5)?,
+        conversations: row.get(6)?,
+        media_stored: row.get(7)?,
+    })
+}
+
+/// Every imported export, most recently imported first.
+pub fn get_import_sources(conn: &Connection) -> Result<Vec<ImportSourceRow>> {
+    let mut stmt = conn.prepare(&format!("{IMPORT_SOURCE_SQL} ORDER BY s.imported_at DESC, s.id DESC"))?;
+    let rows = stmt.query_map([], import_source_row)?;
+    rows.collect()
+}
+
+pub fn get_import_source(conn: &Connection, source_id: i64) -> Result<Option<ImportSourceRow>> {
+    conn.query_row(&format!("{IMPORT_SOURCE_SQL} WHERE s.id = ?1"), params![source_id], import_source_row)
+        .optional()
+}
+
+/// Points an import source at the new location of a moved export.
+pub fn update_import_source_path(conn: &Connection, source_id: i64, path: &str) -> Result<()> {
+    conn.execute("UPDATE import_sources SET path = ?1 WHERE id = ?2", params![path, source_id])?;
+    Ok(())
+}
+
+/// Records that the export entry `path` of `source_id` is kept in the media store under `hash`.
+pub fn insert_stored_media(conn: &Connection, source_id: i64, path: &str, hash: &str, size: u64) -> Result<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO stored_media (source_id, path, hash, size) VALUES (?1, ?2, ?3, ?4)",
+    )?
+    .execute(params![source_id, path, hash, size as i64])?;
+    Ok(())
+}
+
+/// A media file of an import source kept in the store.
+pub struct StoredMediaRow {
+    /// Path inside the export.
+    pub path: String,
+    pub hash: String,
+    /// Sniffed MIME type; `None` for media stored before types were recorded.
+    pub mime_type: Option<String>,
+    /// Whether a tool or assistant message points at the file (see `media_refs`).
+    pub generated: bool,
+}
+
+/// Every stored media file of `source_id`, except files only excluded conversations use.
+pub fn get_stored_media(conn: &Connection, source_id: i64) -> Result<Vec<StoredMediaRow>> {
+    let mut stmt = conn.prepare(
+        "SELECT sm.path, sm.hash, m.mime_type,
+                EXISTS (SELECT 1 FROM media_refs r WHERE r.hash = sm.hash AND r.generated)
+         FROM stored_media sm LEFT JOIN media m ON m.hash = sm.hash
+         WHERE sm.source_id = ?1
+           AND (NOT EXISTS (SELECT 1 FROM media_refs r WHERE r.hash = sm.hash)
+                OR EXISTS (SELECT 1 FROM media_refs r JOIN conversations c ON c.id = r.conversation_id
+                           WHERE r.hash = sm.hash AND c.excluded = 0))
+         ORDER BY sm.path",
+    )?;
+    let rows = stmt.query_map(params![source_id], |row| {
+        Ok(StoredMediaRow {
+            path: row.get(0)?,
+            hash: row.get(1)?,
+            mime_type: row.get(2)?,
+            generated: row.get(3)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// How the conversations point at one media file id (see `get_media_file_refs`).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MediaFileRefs {
+    /// A tool or assistant message points at it rather than the user.
+    pub generated: bool,
+    /// A conversation that is not excluded points at it.
+    pub included: bool,
+}
+
+/// Media file ids referenced by the messages' assets and attachments — for export media
+/// read from an export rather than the store, where `media_refs` has no rows.
+pub fn get_media_file_refs(conn: &Connection) -> Result<HashMap<String, MediaFileRefs>> {
+    let mut stmt = conn.prepare(
+        "SELECT j.value, m.role != 'user', c.excluded = 0
+         FROM messages m JOIN conversations c ON c.id = m.conversation_id JOIN json_each(m.assets) j
+         UNION
+         SELECT json_extract(j.value, '$.id'), m.role != 'user', c.excluded = 0
+         FROM messages m JOIN conversations c ON c.id = m.conversation_id JOIN json_each(m.attachments) j",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, Option<String>>(0)?, row.get::<_, bool>(1)?, row.get::<_, bool>(2)?))
+    })?;
+    let mut refs: HashMap<String, MediaFileRefs> = HashMap::new();
+    for row in rows {
+        if let (Some(file_id), generated, included) = row? {
+            let entry = refs.entry(file_id).or_default();
+            entry.generated |= generated;
+            entry.included |= included;
+        }
+    }
+    Ok(refs)
+}
+
+/// Records what the stored file `hash` is; a hash already known keeps its row.
+pub fn insert_media(conn: &Connection, hash: &str, size: u64, info: &MediaInfo) -> Result<()> {
+    conn.prepare_cached(
+        "INSERT OR IGNORE INTO media (hash, size, mime_type, width, height) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?
+    .execute(params![hash, size as i64, info.mime_type, info.width, info.height])?;
+    Ok(())
+}
+
+/// Rebuilds `media_refs`: which conversations point at each stored media file, through the
+/// asset and attachment ids of their messages.
+pub fn update_media_refs(conn: &Connection) -> Result<()> {
+    let mut by_file_id: HashMap<String, Vec<(String, bool)>> = HashMap::new();
+    {
+        let mut stmt = conn.prepare(
+            "SELECT j.value, m.conversation_id, m.role != 'user' FROM messages m, json_each(m.assets) j
+             UNION
+             SELECT json_extract(j.value, '$.id'), m.conversation_id, m.role != 'user'
+             FROM messages m, json_each(m.attachments) j",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, Option<String>>(0)?, row.get::<_, String>(1)?, row.get::<_, bool>(2)?))
+        })?;
+        for row in rows {
+            if let (Some(file_id), conversation_id, generated) = row? {
+                by_file_id.entry(file_id).or_default().push((conversation_id, generated));
+            }
+        }
+    }
+
+    conn.execute("DELETE FROM media_refs", [])?;
+    let mut stmt = conn.prepare("SELECT DISTINCT path, hash FROM stored_media")?;
+    let files = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+    let mut insert = conn.prepare_cached(
+        "INSERT INTO media_refs (hash, conversation_id, generated) VALUES (?1, ?2, ?3)
+         ON CONFLICT (hash, conversation_id) DO UPDATE SET generated = generated OR excluded.generated",
+    )?;
+    for file in files {
+        let (path, hash) = file?;
+        for (conversation_id, generated) in by_file_id.get(file_id(&path)).into_iter().flatten() {
+            insert.execute(params![hash, conversation_id, generated])?;
+        }
+    }
+    Ok(())
+}
+
+/// Every token handed out by tokenizing redaction.
+pub fn get_redaction_tokens(conn: &Connection) -> Result<Vec<TokenEntry>> {
+    let mut stmt = conn.prepare("SELECT token, label, value FROM redaction_tokens")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(TokenEntry {
+            token: row.get(0)?,
+            label: row.get(1)?,
+            value: row.get(2)?,
+        })
+    })?;
+    rows.collect()
+}
+
+pub fn insert_redaction_tokens(conn: &Connection, tokens: &[TokenEntry]) -> Result<()> {
+    let mut stmt = conn.prepare_cached("INSERT OR IGNORE INTO redaction_tokens (token, label, value) VALUES (?1, ?2, ?3)")?;
+    for entry in tokens {
+        stmt.execute(params![entry.token, entry.label, entry.value])?;
+    }
+    Ok(())
+}
+
+/// A saved exclusion rule.
+#[derive(serde::Serialize)]
+pub struct ExclusionRuleRow {
+    pub id: i64,
+    pub rule: ExclusionRule,
+    pub created_at: i64,
+}
+
+pub fn insert_exclusion_rule(conn: &Connection, rule: &ExclusionRule, created_at: i64) -> Result<i64> {
+    let (kind, value, starts_at, ends_at) = rule.to_columns();
     conn.execute(
-        "INSERT OR REPLACE INTO conversations
-            (id, title, created_at, message_count, has_images, has_code, token_estimate, full_text, gizmo_id)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        params![
-            record.id,
-            record.title,
-            record.created_at,
-            record.message_count,
-            record.has_images as i32,
-            record.has_code as i32,
-            record.token_estimate,
-            record.full_text,
-            record.gizmo_id,
-        ],
+        "INSERT INTO exclusion_rules (kind, value, starts_at, ends_at, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![kind, value, starts_at, ends_at, created_at],
     )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Every exclusion rule, oldest first. Kinds written by a newer version of the app are skipped.
+pub fn get_exclusion_rules(conn: &Connection) -> Result<Vec<ExclusionRuleRow>> {
+    let mut stmt = conn.prepare("SELECT id, kind, value, starts_at, ends_at, created_at FROM exclusion_rules ORDER BY id")?;
+    let rows = stmt.query_map([], |row| {
+        let kind: String = row.get(1)?;
+        Ok((row.get(0)?, kind, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?))
+    })?;
+    let mut rules = Vec::new();
+    for row in rows {
+        let (id, kind, value, starts_at, ends_at, created_at) = row?;
+        if let Some(rule) = ExclusionRule::from_columns(&kind, value, starts_at, ends_at) {
+            rules.push(ExclusionRuleRow { id, rule, created_at });
+        }
+    }
+    Ok(rules)
+}
+
+pub fn delete_exclusion_rule(conn: &Connection, rule_id: i64) -> Result<()> {
+    conn.execute("DELETE FROM exclusion_rules WHERE id = ?1", params![rule_id])?;
+    Ok(())
+}
+
+/// Marks a conversation private, or clears the mark.
+pub fn set_private(conn: &Connection, conversation_id: &str, private: bool) -> Result<()> {
+    if private {
+        conn.execute(
+            "INSERT OR IGNORE INTO private_conversations (conversation_id) VALUES (?1)",
+            params![conversation_id],
+        )?;
+    } else {
+        conn.execute("DELETE FROM private_conversations WHERE conversation_id = ?1", params![conversation_id])?;
+    }
     Ok(())
 }
 
+/// The saved exclusion rules and private marks, ready for matching.
+pub fn get_exclusions(conn: &Connection) -> Result<Exclusions> {
+    let rules: Vec<ExclusionRule> = get_exclusion_rules(conn)?.into_iter().map(|r| r.rule).collect();
+    let mut stmt = conn.prepare("SELECT conversation_id FROM private_conversations")?;
+    let private = stmt.query_map([], |row| row.get(0))?.collect::<Result<Vec<String>>>()?;
+    Ok(Exclusions::new(&rules, private))
+}
+
+/// Stores whether a just-imported conversation is excluded, so it is hidden as soon as its
+/// import batch commits rather than only once `update_exclusions` runs at the end.
+pub fn apply_exclusions(conn: &Connection, record: &ConversationRecord, exclusions: &Exclusions) -> Result<()> {
+    let excluded = exclusions.excludes(&Candidate {
+        id: &record.id,
+        title: Some(&record.title),
+        gizmo_id: record.gizmo_id.as_deref(),
+        created_at: record.created_at,
+        full_text: &record.full_text,
+    });
+    conn.prepare_cached("UPDATE conversations SET excluded = ?2 WHERE id = ?1")?
+        .execute(params![record.id, excluded])?;
+    Ok(())
+}
+
+/// Re-evaluates the exclusion rules and private marks for every conversation and stores the
+/// result in `conversations.excluded`, which every export and API query filters on.
+/// Run after each import and each rule or mark change. Returns how many are excluded.
+pub fn update_exclusions(conn: &Connection) -> Result<usize> {
+    let exclusions = get_exclusions(conn)?;
+
+    let mut stmt = conn.prepare("SELECT id, title, gizmo_id, created_at, full_text, excluded FROM conversations")?;
+    let mut rows = stmt.query([])?;
+    let mut update = conn.prepare_cached("UPDATE conversations SET excluded = ?2 WHERE id = ?1")?;
+    let mut excluded = 0;
+    while let Some(row) = rows.next()? {
+        let id: String = row.get(0)?;
+        let title: Option<String> = row.get(1)?;
+        let gizmo_id: Option<String> = row.get(2)?;
+        let full_text: String = row.get(4)?;
+        let is_excluded = exclusions.excludes(&Candidate {
+            id: &id,
+            title: title.as_deref(),
+            gizmo_id: gizmo_id.as_deref(),
+            created_at: row.get(3)?,
+            full_text: &full_text,
+        });
+        if is_excluded != row.get::<_, bool>(5)? {
+            update.execute(params![id, is_excluded])?;
+        }
+        excluded += is_excluded as usize;
+    }
+    Ok(excluded)
+}
+
+/// Ids of excluded conversations, for filtering files copied verbatim from an export.
+pub fn get_excluded_ids(conn: &Connection) -> Result<HashSet<String>> {
+    let mut stmt = conn.prepare(EXCLUDED_SQL)?;
+    let rows = stmt.query_map([], |row| row.get(0))?;
+    rows.collect()
+}
+
+/// A user-made project, with how many conversations are in it.
+#[derive(serde::Serialize)]
+pub struct ProjectRow {
+    pub name: String,
+    pub created_at: i64,
+    pub conversations: i64,
+}
+
+pub fn get_projects(conn: &Connection) -> Result<Vec<ProjectRow>> {
+    let mut stmt = conn.prepare(
+        "SELECT p.name, p.created_at, (SELECT COUNT(*) FROM conversations c WHERE c.project_name = p.name)
+         FROM projects p ORDER BY p.name",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(ProjectRow { name: row.get(0)?, created_at: row.get(1)?, conversations: row.get(2)? })
+    })?;
+    rows.collect()
+}
+
+/// The stored spelling of project `name`, which is matched ignoring case.
+pub fn find_project(conn: &Connection, name: &str) -> Result<Option<String>> {
+    conn.query_row("SELECT name FROM projects WHERE name = ?1", params![name], |row| row.get(0))
+        .optional()
+}
+
+/// Adds a project; `false` when one with the same name (ignoring case) exists.
+pub fn insert_project(conn: &Connection, name: &str, created_at: i64) -> Result<bool> {
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO projects (name, created_at) VALUES (?1, ?2)",
+        params![name, created_at],
+    )?;
+    Ok(inserted > 0)
+}
+
+/// Renames project `name` (its stored spelling) and moves its conversations along.
+pub fn rename_project(conn: &Connection, name: &str, new_name: &str) -> Result<()> {
+    conn.execute("UPDATE projects SET name = ?2 WHERE name = ?1", params![name, new_name])?;
+    conn.execute("UPDATE conversations SET project_name = ?2 WHERE project_name = ?1", params![name, new_name])?;
+    Ok(())
+}
+
+/// Deletes project `name` (its stored spelling); its conversations go back to their clusters.
+pub fn delete_project(conn: &Connection, name: &str) -> Result<()> {
+    conn.execute("UPDATE conversations SET project_name = NULL WHERE project_name = ?1", params![name])?;
+    conn.execute("DELETE FROM projects WHERE name = ?1", params![name])?;
+    Ok(())
+}
+
+/// Puts the conversations in `ids` into `project`, or takes them out of any project with
+/// `None`. Returns how many conversations changed.
+pub fn set_project(conn: &Connection, project: Option<&str>, ids: &[String]) -> Result<usize> {
+    let mut stmt = conn.prepare_cached(
+        "UPDATE conversations SET project_name = ?1 WHERE id = ?2 AND project_name IS NOT ?1",
+    )?;
+    let mut changed = 0;
+    for id in ids {
+        changed += stmt.execute(params![project, id])?;
+    }
+    Ok(changed)
+}
+
+/// Ids of conversations whose title or transcript contains every word of `query`, ignoring case.
+pub fn search_conversation_ids(conn: &Connection, query: &str) -> Result<Vec<String>> {
+    let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare("SELECT id, lower(IFNULL(title, '') || ' ' || full_text) FROM conversations")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+    let mut ids = Vec::new();
+    for row in rows {
+        let (id, text) = row?;
+        if terms.iter().all(|t| text.contains(t.as_str())) {
+            ids.push(id);
+        }
+    }
+    Ok(ids)
+}
+
+/// Ids of the conversations clustering labelled `label`.
+pub fn cluster_conversation_ids(conn: &Connection, label: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT id FROM conversations WHERE cluster_label = ?1")?;
+    let rows = stmt.query_map(params![label], |row| row.get(0))?;
+    rows.collect()
+}
+
 /// Writes Phase 2 clustering results back to a conversation row.
 pub fn update_cluster_result(
     conn: &Connection,
@@ -52,11 +674,12 @@ pub struct ConversationRow {
     pub token_estimate: i64,
 }
 
-/// Fetches all conversations ordered by creation time for batch clustering.
-pub fn get_all_conversations(conn: &Connection) -> Result<Vec<ConversationRow>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, title, full_text, token_estimate FROM conversations ORDER BY created_at ASC",
-    )?;
+/// Fetches all conversations in `sort` order for batch clustering.
+pub fn get_all_conversations(conn: &Connection, sort: ConversationSort) -> Result<Vec<ConversationRow>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, title, full_text, token_estimate FROM conversations WHERE excluded = 0 ORDER BY {}",
+        sort.order_by()
+    ))?;
     let rows = stmt.query_map([], |row| {
         Ok(ConversationRow {
             id: row.get(0)?,
@@ -68,27 +691,548 @@ pub fn get_all_conversations(conn: &Connection) -> Result<Vec<ConversationRow>>
     rows.collect()
 }
 
+#[derive(Default)]
 pub struct ExportRow {
     pub id: String,
+    pub kind: ConversationKind,
+    /// Group chat participants; empty for regular chats.
+    pub participants: Vec<String>,
     pub title: Option<String>,
     pub created_at: Option<i64>,
+    pub updated_at: Option<i64>,
+    /// See `LAST_ACTIVE_SQL`.
+    pub last_active_at: Option<i64>,
+    pub message_count: i64,
+    pub has_images: bool,
+    pub has_code: bool,
     pub full_text: String,
     pub gizmo_id: Option<String>,
+    pub cluster_label: Option<String>,
+    /// Project the user put the conversation in (see `set_project`).
+    pub project_name: Option<String>,
+    pub summary: Option<String>,
+    pub instructions: Option<String>,
+    /// Public share links created from this conversation.
+    pub shares: Vec<ShareLink>,
+    /// Accounts whose exports contained this conversation (see `Account::label`).
+    pub accounts: Vec<String>,
 }
 
-/// Fetches all conversations for markdown export.
-pub fn get_conversations_for_export(conn: &Connection) -> Result<Vec<ExportRow>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, title, created_at, full_text, gizmo_id FROM conversations ORDER BY created_at ASC",
-    )?;
+pub struct ShareLink {
+    pub url: String,
+    pub anonymous: bool,
+}
+
+impl ExportRow {
+    /// The group the conversation is filed under: its project, which overrides the AI
+    /// cluster, or else its cluster.
+    pub fn group(&self) -> Option<Group<'_>> {
+        fn named(name: &Option<String>) -> Option<&str> {
+            name.as_deref().filter(|n| !n.trim().is_empty())
+        }
+        named(&self.project_name)
+            .map(Group::Project)
+            .or_else(|| named(&self.cluster_label).map(Group::Cluster))
+    }
+}
+
+/// A project or an AI cluster (see `ExportRow::group`). The two are kept apart even when a
+/// project has the same name as a cluster.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Group<'a> {
+    Project(&'a str),
+    Cluster(&'a str),
+}
+
+impl<'a> Group<'a> {
+    pub fn label(self) -> &'a str {
+        match self {
+            Group::Project(name) | Group::Cluster(name) => name,
+        }
+    }
+
+    /// Name of the group's folder or pack: projects are prefixed, so they never share one
+    /// with a cluster.
+    pub fn folder_label(self) -> String {
+        match self {
+            Group::Project(name) => format!("Project - {name}"),
+            Group::Cluster(label) => label.to_string(),
+        }
+    }
+}
+
+/// Fetches all conversations for markdown export in `sort` order.
+pub fn get_conversations_for_export(conn: &Connection, sort: ConversationSort) -> Result<Vec<ExportRow>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, title, created_at, updated_at, {LAST_ACTIVE_SQL}, message_count, has_images,
+                has_code, full_text, gizmo_id, cluster_label, summary, instructions, kind, participants,
+                project_name
+         FROM conversations WHERE excluded = 0 ORDER BY {}",
+        sort.order_by()
+    ))?;
     let rows = stmt.query_map([], |row| {
+        let kind: Option<String> = row.get(13)?;
+        let participants: Option<String> = row.get(14)?;
         Ok(ExportRow {
             id: row.get(0)?,
+            kind: ConversationKind::parse(kind.as_deref()),
+            participants: participants
+                .and_then(|p| serde_json::from_str(&p).ok())
+                .unwrap_or_default(),
             title: row.get(1)?,
             created_at: row.get(2)?,
-            full_text: row.get(3)?,
-            gizmo_id: row.get(4)?,
+            updated_at: row.get(3)?,
+            last_active_at: row.get(4)?,
+            message_count: row.get(5)?,
+            has_images: row.get(6)?,
+            has_code: row.get(7)?,
+            full_text: row.get(8)?,
+            gizmo_id: row.get(9)?,
+            cluster_label: row.get(10)?,
+            project_name: row.get(15)?,
+            summary: row.get(11)?,
+            instructions: row.get(12)?,
+            shares: Vec::new(),
+            accounts: Vec::new(),
+        })
+    })?;
+    let mut conversations: Vec<ExportRow> = rows.collect::<Result<_>>()?;
+
+    let mut shares: HashMap<String, Vec<ShareLink>> = HashMap::new();
+    let mut stmt = conn.prepare("SELECT conversation_id, url, anonymous FROM shared_links ORDER BY url")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, ShareLink { url: row.get(1)?, anonymous: row.get(2)? }))
+    })?;
+    for row in rows {
+        let (conversation_id, link) = row?;
+        shares.entry(conversation_id).or_default().push(link);
+    }
+
+    let mut accounts: HashMap<String, Vec<String>> = HashMap::new();
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT cs.conversation_id, COALESCE(s.account_email, s.account_name) AS account
+         FROM conversation_sources cs JOIN import_sources s ON s.id = cs.source_id
+         WHERE account IS NOT NULL ORDER BY account",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+    for row in rows {
+        let (conversation_id, account) = row?;
+        accounts.entry(conversation_id).or_default().push(account);
+    }
+
+    for conv in &mut conversations {
+        conv.shares = shares.remove(&conv.id).unwrap_or_default();
+        conv.accounts = accounts.remove(&conv.id).unwrap_or_default();
+    }
+    Ok(conversations)
+}
+
+pub struct MessageRow {
+    pub id: String,
+    pub role: String,
+    /// Group chat author, shown instead of the role.
+    pub author_name: Option<String>,
+    pub kind: MessageKind,
+    /// Tool name for tool calls and outputs.
+    pub tool: Option<String>,
+    pub content_type: Option<String>,
+    pub created_at: Option<i64>,
+    pub text: String,
+    /// File ids of images and recordings in the message.
+    pub assets: Vec<String>,
+    /// Files uploaded with the message.
+    pub attachments: Vec<Attachment>,
+    /// The user's thumbs up/down on this message, if any.
+    pub feedback: Option<Feedback>,
+}
+
+pub struct Feedback {
+    pub positive: bool,
+    pub tags: Vec<String>,
+    pub text: Option<String>,
+}
+
+/// Fetches the stored messages of one conversation in order, tool calls and outputs included —
+/// filter them with an `InclusionPolicy`.
+/// Empty for conversations imported before messages were stored — callers fall back to full_text.
+pub fn get_messages(conn: &Connection, conversation_id: &str) -> Result<Vec<MessageRow>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT m.role, m.created_at, m.text, m.assets, m.kind, m.tool, m.content_type, m.id,
+                f.positive, f.tags, f.text, m.author_name, m.attachments
+         FROM messages m
+         -- Latest rating only, in case the user changed their mind
+         LEFT JOIN message_feedback f ON f.id = (
+             SELECT id FROM message_feedback
+             WHERE conversation_id = m.conversation_id AND message_id = m.id
+             ORDER BY created_at DESC LIMIT 1)
+         WHERE m.conversation_id = ?1 ORDER BY m.position ASC",
+    )?;
+    let rows = stmt.query_map(params![conversation_id], |row| {
+        let assets: Option<String> = row.get(3)?;
+        let attachments: Option<String> = row.get(12)?;
+        let kind: Option<String> = row.get(4)?;
+        let positive: Option<bool> = row.get(8)?;
+        let tags: Option<String> = row.get(9)?;
+        Ok(MessageRow {
+            id: row.get(7)?,
+            author_name: row.get(11)?,
+            feedback: match positive {
+                Some(positive) => Some(Feedback {
+                    positive,
+                    tags: tags.and_then(|t| serde_json::from_str(&t).ok()).unwrap_or_default(),
+                    text: row.get(10)?,
+                }),
+                None => None,
+            },
+            role: row.get(0)?,
+            kind: MessageKind::parse(kind.as_deref()),
+            tool: row.get(5)?,
+            content_type: row.get(6)?,
+            created_at: row.get(1)?,
+            text: row.get(2)?,
+            assets: assets
+                .and_then(|a| serde_json::from_str(&a).ok())
+                .unwrap_or_default(),
+            attachments: attachments
+                .and_then(|a| serde_json::from_str(&a).ok())
+                .unwrap_or_default(),
         })
     })?;
     rows.collect()
 }
+
+/// Distinct model slugs used in a conversation, in order of first use.
+pub fn get_model_slugs(conn: &Connection, conversation_id: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT model_slug FROM messages
+         WHERE conversation_id = ?1 AND model_slug IS NOT NULL
+         GROUP BY model_slug ORDER BY MIN(position)",
+    )?;
+    let rows = stmt.query_map(params![conversation_id], |row| row.get(0))?;
+    rows.collect()
+}
+
+/// One distinct custom-instruction version or memory, aggregated across conversations.
+pub struct ProfileVersion {
+    pub kind: ProfileKind,
+    pub text: String,
+    pub first_seen: Option<i64>,
+    pub last_seen: Option<i64>,
+    pub conversations: i64,
+}
+
+/// Distinct profile texts with the span of time they were seen in, oldest first per kind.
+pub fn get_profile_versions(conn: &Connection) -> Result<Vec<ProfileVersion>> {
+    let mut stmt = conn.prepare(
+        &format!(
+            "SELECT kind, text, MIN(seen_at), MAX(seen_at), COUNT(*) FROM profile_entries
+             WHERE conversation_id NOT IN ({EXCLUDED_SQL})
+             GROUP BY kind, text ORDER BY kind, MIN(seen_at) ASC"
+        ),
+    )?;
+    let rows = stmt.query_map([], |row| {
+        let kind: String = row.get(0)?;
+        Ok((kind, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+    })?;
+    let mut versions = Vec::new();
+    for row in rows {
+        let (kind, text, first_seen, last_seen, conversations) = row?;
+        // Skip kinds written by a newer version of the app
+        if let Some(kind) = ProfileKind::parse(&kind) {
+            versions.push(ProfileVersion { kind, text, first_seen, last_seen, conversations });
+        }
+    }
+    Ok(versions)
+}
+
+/// Conversations and assistant messages per model.
+#[derive(serde::Serialize)]
+pub struct ModelUsage {
+    pub model: String,
+    pub conversations: i64,
+    pub messages: i64,
+}
+
+/// Conversations and calls per tool category.
+#[derive(serde::Serialize)]
+pub struct ToolUsage {
+    pub category: ToolCategory,
+    pub conversations: i64,
+    pub calls: i64,
+}
+
+/// Minimal conversation reference returned by usage queries.
+#[derive(serde::Serialize)]
+pub struct ConversationRef {
+    pub id: String,
+    pub title: Option<String>,
+    pub created_at: Option<i64>,
+    /// Marked private (see `set_private`).
+    pub private: bool,
+}
+
+/// Model usage across the archive, or across one account's conversations, most used first.
+pub fn get_model_usage(conn: &Connection, account: Option<&str>) -> Result<Vec<ModelUsage>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT model_slug, COUNT(DISTINCT conversation_id), COUNT(*) FROM messages
+         WHERE model_slug IS NOT NULL AND IFNULL(kind, 'message') = 'message'
+           AND (?1 IS NULL OR conversation_id IN ({ACCOUNT_CONVERSATIONS_SQL}))
+           AND conversation_id NOT IN ({EXCLUDED_SQL})
+         GROUP BY model_slug ORDER BY 2 DESC, 3 DESC"
+    ))?;
+    let rows = stmt.query_map(params![account], |row| {
+        Ok(ModelUsage { model: row.get(0)?, conversations: row.get(1)?, messages: row.get(2)? })
+    })?;
+    rows.collect()
+}
+
+/// Tool usage across the archive, or across one account's conversations, most used first.
+pub fn get_tool_usage(conn: &Connection, account: Option<&str>) -> Result<Vec<ToolUsage>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT category, COUNT(DISTINCT conversation_id), COUNT(*) FROM tool_calls
+         WHERE (?1 IS NULL OR conversation_id IN ({ACCOUNT_CONVERSATIONS_SQL}))
+           AND conversation_id NOT IN ({EXCLUDED_SQL})
+         GROUP BY category ORDER BY 2 DESC, 3 DESC"
+    ))?;
+    let rows = stmt.query_map(params![account], |row| {
+        let category: String = row.get(0)?;
+        Ok((category, row.get(1)?, row.get(2)?))
+    })?;
+    let mut usage = Vec::new();
+    for row in rows {
+        let (category, conversations, calls) = row?;
+        if let Some(category) = ToolCategory::parse(&category) {
+            usage.push(ToolUsage { category, conversations, calls });
+        }
+    }
+    Ok(usage)
+}
+
+/// Conversations that used `model` (exact slug) and/or a tool of `tool` category, newest first.
+/// With neither filter set, returns every conversation.
+pub fn find_conversations_by_usage(
+    conn: &Connection,
+    model: Option<&str>,
+    tool: Option<ToolCategory>,
+) -> Result<Vec<ConversationRef>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, title, created_at, id IN (SELECT conversation_id FROM private_conversations)
+         FROM conversations c
+         WHERE (?1 IS NULL OR EXISTS (SELECT 1 FROM messages m
+                                      WHERE m.conversation_id = c.id AND m.model_slug = ?1))
+           AND (?2 IS NULL OR EXISTS (SELECT 1 FROM tool_calls t
+                                      WHERE t.conversation_id = c.id AND t.category = ?2))
+         ORDER BY created_at DESC",
+    )?;
+    let rows = stmt.query_map(params![model, tool.map(ToolCategory::as_str)], |row| {
+        Ok(ConversationRef { id: row.get(0)?, title: row.get(1)?, created_at: row.get(2)?, private: row.get(3)? })
+    })?;
+    rows.collect()
+}
+
+pub struct ComparisonRow {
+    pub conversation_id: Option<String>,
+    pub conversation_title: Option<String>,
+    pub created_at: Option<i64>,
+    pub rating: Option<String>,
+}
+
+/// Answer comparisons, newest first, with the title of the conversation they came from.
+pub fn get_comparisons(conn: &Connection) -> Result<Vec<ComparisonRow>> {
+    let mut stmt = conn.prepare(
+        "SELECT mc.conversation_id, c.title, mc.created_at, mc.rating
+         FROM model_comparisons mc LEFT JOIN conversations c ON c.id = mc.conversation_id
+         WHERE IFNULL(c.excluded, 0) = 0
+         ORDER BY mc.created_at DESC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(ComparisonRow {
+            conversation_id: row.get(0)?,
+            conversation_title: row.get(1)?,
+            created_at: row.get(2)?,
+            rating: row.get(3)?,
+        })
+    })?;
+    rows.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn
+    }
+
+    fn record(id: &str, title: &str, updated_at: i64) -> ConversationRecord {
+        ConversationRecord {
+            id: id.to_string(),
+            title: title.to_string(),
+            created_at: Some(1_000),
+            updated_at: Some(updated_at),
+            ..Default::default()
+        }
+    }
+
+    fn title(conn: &Connection, id: &str) -> String {
+        conn.query_row("SELECT title FROM conversations WHERE id = ?1", [id], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn latest_version_wins_in_either_import_order() {
+        let conn = open();
+        assert!(insert_conversation_if_newer(&conn, &record("c1", "Newer", 2_000)).unwrap());
+        // An older export imported afterwards must not overwrite the newer version
+        assert!(!insert_conversation_if_newer(&conn, &record("c1", "Older", 1_500)).unwrap());
+        assert_eq!(title(&conn, "c1"), "Newer");
+
+        // The same version again (re-importing one export) is written
+        assert!(insert_conversation_if_newer(&conn, &record("c1", "Newer, again", 2_000)).unwrap());
+        assert_eq!(title(&conn, "c1"), "Newer, again");
+
+        // A newer message counts even when update_time lags behind
+        let continued = ConversationRecord { last_message_at: Some(3_000), ..record("c1", "Continued", 1_500) };
+        assert!(insert_conversation_if_newer(&conn, &continued).unwrap());
+        assert_eq!(title(&conn, "c1"), "Continued");
+        assert!(!insert_conversation_if_newer(&conn, &record("c1", "Stale", 2_500)).unwrap());
+        assert_eq!(title(&conn, "c1"), "Continued");
+
+        // Unknown conversations are always written
+        assert!(insert_conversation_if_newer(&conn, &record("c2", "Other", 0)).unwrap());
+
+        // A newer version stays in its cluster and project, and stays excluded
+        conn.execute(
+            "UPDATE conversations SET cluster_label = 'Travel', summary = 'Trip plans',
+                 instructions = 'Plan trips', project_name = 'Holidays', excluded = 1
+             WHERE id = 'c1'",
+            [],
+        )
+        .unwrap();
+        assert!(insert_conversation_if_newer(&conn, &record("c1", "Clustered", 4_000)).unwrap());
+        assert_eq!(title(&conn, "c1"), "Clustered");
+        let kept: (String, String, String, String, i64) = conn
+            .query_row(
+                "SELECT cluster_label, summary, instructions, project_name, excluded FROM conversations WHERE id = 'c1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+            )
+            .unwrap();
+        assert_eq!(kept, ("Travel".into(), "Trip plans".into(), "Plan trips".into(), "Holidays".into(), 1));
+    }
+
+    #[test]
+    fn imported_conversations_are_checked_against_saved_exclusions() {
+        let conn = open();
+        insert_exclusion_rule(&conn, &ExclusionRule::Title { pattern: "diagnosis".to_string() }, 0).unwrap();
+        set_private(&conn, "c3", true).unwrap();
+        let exclusions = get_exclusions(&conn).unwrap();
+
+        for (id, title) in [("c1", "My Diagnosis"), ("c2", "Bread"), ("c3", "Letters")] {
+            let record = record(id, title, 1_000);
+            insert_conversation_if_newer(&conn, &record).unwrap();
+            apply_exclusions(&conn, &record, &exclusions).unwrap();
+        }
+        let mut excluded: Vec<String> = get_excluded_ids(&conn).unwrap().into_iter().collect();
+        excluded.sort();
+        assert_eq!(excluded, ["c1", "c3"]);
+        assert_eq!(update_exclusions(&conn).unwrap(), 2);
+    }
+
+    fn project_of(conn: &Connection, id: &str) -> Option<String> {
+        conn.query_row("SELECT project_name FROM conversations WHERE id = ?1", [id], |row| row.get(0)).unwrap()
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn set_project_counts_changed_conversations() {
+        let conn = open();
+        for id in ["c1", "c2"] {
+            insert_conversation_if_newer(&conn, &record(id, id, 1_000)).unwrap();
+        }
+        assert!(insert_project(&conn, "Work", 0).unwrap());
+        assert!(!insert_project(&conn, "work", 0).unwrap());
+
+        assert_eq!(set_project(&conn, Some("Work"), &ids(&["c1", "c2", "missing"])).unwrap(), 2);
+        // Conversations already in the project do not count
+        assert_eq!(set_project(&conn, Some("Work"), &ids(&["c1"])).unwrap(), 0);
+        assert_eq!(project_of(&conn, "c2").as_deref(), Some("Work"));
+
+        assert_eq!(set_project(&conn, None, &ids(&["c1"])).unwrap(), 1);
+        assert_eq!(project_of(&conn, "c1"), None);
+        assert_eq!(set_project(&conn, None, &ids(&["c1"])).unwrap(), 0);
+        assert_eq!(get_projects(&conn).unwrap()[0].conversations, 1);
+    }
+
+    #[test]
+    fn renamed_and_deleted_projects_take_their_conversations_along() {
+        let conn = open();
+        insert_conversation_if_newer(&conn, &record("c1", "One", 1_000)).unwrap();
+        insert_project(&conn, "Work", 0).unwrap();
+        insert_project(&conn, "Home", 0).unwrap();
+        set_project(&conn, Some("Work"), &ids(&["c1"])).unwrap();
+
+        rename_project(&conn, "Work", "Job").unwrap();
+        assert_eq!(project_of(&conn, "c1").as_deref(), Some("Job"));
+        // Changing only the case is a rename of the same project
+        rename_project(&conn, "Job", "JOB").unwrap();
+        assert_eq!(find_project(&conn, "job").unwrap().as_deref(), Some("JOB"));
+        assert_eq!(project_of(&conn, "c1").as_deref(), Some("JOB"));
+
+        // A name taken by another project in a different case collides
+        assert_eq!(find_project(&conn, "home").unwrap().as_deref(), Some("Home"));
+        let err = rename_project(&conn, "JOB", "home").unwrap_err();
+        assert!(err.to_string().contains("UNIQUE"), "{err}");
+        assert_eq!(project_of(&conn, "c1").as_deref(), Some("JOB"));
+
+        delete_project(&conn, "JOB").unwrap();
+        assert_eq!(find_project(&conn, "JOB").unwrap(), None);
+        assert_eq!(project_of(&conn, "c1"), None);
+    }
+
+    #[test]
+    fn search_matches_every_term_in_title_or_text() {
+        let conn = open();
+        let with_text = |id: &str, title: &str, text: &str| ConversationRecord {
+            full_text: text.to_string(),
+            ..record(id, title, 1_000)
+        };
+        insert_conversation_if_newer(&conn, &with_text("c1", "Rust lifetimes", "borrow checker errors")).unwrap();
+        insert_conversation_if_newer(&conn, &with_text("c2", "Dinner", "a RUST-coloured sauce")).unwrap();
+        insert_conversation_if_newer(&conn, &with_text("c3", "Other", "nothing here")).unwrap();
+
+        let mut found = search_conversation_ids(&conn, "rust").unwrap();
+        found.sort();
+        assert_eq!(found, ["c1", "c2"]);
+        // Terms may be split between title and text, in any case
+        assert_eq!(search_conversation_ids(&conn, "  LIFETIMES  Borrow ").unwrap(), ["c1"]);
+        assert!(search_conversation_ids(&conn, "rust pasta").unwrap().is_empty());
+        assert!(search_conversation_ids(&conn, "   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn projects_and_clusters_of_the_same_name_stay_apart() {
+        let clustered = ExportRow { cluster_label: Some("Travel".to_string()), ..Default::default() };
+        let in_project = ExportRow {
+            project_name: Some("Travel".to_string()),
+            cluster_label: Some("Travel".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(clustered.group(), Some(Group::Cluster("Travel")));
+        assert_eq!(in_project.group(), Some(Group::Project("Travel")));
+        assert_ne!(clustered.group().unwrap().folder_label(), in_project.group().unwrap().folder_label());
+        assert_eq!(in_project.group().unwrap().label(), "Travel");
+        // A blank project name falls back to the cluster
+        let blank = ExportRow {
+            project_name: Some(" ".to_string()),
+            cluster_label: Some("Travel".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(blank.group(), Some(Group::Cluster("Travel")));
+        assert_eq!(ExportRow::default().group(), None);
+    }
+}
@@ -0,0 +1,160 @@
+use rand::RngCore;
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+/// First bytes of every unencrypted SQLite file. SQLCipher files start with random salt.
+const PLAINTEXT_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// A new random 256-bit database key, as 64 hex digits.
+pub fn new_key() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// SQLCipher syntax for a raw hex key, which skips the slow passphrase derivation.
+fn raw_key(key: &str) -> String {
+    format!("x'{key}'")
+}
+
+/// Whether the database at `path` exists and is unencrypted.
+pub fn is_plaintext(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    std::fs::File::open(path)
+        .and_then(|mut f| std::io::Read::read_exact(&mut f, &mut header))
+        .is_ok_and(|_| header == PLAINTEXT_HEADER)
+}
+
+/// Whether the database at `path` exists, has data and is not plaintext — so it can't be
+/// opened without its key.
+pub fn is_encrypted(path: &Path) -> bool {
+    std::fs::metadata(path).is_ok_and(|m| m.len() > 0) && !is_plaintext(path)
+}
+
+/// Opens the database at `path`, encrypted with `key` or unencrypted when `key` is `None`,
+/// and checks that the key fits. A new database is created encrypted when `key` is given.
+pub fn open(path: &Path, key: Option<&str>) -> Result<Connection, String> {
+    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+    if let Some(key) = key {
+        conn.pragma_update(None, "key", raw_key(key)).map_err(|e| e.to_string())?;
+    }
+    // SQLCipher only reads the file on first use; a wrong key fails here
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))
+        .map_err(|_| "The database could not be opened: it is encrypted with another key, or damaged".to_string())?;
+    Ok(conn)
+}
+
+/// Opens conversations.db at startup with the key from the keychain (`None` when there
+/// is none). An unencrypted database is encrypted first when a key exists — that is how
+/// a migration interrupted by a crash or a forced quit is finished.
+pub fn open_store(path: &Path, key: Option<&str>) -> Result<Connection, String> {
+    match key {
+        Some(key) if is_plaintext(path) => {
+            let mut conn = open(path, None)?;
+            rekey(&mut conn, path, None, Some(key))?;
+            Ok(conn)
+        }
+        None if is_encrypted(path) => {
+            Err("conversations.db is encrypted, but its key is missing from the keychain".to_string())
+        }
+        _ => open(path, key),
+    }
+}
+
+/// Rewrites the database at `path` with a new key — `None` for no encryption — and
+/// reopens `conn` on the result.
+///
+/// The data is copied into a new file with `sqlcipher_export`, which then replaces the
+/// old one, so an interruption leaves the old file as it was. `from` is the current key,
+/// used to reopen the old file if it can't be replaced.
+pub fn rekey(conn: &mut Connection, path: &Path, from: Option<&str>, to: Option<&str>) -> Result<(), String> {
+    let target = path.with_extension("db.rekey");
+    let _ = std::fs::remove_file(&target);
+    let version: i64 = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    let copied = conn
+        .execute(
+            "ATTACH DATABASE ?1 AS rekeyed KEY ?2",
+            params![target.to_string_lossy(), to.map(raw_key).unwrap_or_default()],
+        )
+        .and_then(|_| conn.query_row("SELECT sqlcipher_export('rekeyed')", [], |_| Ok(())))
+        .and_then(|_| conn.execute_batch(&format!("PRAGMA rekeyed.user_version = {version}")));
+    let _ = conn.execute_batch("DETACH DATABASE rekeyed");
+    if let Err(e) = copied {
+        let _ = std::fs::remove_file(&target);
+        return Err(e.to_string());
+    }
+
+    // The old connection has to be closed before its file is replaced (Windows locks it)
+    let placeholder = Connection::open_in_memory().map_err(|e| e.to_string())?;
+    let old = std::mem::replace(conn, placeholder);
+    old.close().map_err(|(_, e)| e.to_string())?;
+    match std::fs::rename(&target, path) {
+        Ok(()) => {
+            *conn = open(path, to)?;
+            Ok(())
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&target);
+            *conn = open(path, from)?;
+            Err(e.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypts_and_decrypts_in_place() {
+        let dir = std::env::temp_dir().join(format!("encryption-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("conversations.db");
+        let _ = std::fs::remove_file(&path);
+        assert!(!is_encrypted(&path));
+
+        let mut conn = open(&path, None).unwrap();
+        conn.execute_batch("CREATE TABLE t (x TEXT); INSERT INTO t VALUES ('secret'); PRAGMA user_version = 3")
+            .unwrap();
+        assert!(is_plaintext(&path));
+
+        let key = new_key();
+        assert!(!is_encrypted(&path));
+        rekey(&mut conn, &path, None, Some(&key)).unwrap();
+        assert!(!is_plaintext(&path));
+        assert!(is_encrypted(&path));
+        drop(conn);
+        assert!(open(&path, None).is_err());
+        assert!(open(&path, Some(&new_key())).is_err());
+        assert!(open_store(&path, None).is_err());
+
+        let mut conn = open_store(&path, Some(&key)).unwrap();
+        let x: String = conn.query_row("SELECT x FROM t", [], |row| row.get(0)).unwrap();
+        assert_eq!(x, "secret");
+        let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0)).unwrap();
+        assert_eq!(version, 3);
+
+        rekey(&mut conn, &path, Some(&key), None).unwrap();
+        assert!(is_plaintext(&path));
+        drop(conn);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn startup_finishes_an_interrupted_migration() {
+        let dir = std::env::temp_dir().join(format!("encryption-migrate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("conversations.db");
+        let _ = std::fs::remove_file(&path);
+        open(&path, None).unwrap().execute_batch("CREATE TABLE t (x TEXT)").unwrap();
+
+        // The key reached the keychain, but the app quit before the file was rewritten
+        let key = new_key();
+        open_store(&path, Some(&key)).unwrap();
+        assert!(!is_plaintext(&path));
+        assert!(open(&path, Some(&key)).is_ok());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
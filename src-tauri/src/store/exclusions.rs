@@ -0,0 +1,165 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// A persistent rule keeping matching conversations out of exports, project packs and
+/// clustering requests (see `db::update_exclusions`).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum ExclusionRule {
+    /// One conversation.
+    Id { id: String },
+    /// Titles matching a regex, ignoring case.
+    Title { pattern: String },
+    /// Conversations made with a custom GPT or ChatGPT project.
+    Gizmo {
+        #[serde(rename = "gizmoId")]
+        gizmo_id: String,
+    },
+    /// Conversations started in a time span (Unix seconds, inclusive); either end may be open.
+    DateRange { from: Option<i64>, to: Option<i64> },
+    /// Transcripts containing this text, ignoring case.
+    Content { text: String },
+}
+
+impl ExclusionRule {
+    /// Checks a rule before it is saved: non-empty values, a valid regex, an ordered range.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            ExclusionRule::Id { id: value }
+            | ExclusionRule::Gizmo { gizmo_id: value }
+            | ExclusionRule::Content { text: value }
+                if value.trim().is_empty() =>
+            {
+                Err("The rule needs a value".to_string())
+            }
+            ExclusionRule::Title { pattern } => title_regex(pattern).map(|_| ()),
+            ExclusionRule::DateRange { from: None, to: None } => Err("The date range needs a start or an end".to_string()),
+            ExclusionRule::DateRange { from: Some(from), to: Some(to) } if from > to => {
+                Err("The date range ends before it starts".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// `(kind, value, starts_at, ends_at)` columns of `exclusion_rules`.
+    pub fn to_columns(&self) -> (&'static str, Option<&str>, Option<i64>, Option<i64>) {
+        match self {
+            ExclusionRule::Id { id } => ("id", Some(id), None, None),
+            ExclusionRule::Title { pattern } => ("title", Some(pattern), None, None),
+            ExclusionRule::Gizmo { gizmo_id } => ("gizmo", Some(gizmo_id), None, None),
+            ExclusionRule::DateRange { from, to } => ("dateRange", None, *from, *to),
+            ExclusionRule::Content { text } => ("content", Some(text), None, None),
+        }
+    }
+
+    /// Inverse of `to_columns`; `None` for kinds written by a newer version of the app.
+    pub fn from_columns(kind: &str, value: Option<String>, starts_at: Option<i64>, ends_at: Option<i64>) -> Option<ExclusionRule> {
+        let value = value.unwrap_or_default();
+        Some(match kind {
+            "id" => ExclusionRule::Id { id: value },
+            "title" => ExclusionRule::Title { pattern: value },
+            "gizmo" => ExclusionRule::Gizmo { gizmo_id: value },
+            "dateRange" => ExclusionRule::DateRange { from: starts_at, to: ends_at },
+            "content" => ExclusionRule::Content { text: value },
+            _ => return None,
+        })
+    }
+}
+
+fn title_regex(pattern: &str) -> Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| format!("Invalid title pattern: {e}"))
+}
+
+/// The fields of a conversation the rules look at.
+pub struct Candidate<'a> {
+    pub id: &'a str,
+    pub title: Option<&'a str>,
+    pub gizmo_id: Option<&'a str>,
+    pub created_at: Option<i64>,
+    pub full_text: &'a str,
+}
+
+/// Every exclusion rule plus the conversations marked private, ready for matching.
+#[derive(Default)]
+pub struct Exclusions {
+    ids: HashSet<String>,
+    titles: Vec<Regex>,
+    gizmos: HashSet<String>,
+    ranges: Vec<(Option<i64>, Option<i64>)>,
+    contents: Vec<String>,
+}
+
+impl Exclusions {
+    pub fn new(rules: &[ExclusionRule], private: impl IntoIterator<Item = String>) -> Exclusions {
+        let mut exclusions = Exclusions { ids: private.into_iter().collect(), ..Default::default() };
+        for rule in rules {
+            match rule {
+                ExclusionRule::Id { id } => {
+                    exclusions.ids.insert(id.clone());
+                }
+                // Validated when saved; a pattern that no longer compiles matches nothing
+                ExclusionRule::Title { pattern } => exclusions.titles.extend(title_regex(pattern).ok()),
+                ExclusionRule::Gizmo { gizmo_id } => {
+                    exclusions.gizmos.insert(gizmo_id.clone());
+                }
+                ExclusionRule::DateRange { from, to } => exclusions.ranges.push((*from, *to)),
+                ExclusionRule::Content { text } => exclusions.contents.push(text.to_lowercase()),
+            }
+        }
+        exclusions
+    }
+
+    pub fn excludes(&self, conv: &Candidate) -> bool {
+        self.ids.contains(conv.id)
+            || conv.title.is_some_and(|t| self.titles.iter().any(|r| r.is_match(t)))
+            || conv.gizmo_id.is_some_and(|g| self.gizmos.contains(g))
+            || conv.created_at.is_some_and(|ts| {
+                self.ranges
+                    .iter()
+                    .any(|(from, to)| from.is_none_or(|f| ts >= f) && to.is_none_or(|t| ts <= t))
+            })
+            || (!self.contents.is_empty() && {
+                let text = conv.full_text.to_lowercase();
+                self.contents.iter().any(|c| text.contains(c.as_str()))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate<'a>(id: &'a str, title: &'a str, created_at: i64, full_text: &'a str) -> Candidate<'a> {
+        Candidate { id, title: Some(title), gizmo_id: None, created_at: Some(created_at), full_text }
+    }
+
+    #[test]
+    fn rules_and_private_marks_exclude() {
+        let rules = [
+            ExclusionRule::Title { pattern: "^therapy".to_string() },
+            ExclusionRule::DateRange { from: Some(100), to: Some(200) },
+            ExclusionRule::Content { text: "Project Falcon".to_string() },
+        ];
+        let exclusions = Exclusions::new(&rules, ["c-private".to_string()]);
+        assert!(exclusions.excludes(&candidate("a", "Therapy notes", 50, "")));
+        assert!(exclusions.excludes(&candidate("b", "Trip", 150, "")));
+        assert!(exclusions.excludes(&candidate("c", "Plans", 300, "about project falcon")));
+        assert!(exclusions.excludes(&candidate("c-private", "Plans", 300, "")));
+        assert!(!exclusions.excludes(&candidate("d", "My therapy", 300, "falcon")));
+    }
+
+    #[test]
+    fn rules_round_trip_and_validate() {
+        let rule = ExclusionRule::DateRange { from: None, to: Some(5) };
+        let (kind, value, starts_at, ends_at) = rule.to_columns();
+        let back = ExclusionRule::from_columns(kind, value.map(String::from), starts_at, ends_at);
+        assert!(matches!(back, Some(ExclusionRule::DateRange { from: None, to: Some(5) })));
+        assert!(ExclusionRule::Title { pattern: "(".to_string() }.validate().is_err());
+        assert!(ExclusionRule::DateRange { from: Some(9), to: Some(1) }.validate().is_err());
+        assert!(ExclusionRule::Id { id: " ".to_string() }.validate().is_err());
+    }
+}
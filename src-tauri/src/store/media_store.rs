@@ -0,0 +1,260 @@
+use crate::pipeline::source::sha256_hex;
+use std::path::{Path, PathBuf};
+
+/// App-managed copies of export media, stored by content hash under the app data dir:
+/// `media/ab/abcdef…` where `abcdef…` is the file's SHA-256.
+///
+/// Once an export's media is here, the original ZIP or folder can be moved or deleted and
+/// exports still include it. Identical files from different exports are stored once.
+pub struct MediaStore {
+    root: PathBuf,
+}
+
+impl MediaStore {
+    pub fn new(root: PathBuf) -> MediaStore {
+        MediaStore { root }
+    }
+
+    /// Stores `bytes` unless a file with the same hash is already present, and returns the hash.
+    pub fn put(&self, bytes: &[u8]) -> Result<String, String> {
+        let hash = sha256_hex([bytes]);
+        let path = self.path(&hash);
+        if !path.exists() {
+            let dir = path.parent().unwrap_or(&self.root);
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            // Write then rename, so an interrupted copy never leaves a truncated file under its hash
+            let partial = dir.join(format!("{hash}.partial"));
+            std::fs::write(&partial, bytes).map_err(|e| e.to_string())?;
+            std::fs::rename(&partial, &path).map_err(|e| e.to_string())?;
+        }
+        Ok(hash)
+    }
+
+    /// Where the file with `hash` is (or would be) stored.
+    pub fn path(&self, hash: &str) -> PathBuf {
+        self.root.join(hash.get(..2).unwrap_or("00")).join(hash)
+    }
+
+    /// Puts the stored file with `hash` at `dest`: a hard link where the file system allows
+    /// it, a copy otherwise. A file of the same size already at `dest` is left as it is, so
+    /// repeated exports don't rewrite unchanged media.
+    pub fn link_to(&self, hash: &str, dest: &Path) -> Result<(), String> {
+        let stored = self.path(hash);
+        let size = std::fs::metadata(&stored).map_err(|e| e.to_string())?.len();
+        if std::fs::metadata(dest).is_ok_and(|m| m.len() == size) {
+            return Ok(());
+        }
+        let _ = std::fs::remove_file(dest);
+        if std::fs::hard_link(&stored, dest).is_err() {
+            std::fs::copy(&stored, dest).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+/// What a media file is, judged from its bytes (falling back to the name for formats
+/// without a signature, such as CSV or source code).
+#[derive(Debug, PartialEq)]
+pub struct MediaInfo {
+    pub mime_type: &'static str,
+    /// Pixel size, for PNG, JPEG, GIF and WebP images.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+pub fn sniff(name: &str, bytes: &[u8]) -> MediaInfo {
+    let ext = Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let (mime_type, size) = if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        ("image/png", png_size(bytes))
+    } else if bytes.starts_with(b"\xFF\xD8\xFF") {
+        ("image/jpeg", jpeg_size(bytes))
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        ("image/gif", gif_size(bytes))
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        ("image/webp", webp_size(bytes))
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WAVE") {
+        ("audio/wav", None)
+    } else if bytes.starts_with(b"%PDF") {
+        ("application/pdf", None)
+    } else if bytes.starts_with(b"OggS") {
+        ("audio/ogg", None)
+    } else if bytes.starts_with(b"ID3") || (bytes.len() > 1 && bytes[0] == 0xFF && bytes[1] & 0xE0 == 0xE0) {
+        ("audio/mpeg", None)
+    } else if bytes.starts_with(b"\x1A\x45\xDF\xA3") {
+        ("video/webm", None)
+    } else if bytes.get(4..8) == Some(b"ftyp") {
+        (if bytes.get(8..11) == Some(b"M4A") { "audio/mp4" } else { "video/mp4" }, None)
+    } else if bytes.starts_with(b"PK\x03\x04") {
+        // Office documents are ZIPs; only the name tells them apart
+        let mime = match ext.as_str() {
+            "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+            _ => "application/zip",
+        };
+        (mime, None)
+    } else if let Some(text) = utf8_prefix(bytes) {
+        let mime = match ext.as_str() {
+            _ if text.contains("<svg") => "image/svg+xml",
+            "csv" => "text/csv",
+            "md" => "text/markdown",
+            "html" | "htm" => "text/html",
+            "json" => "application/json",
+            _ => "text/plain",
+        };
+        (mime, None)
+    } else {
+        ("application/octet-stream", None)
+    };
+    MediaInfo {
+        mime_type,
+        width: size.map(|(w, _)| w),
+        height: size.map(|(_, h)| h),
+    }
+}
+
+/// Which archive folder a media file is exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MediaKind {
+    /// Files the user uploaded: images, PDFs, spreadsheets, code, …
+    Attachment,
+    /// Voice-mode recordings.
+    Audio,
+    /// DALL-E images and code-interpreter output.
+    Generated,
+}
+
+impl MediaKind {
+    /// `generated` is whether a tool or assistant message (rather than the user) points at the file.
+    pub fn classify(path: &str, mime_type: &str, generated: bool) -> MediaKind {
+        if mime_type.starts_with("audio/") || path.split('/').any(|dir| dir == "audio") {
+            MediaKind::Audio
+        } else if generated || path.split('/').any(|dir| dir == "dalle-generations") {
+            MediaKind::Generated
+        } else {
+            MediaKind::Attachment
+        }
+    }
+
+    pub fn folder(self) -> &'static str {
+        match self {
+            MediaKind::Attachment => "attachments",
+            MediaKind::Audio => "audio",
+            MediaKind::Generated => "generated",
+        }
+    }
+}
+
+/// The file id a media file name starts with — the id conversations use to point at it:
+/// `file-AbC123` for `file-AbC123-photo.png`, `file_00ab…` for `file_00ab…-5f1c….png`.
+pub fn file_id(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let start = if name.starts_with("file-") { 5 } else { 0 };
+    let end = name[start..].find(['-', '.']).map_or(name.len(), |i| start + i);
+    &name[..end]
+}
+
+/// The first KiB of `bytes` as text, if it is text (a character cut at the end is fine).
+fn utf8_prefix(bytes: &[u8]) -> Option<&str> {
+    let head = &bytes[..bytes.len().min(1024)];
+    match std::str::from_utf8(head) {
+        Ok(text) => Some(text),
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&head[..e.valid_up_to()]).ok(),
+        Err(_) => None,
+    }
+}
+
+fn be16(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn le16(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32)
+}
+
+fn le24(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 3)?;
+    Some(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16)
+}
+
+fn png_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    let width = u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?);
+    let height = u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?);
+    Some((width, height))
+}
+
+fn gif_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    Some((le16(bytes, 6)?, le16(bytes, 8)?))
+}
+
+/// Walks the JPEG segments up to the first start-of-frame, which holds the size.
+fn jpeg_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut at = 2;
+    while *bytes.get(at)? == 0xFF {
+        let marker = *bytes.get(at + 1)?;
+        if marker == 0xFF {
+            at += 1;
+            continue;
+        }
+        if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            return Some((be16(bytes, at + 7)?, be16(bytes, at + 5)?));
+        }
+        at += 2 + be16(bytes, at + 2)? as usize;
+    }
+    None
+}
+
+fn webp_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    match bytes.get(12..16)? {
+        b"VP8 " => Some((le16(bytes, 26)? & 0x3FFF, le16(bytes, 28)? & 0x3FFF)),
+        b"VP8L" => {
+            let bits = u32::from_le_bytes(bytes.get(21..25)?.try_into().ok()?);
+            Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        b"VP8X" => Some((le24(bytes, 24)? + 1, le24(bytes, 27)? + 1)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_image_sizes() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0DIHDR".to_vec();
+        png.extend_from_slice(&640u32.to_be_bytes());
+        png.extend_from_slice(&480u32.to_be_bytes());
+        let info = sniff("photo.jpg", &png);
+        assert_eq!(info.mime_type, "image/png");
+        assert_eq!((info.width, info.height), (Some(640), Some(480)));
+
+        // SOI, an APP0 segment, then SOF0 with height 200 and width 300
+        let jpeg = [
+            0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0xC8, 0x01,
+            0x2C,
+        ];
+        let info = sniff("x", &jpeg);
+        assert_eq!(info.mime_type, "image/jpeg");
+        assert_eq!((info.width, info.height), (Some(300), Some(200)));
+    }
+
+    #[test]
+    fn falls_back_to_the_name_for_text_and_zips() {
+        assert_eq!(sniff("data.csv", b"a,b\n1,2\n").mime_type, "text/csv");
+        assert_eq!(sniff("chart.svg", b"<?xml version=\"1.0\"?><svg>").mime_type, "image/svg+xml");
+        assert!(sniff("report.docx", b"PK\x03\x04...").mime_type.contains("wordprocessingml"));
+        assert_eq!(sniff("blob", &[0, 159, 146, 150]).mime_type, "application/octet-stream");
+    }
+
+    #[test]
+    fn file_id_is_the_name_prefix() {
+        assert_eq!(file_id("user-1/file-AbC123-photo.png"), "file-AbC123");
+        assert_eq!(file_id("file_00000000ab12-5f1c.png"), "file_00000000ab12");
+        assert_eq!(file_id("dalle-generations/file-Xy.webp"), "file-Xy");
+    }
+}
@@ -0,0 +1,173 @@
+use rusqlite::{Connection, Result};
+
+/// One step of the schema history: `up` takes a database at the previous version to this one.
+/// A migration's version is its position in `MIGRATIONS`, counting from 1, and is stored in
+/// `PRAGMA user_version` once it has run.
+///
+/// Append new steps at the end and never edit one that has shipped — databases that ran it
+/// won't run it again.
+struct Migration {
+    description: &'static str,
+    up: fn(&Connection) -> Result<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration { description: "Schema before versioned migrations", up: baseline },
+    Migration { description: "Projects", up: projects },
+];
+
+/// The schema version this build writes.
+pub const LATEST_VERSION: i64 = MIGRATIONS.len() as i64;
+
+/// Brings the database up to `LATEST_VERSION`, one transaction per step, and refuses a
+/// database written by a newer version of the app (its schema may not match what this
+/// version reads and writes).
+pub fn migrate(conn: &Connection) -> Result<(), String> {
+    migrate_to(conn, LATEST_VERSION)
+}
+
+fn migrate_to(conn: &Connection, target: i64) -> Result<(), String> {
+    let version = user_version(conn).map_err(|e| e.to_string())?;
+    if version > LATEST_VERSION {
+        return Err(format!(
+            "conversations.db was written by a newer version of the app (schema version {version}, \
+             this version supports up to {LATEST_VERSION}). Update the app to open it."
+        ));
+    }
+    for (i, migration) in MIGRATIONS.iter().enumerate().take(target as usize).skip(version as usize) {
+        let next = i as i64 + 1;
+        let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+        (migration.up)(&tx)
+            .and_then(|_| tx.pragma_update(None, "user_version", next))
+            .and_then(|_| tx.commit())
+            .map_err(|e| format!("Migration to schema version {next} ({}) failed: {e}", migration.description))?;
+    }
+    Ok(())
+}
+
+fn user_version(conn: &Connection) -> Result<i64> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+/// Version 1: `schema.sql` plus the columns added to it before schema versions were tracked.
+/// Databases from that time are at version 0 with any subset of those columns, so adding
+/// one that exists is not an error here (unlike in later migrations).
+fn baseline(conn: &Connection) -> Result<()> {
+    conn.execute_batch(include_str!("schema.sql"))?;
+    let columns = [
+        ("conversations", "gizmo_id TEXT"),
+        ("conversations", "updated_at INTEGER"),
+        ("conversations", "first_message_at INTEGER"),
+        ("conversations", "last_message_at INTEGER"),
+        ("conversations", "kind TEXT"),
+        ("conversations", "participants TEXT"),
+        ("conversations", "excluded INTEGER NOT NULL DEFAULT 0"),
+        ("messages", "model_slug TEXT"),
+        ("messages", "assets TEXT"),
+        ("messages", "finish_type TEXT"),
+        ("messages", "attachments TEXT"),
+        ("messages", "citations TEXT"),
+        ("messages", "kind TEXT"),
+        ("messages", "tool TEXT"),
+        ("messages", "content_type TEXT"),
+        ("messages", "author_name TEXT"),
+        ("media_refs", "generated INTEGER NOT NULL DEFAULT 0"),
+    ];
+    for (table, column) in columns {
+        let _ = conn.execute(&format!("ALTER TABLE {table} ADD COLUMN {column}"), []);
+    }
+    Ok(())
+}
+
+/// Version 2: user-made projects. Members are the conversations whose `project_name` is
+/// the project's name.
+fn projects(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE projects (
+            name TEXT PRIMARY KEY COLLATE NOCASE,
+            created_at INTEGER NOT NULL
+        );",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    /// Every table with its column names, sorted (ALTER TABLE appends, so order differs).
+    fn schema(conn: &Connection) -> BTreeMap<String, Vec<String>> {
+        let mut stmt = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'")
+            .unwrap();
+        let tables: Vec<String> = stmt.query_map([], |row| row.get(0)).unwrap().map(|r| r.unwrap()).collect();
+        tables
+            .into_iter()
+            .map(|table| {
+                let mut stmt = conn.prepare(&format!("SELECT name FROM pragma_table_info('{table}')")).unwrap();
+                let mut columns: Vec<String> =
+                    stmt.query_map([], |row| row.get(0)).unwrap().map(|r| r.unwrap()).collect();
+                columns.sort();
+                (table, columns)
+            })
+            .collect()
+    }
+
+    fn fresh() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn upgrades_from_every_prior_version() {
+        let expected = schema(&fresh());
+        for from in 0..LATEST_VERSION {
+            let conn = Connection::open_in_memory().unwrap();
+            migrate_to(&conn, from).unwrap();
+            assert_eq!(user_version(&conn).unwrap(), from);
+            migrate(&conn).unwrap();
+            assert_eq!(user_version(&conn).unwrap(), LATEST_VERSION, "from version {from}");
+            assert_eq!(schema(&conn), expected, "from version {from}");
+        }
+    }
+
+    #[test]
+    fn upgrades_a_database_from_before_versioning() {
+        // The first released schema: one table, user_version 0
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE conversations (
+                id TEXT PRIMARY KEY, title TEXT, created_at INTEGER,
+                message_count INTEGER NOT NULL DEFAULT 0, has_images INTEGER NOT NULL DEFAULT 0,
+                has_code INTEGER NOT NULL DEFAULT 0, token_estimate INTEGER NOT NULL DEFAULT 0,
+                full_text TEXT NOT NULL DEFAULT '', gizmo_id TEXT, cluster_id TEXT, project_name TEXT,
+                cluster_label TEXT, summary TEXT, instructions TEXT
+            );
+            CREATE TABLE messages (conversation_id TEXT NOT NULL, position INTEGER NOT NULL, id TEXT NOT NULL,
+                role TEXT NOT NULL, created_at INTEGER, text TEXT NOT NULL DEFAULT '', PRIMARY KEY (conversation_id, position));
+            INSERT INTO conversations (id, title, full_text) VALUES ('c1', 'Old', 'hello');",
+        )
+        .unwrap();
+
+        migrate(&conn).unwrap();
+        assert_eq!(schema(&conn), schema(&fresh()));
+        let (title, excluded): (String, i64) = conn
+            .query_row("SELECT title, excluded FROM conversations WHERE id = 'c1'", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((title.as_str(), excluded), ("Old", 0));
+    }
+
+    #[test]
+    fn is_idempotent_and_refuses_newer_databases() {
+        let conn = fresh();
+        migrate(&conn).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), LATEST_VERSION);
+
+        conn.pragma_update(None, "user_version", LATEST_VERSION + 1).unwrap();
+        let err = migrate(&conn).unwrap_err();
+        assert!(err.contains("newer version"), "{err}");
+    }
+}
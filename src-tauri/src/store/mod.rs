@@ -1 +1,5 @@
 pub mod db;
+pub mod encryption;
+pub mod exclusions;
+pub mod media_store;
+pub mod migrations;
@@ -1,7 +1,15 @@
+-- Schema version 1, run by `migrations::baseline`. Later changes are new steps in
+-- migrations.rs rather than edits here, so upgraded and new databases end up alike.
+
 CREATE TABLE IF NOT EXISTS conversations (
     id TEXT PRIMARY KEY,
+    kind TEXT,
+    participants TEXT,
     title TEXT,
     created_at INTEGER,
+    updated_at INTEGER,
+    first_message_at INTEGER,
+    last_message_at INTEGER,
     message_count INTEGER NOT NULL DEFAULT 0,
     has_images INTEGER NOT NULL DEFAULT 0,
     has_code INTEGER NOT NULL DEFAULT 0,
@@ -12,5 +20,146 @@ CREATE TABLE IF NOT EXISTS conversations (
     project_name TEXT,
     cluster_label TEXT,
     summary TEXT,
-    instructions TEXT
+    instructions TEXT,
+    -- 1 when an exclusion rule or the private flag keeps it out of exports and API requests
+    excluded INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS messages (
+    conversation_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    id TEXT NOT NULL,
+    role TEXT NOT NULL,
+    author_name TEXT,
+    kind TEXT,
+    tool TEXT,
+    content_type TEXT,
+    created_at INTEGER,
+    text TEXT NOT NULL DEFAULT '',
+    model_slug TEXT,
+    assets TEXT,
+    finish_type TEXT,
+    attachments TEXT,
+    citations TEXT,
+    PRIMARY KEY (conversation_id, position)
+);
+
+-- Tool invocations on each conversation's current branch (browsing, code interpreter, DALL-E, …)
+CREATE TABLE IF NOT EXISTS tool_calls (
+    conversation_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    message_id TEXT NOT NULL,
+    tool TEXT NOT NULL,
+    category TEXT NOT NULL,
+    created_at INTEGER,
+    PRIMARY KEY (conversation_id, position)
+);
+
+-- Custom instructions and memories, one row per conversation that carried them
+CREATE TABLE IF NOT EXISTS profile_entries (
+    conversation_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    text TEXT NOT NULL,
+    seen_at INTEGER,
+    PRIMARY KEY (conversation_id, kind, text)
+);
+
+-- Thumbs up/down ratings from message_feedback.json
+CREATE TABLE IF NOT EXISTS message_feedback (
+    id TEXT PRIMARY KEY,
+    conversation_id TEXT NOT NULL,
+    message_id TEXT NOT NULL,
+    positive INTEGER NOT NULL,
+    tags TEXT,
+    text TEXT,
+    created_at INTEGER
+);
+
+-- Answer comparisons from model_comparisons.json (raw entry kept in `data`)
+CREATE TABLE IF NOT EXISTS model_comparisons (
+    id TEXT PRIMARY KEY,
+    conversation_id TEXT,
+    created_at INTEGER,
+    rating TEXT,
+    data TEXT NOT NULL
+);
+
+-- Public share links from shared_conversations.json
+CREATE TABLE IF NOT EXISTS shared_links (
+    id TEXT PRIMARY KEY,
+    conversation_id TEXT NOT NULL,
+    title TEXT,
+    url TEXT NOT NULL,
+    anonymous INTEGER NOT NULL DEFAULT 0
+);
+
+-- One row per imported export (ZIP, folder or JSON file). `file_hash` is the SHA-256 of its
+-- conversation data, so importing the same export again reuses the row.
+CREATE TABLE IF NOT EXISTS import_sources (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    file_hash TEXT NOT NULL UNIQUE,
+    path TEXT NOT NULL,
+    account_email TEXT,
+    account_name TEXT,
+    imported_at INTEGER NOT NULL
+);
+
+-- Which exports each conversation appeared in, with the version (last activity) each carried
+CREATE TABLE IF NOT EXISTS conversation_sources (
+    conversation_id TEXT NOT NULL,
+    source_id INTEGER NOT NULL,
+    last_active_at INTEGER,
+    PRIMARY KEY (conversation_id, source_id)
+);
+
+-- Media copied from an export into the content-addressed store (store::media_store),
+-- so exports keep their media after the original ZIP or folder is gone
+CREATE TABLE IF NOT EXISTS stored_media (
+    source_id INTEGER NOT NULL,
+    path TEXT NOT NULL,
+    hash TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    PRIMARY KEY (source_id, path)
+);
+
+-- What each file in the media store is, by content hash (see media_store::sniff)
+CREATE TABLE IF NOT EXISTS media (
+    hash TEXT PRIMARY KEY,
+    size INTEGER NOT NULL,
+    mime_type TEXT NOT NULL,
+    width INTEGER,
+    height INTEGER
+);
+
+-- Conversations whose messages point at a stored media file (by the file id its name starts with).
+-- `generated` is set when a tool or assistant message points at it rather than the user.
+CREATE TABLE IF NOT EXISTS media_refs (
+    hash TEXT NOT NULL,
+    conversation_id TEXT NOT NULL,
+    generated INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (hash, conversation_id)
+);
+
+-- Token vault for reversible redaction (redact::RedactionMode::Tokenize): `token` stands for `value`
+CREATE TABLE IF NOT EXISTS redaction_tokens (
+    token TEXT PRIMARY KEY,
+    label TEXT NOT NULL,
+    value TEXT NOT NULL,
+    UNIQUE (label, value)
+);
+
+-- Rules that keep conversations out of exports and API requests (store::exclusions)
+CREATE TABLE IF NOT EXISTS exclusion_rules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    value TEXT,
+    starts_at INTEGER,
+    ends_at INTEGER,
+    created_at INTEGER NOT NULL
+);
+
+-- Conversations marked private in the UI. Kept apart from `conversations`, whose rows are
+-- replaced when a newer export is imported.
+CREATE TABLE IF NOT EXISTS private_conversations (
+    conversation_id TEXT PRIMARY KEY
 );
@@ -1,5 +1,5 @@
 use tauri_app_lib::pipeline::json_parser::{Author, Content, Message, MessageNode};
-use tauri_app_lib::pipeline::traversal::linearize_messages;
+use tauri_app_lib::pipeline::traversal::{linearize_messages, walk_branch, InclusionPolicy, MessageKind, ToolDetail};
 use std::collections::HashMap;
 
 /// Helper: build a minimal MessageNode with user message
@@ -15,8 +15,10 @@ fn user_node(id: &str, parent: Option<&str>, text: &str) -> MessageNode {
             content: Some(Content {
                 content_type: "text".to_string(),
                 parts: vec![serde_json::Value::String(text.to_string())],
+                ..Default::default()
             }),
             metadata: serde_json::Value::Null,
+            recipient: None,
         }),
     }
 }
@@ -34,8 +36,10 @@ fn assistant_node(id: &str, parent: Option<&str>, text: &str) -> MessageNode {
             content: Some(Content {
                 content_type: "text".to_string(),
                 parts: vec![serde_json::Value::String(text.to_string())],
+                ..Default::default()
             }),
             metadata: serde_json::Value::Null,
+            recipient: None,
         }),
     }
 }
@@ -145,8 +149,10 @@ fn test_system_messages_excluded() {
                 content: Some(Content {
                     content_type: "text".to_string(),
                     parts: vec![serde_json::Value::String("System init".to_string())],
+                    ..Default::default()
                 }),
                 metadata: serde_json::Value::Null,
+                recipient: None,
             }),
         },
     );
@@ -156,3 +162,123 @@ fn test_system_messages_excluded() {
     assert_eq!(messages.len(), 1, "System message must be excluded");
     assert_eq!(messages[0].author.role, "user");
 }
+
+#[test]
+fn test_walk_branch_keeps_hidden_messages_on_the_branch_only() {
+    let mut mapping = HashMap::new();
+    mapping.insert("root".to_string(), struct_node("root", None));
+    mapping.insert("a".to_string(), user_node("a", Some("root"), "Question"));
+    mapping.insert("b1".to_string(), assistant_node("b1", Some("a"), "Draft answer"));
+    mapping.insert("b2".to_string(), assistant_node("b2", Some("a"), "Regenerated answer"));
+    // An empty assistant message that `linearize_messages` drops
+    mapping.insert("c".to_string(), assistant_node("c", Some("b2"), ""));
+
+    let ids: Vec<String> = walk_branch(&mapping, "c").into_iter().map(|m| m.id).collect();
+    assert_eq!(ids, vec!["a", "b2", "c"], "Structural nodes skipped, other branch not visited");
+    assert_eq!(linearize_messages(&mapping, "c").len(), 2);
+}
+
+#[test]
+fn test_walk_branch_stops_at_missing_parent() {
+    let mut mapping = HashMap::new();
+    mapping.insert("b".to_string(), assistant_node("b", Some("gone"), "Orphan"));
+    mapping.insert("c".to_string(), user_node("c", Some("b"), "Follow-up"));
+
+    let ids: Vec<String> = walk_branch(&mapping, "c").into_iter().map(|m| m.id).collect();
+    assert_eq!(ids, vec!["b", "c"]);
+    assert!(walk_branch(&mapping, "missing").is_empty());
+}
+
+/// Helper: a message with the given role, recipient and content
+fn message(role: &str, recipient: Option<&str>, content: Content) -> Message {
+    Message {
+        id: "m".to_string(),
+        author: Author { role: role.to_string(), name: None },
+        create_time: None,
+        content: Some(content),
+        metadata: serde_json::Value::Null,
+        recipient: recipient.map(|r| r.to_string()),
+    }
+}
+
+fn text(s: &str) -> Content {
+    Content {
+        content_type: "text".to_string(),
+        parts: vec![serde_json::Value::String(s.to_string())],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_message_kinds() {
+    assert_eq!(MessageKind::of(&message("assistant", Some("all"), text("Hi"))), MessageKind::Message);
+    assert_eq!(MessageKind::of(&message("assistant", None, text("Hi"))), MessageKind::Message);
+    assert_eq!(MessageKind::of(&message("assistant", Some("python"), text("1+1"))), MessageKind::ToolCall);
+    assert_eq!(MessageKind::of(&message("tool", Some("all"), text("2"))), MessageKind::ToolOutput);
+    // A user message is never a tool call, whatever its recipient
+    assert_eq!(MessageKind::of(&message("user", Some("python"), text("Hi"))), MessageKind::Message);
+}
+
+#[test]
+fn test_inclusion_policy_tool_detail_levels() {
+    let code = Content {
+        content_type: "code".to_string(),
+        text: Some("print(2)".to_string()),
+        ..Default::default()
+    };
+    let call = message("assistant", Some("python"), code);
+    let output = message(
+        "tool",
+        None,
+        Content {
+            content_type: "execution_output".to_string(),
+            text: Some("2".to_string()),
+            ..Default::default()
+        },
+    );
+
+    let hidden = InclusionPolicy::default();
+    let calls = InclusionPolicy { tool_detail: ToolDetail::Calls, ..InclusionPolicy::default() };
+    let full = InclusionPolicy { tool_detail: ToolDetail::Full, ..InclusionPolicy::default() };
+    assert!(!hidden.includes_message(&call) && !hidden.includes_message(&output));
+    assert!(calls.includes_message(&call) && !calls.includes_message(&output));
+    // Tool payloads outside `parts` still count as content
+    assert!(full.includes_message(&call) && full.includes_message(&output));
+    assert_eq!(InclusionPolicy::everything().tool_detail, ToolDetail::Full);
+}
+
+#[test]
+fn test_inclusion_policy_roles_and_hidden_messages() {
+    let system = message("system", None, text("You are ChatGPT"));
+    assert!(!InclusionPolicy::default().includes_message(&system));
+    assert!(InclusionPolicy::everything().includes_message(&system));
+
+    let user_only = InclusionPolicy { roles: vec!["user".to_string()], ..InclusionPolicy::default() };
+    assert!(user_only.includes_message(&message("user", None, text("Hi"))));
+    assert!(!user_only.includes_message(&message("assistant", None, text("Hello"))));
+
+    // Visually hidden context, empty parts and missing content are never shown
+    let mut hidden = message("user", None, text("Custom instructions"));
+    hidden.metadata = serde_json::json!({ "is_visually_hidden_from_conversation": true });
+    assert!(!InclusionPolicy::everything().includes_message(&hidden));
+    assert!(!InclusionPolicy::everything().includes_message(&message("user", None, text(""))));
+    let mut no_content = message("user", None, text("x"));
+    no_content.content = None;
+    assert!(!InclusionPolicy::everything().includes_message(&no_content));
+    // Image parts are content
+    let image = Content {
+        content_type: "multimodal_text".to_string(),
+        parts: vec![serde_json::json!({ "asset_pointer": "file-service://file-1" })],
+        ..Default::default()
+    };
+    assert!(InclusionPolicy::default().includes_message(&message("user", None, image)));
+}
+
+#[test]
+fn test_inclusion_policy_deserializes_with_defaults() {
+    let policy: InclusionPolicy = serde_json::from_str(r#"{"toolDetail":"calls"}"#).unwrap();
+    assert_eq!(policy, InclusionPolicy { tool_detail: ToolDetail::Calls, ..InclusionPolicy::default() });
+    let policy: InclusionPolicy = serde_json::from_str(r#"{"roles":["user"]}"#).unwrap();
+    assert_eq!(policy.roles, vec!["user"]);
+    assert_eq!(policy.tool_detail, ToolDetail::Hidden);
+}
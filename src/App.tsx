@@ -1,5 +1,7 @@
 import { invoke } from '@tauri-apps/api/core';
 import { openUrl } from '@tauri-apps/plugin-opener';
+import { ask, message, open } from '@tauri-apps/plugin-dialog';
+import type { MissingSource } from './lib/bindings';
 import { useAppStore } from './store/appStore';
 import { DropZone } from './components/DropZone';
 import { ProgressView } from './components/ProgressView';
@@ -11,6 +13,36 @@ interface ExportResult {
   folder_path: string;
   mcp_configured: boolean;
   media_extracted: number;
+  missing_sources: MissingSource[];
+  bundle_path: string | null;
+}
+
+/**
+ * Asks the user to locate each export that moved since it was imported.
+ * Returns true when at least one was found again, so the export is worth re-running.
+ */
+async function relocateSources(missing: MissingSource[]): Promise<boolean> {
+  let relocated = false;
+  for (const source of missing) {
+    const locate = await ask(
+      `${source.reason}.\n\nIts media is missing from the archive. Locate the export?`,
+      { title: 'Export moved', kind: 'warning', okLabel: 'Locate…', cancelLabel: 'Skip' },
+    );
+    if (!locate) continue;
+    const path = await open({
+      multiple: false,
+      directory: false,
+      filters: [{ name: 'ChatGPT Export', extensions: ['zip', 'json'] }],
+    });
+    if (!path) continue;
+    try {
+      await invoke('relocate_import_source', { sourceId: source.id, path });
+      relocated = true;
+    } catch (err) {
+      await message(String(err), { title: 'Wrong export', kind: 'error' });
+    }
+  }
+  return relocated;
 }
 
 export default function App() {
@@ -31,7 +63,10 @@ export default function App() {
   const handleExport = async () => {
     setExporting();
     try {
-      const result = await invoke<ExportResult>('export_conversations');
+      let result = await invoke<ExportResult>('export_conversations');
+      if (result.missing_sources.length > 0 && (await relocateSources(result.missing_sources))) {
+        result = await invoke<ExportResult>('export_conversations');
+      }
       setExportSuccess(result.folder_path, result.files_written, result.mcp_configured, result.media_extracted);
     } catch (err) {
       useAppStore.setState({ phase: 'error', error: String(err) });
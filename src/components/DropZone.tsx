@@ -30,11 +30,13 @@ export function DropZone({ errorMessage, onReset }: DropZoneProps) {
           setIsDragging(false);
           // CRITICAL: Use Tauri's payload.paths — NOT HTML5 dataTransfer (gives no paths in Tauri)
           const paths: string[] = event.payload.paths ?? [];
-          const zipPath = paths.find((p) => p.toLowerCase().endsWith('.zip'));
-          if (zipPath) {
-            startIngest(zipPath);
+          // A ZIP or conversations.json is preferred; otherwise assume an extracted export folder
+          const exportPath =
+            paths.find((p) => /\.(zip|json)$/i.test(p)) ?? paths[0];
+          if (exportPath) {
+            startIngest(exportPath);
           }
-          // Non-ZIP dropped: drop zone stays interactive, no action needed
+          // Anything unreadable comes back as an ingest error; the zone stays interactive
         }
       })
       .then((unlisten) => {
@@ -51,7 +53,7 @@ export function DropZone({ errorMessage, onReset }: DropZoneProps) {
     const path = await open({
       multiple: false,
       directory: false,
-      filters: [{ name: 'ChatGPT Export', extensions: ['zip'] }],
+      filters: [{ name: 'ChatGPT Export', extensions: ['zip', 'json'] }],
     });
     if (path) {
       startIngest(path as string);
@@ -3,8 +3,8 @@ import openaiFolderIcon from '../assets/openai_folder_icon.png';
 
 interface SummaryCardProps {
   total: number;
-  earliestYear: number;
-  latestYear: number;
+  earliestYear: number | null;
+  latestYear: number | null;
   onExport: () => void;
 }
 
@@ -2,16 +2,224 @@
 
 export type IngestEvent =
   | { event: 'started'; data: never }
-  | { event: 'extractingZip'; data: never }
-  | { event: 'parsingConversations'; data: { processed: number } }
+  | { event: 'extractingZip'; data: { bytesRead: number; totalBytes: number; percent: number } }
+  | { event: 'parsingConversations'; data: { processed: number; total: number; percent: number } }
   | { event: 'buildingIndex'; data: never }
-  | { event: 'complete'; data: { total: number; earliestYear: number; latestYear: number } }
+  | {
+      event: 'complete';
+      data: {
+        total: number;
+        earliestYear: number | null;
+        latestYear: number | null;
+        elapsedMs: number;
+        bytesRead: number;
+        conversationsPerSec: number;
+        megabytesPerSec: number;
+        feedbackImported: number;
+        comparisonsImported: number;
+        groupChatsImported: number;
+        // Already stored from an earlier-imported export with a newer version
+        olderVersionsSkipped: number;
+        account: string | null;
+        sharesImported: number;
+        // conversations.json was missing or corrupt; data recovered from chat.html
+        recoveredFromHtml: boolean;
+        // Media copied into the app's store (parse_zip with storeMedia)
+        mediaStored: number;
+        // Kept out of exports and API requests by exclusion rules or private marks
+        excluded: number;
+        report: ImportReport;
+        format: ExportFingerprint;
+      };
+    }
   | { event: 'error'; data: { message: string } };
 
+// ImportReport — must stay in sync with src-tauri/src/pipeline/report.rs
+// conversationId is null when a whole shard could not be read
+export type ImportIssue = {
+  conversationId: string | null;
+  shard: string;
+  reason: string;
+};
+
+export type ImportReport = {
+  skipped: ImportIssue[];
+  recovered: ImportIssue[];
+};
+
+// ExportFingerprint — must stay in sync with src-tauri/src/pipeline/format.rs
+export type FormatVersion = 'legacy' | 'parts2025' | 'sharded2026';
+
+export type ExportFingerprint = {
+  version: FormatVersion;
+  files: string[];
+  shards: number;
+  contentTypes: Record<string, number>;
+  partShapes: Record<string, number>;
+  // Unrecognised files, content types, part shapes and metadata keys
+  warnings: string[];
+};
+
 export type ParseZipArgs = {
   path: string;
+  // IANA zone name or 'UTC' for year bucketing; omitted = OS time zone
+  timeZone?: string;
+  // Copy the export's media into the app's store so the ZIP can be deleted afterwards
+  storeMedia?: boolean;
+};
+
+// ExportOptions — must stay in sync with src-tauri/src/commands/export.rs ExportOptions
+export type ExportOptions = {
+  timeZone?: string;
+  // Year folders by creation date (default) or by last activity
+  bucketBy?: 'created' | 'lastActive';
+  // 'obsidian' writes a vault with Map-of-Content pages and wikilinks
+  profile?: 'standard' | 'obsidian';
+  // Folder grouping; cluster layouts put project members in their project's folder
+  // and write a README.md per folder
+  layout?: 'year' | 'cluster' | 'gizmo' | 'clusterYear';
+  include?: InclusionPolicy;
+  // Only this account's conversations (ImportSource email, or name when it has none)
+  account?: string;
+  redaction?: RedactionPolicy;
+  // Also write an age-encrypted ZIP of the archive (default: next to the folder)
+  bundle?: { passphrase: string; path?: string };
+};
+
+// ImportSource — returned by get_import_sources (snake_case, like ExportResult)
+export type ImportSource = {
+  id: number;
+  path: string;
+  file_hash: string;
+  account_email: string | null;
+  account_name: string | null;
+  imported_at: number;
+  conversations: number;
+  // Media files copied into the app's store; the original export is then optional
+  media_stored: number;
+};
+
+// MissingSource — ExportResult.missing_sources; locate it with relocate_import_source
+export type MissingSource = {
+  id: number;
+  path: string;
+  account: string | null;
+  reason: string;
+};
+
+// InclusionPolicy — must stay in sync with src-tauri/src/pipeline/traversal.rs InclusionPolicy
+export type InclusionPolicy = {
+  // Roles of ordinary messages to keep (default ['user', 'assistant'])
+  roles?: ('user' | 'assistant' | 'system')[];
+  // 'calls' adds code / queries / prompts sent to tools; 'full' also adds their output
+  toolDetail?: 'hidden' | 'calls' | 'full';
+};
+
+// RedactionPolicy — must stay in sync with src-tauri/src/redact.rs RedactionPolicy.
+// Also the `redaction` argument of start_clustering. The default masks nothing.
+export type RedactionPolicy = {
+  detectors?: ('secret' | 'email' | 'phone' | 'creditCard' | 'iban')[];
+  // Named regexes; the name appears in the mask, e.g. [REDACTED EMPLOYEE ID]
+  patterns?: { name: string; regex: string }[];
+  // Values never masked (case-insensitive)
+  allow?: string[];
+  // Words or phrases always masked
+  deny?: string[];
+  // 'tokenize' writes [EMAIL-3] tokens that unmask_text can turn back
+  mode?: 'mask' | 'tokenize';
 };
 
+// ExclusionRule — must stay in sync with src-tauri/src/store/exclusions.rs ExclusionRule.
+// Argument of add_exclusion_rule. Matching conversations are never exported or sent to the API.
+export type ExclusionRule =
+  | { kind: 'id'; id: string }
+  // Regex, case-insensitive
+  | { kind: 'title'; pattern: string }
+  | { kind: 'gizmo'; gizmoId: string }
+  // Unix seconds, inclusive; either end may be null
+  | { kind: 'dateRange'; from: number | null; to: number | null }
+  // Case-insensitive text in the transcript
+  | { kind: 'content'; text: string };
+
+// Returned by get_exclusion_rules
+export type ExclusionRuleRow = { id: number; rule: ExclusionRule; created_at: number };
+
+// RedactionReport — returned by preview_redaction (dry run, snake_case)
+export type RedactionReport = {
+  conversations_scanned: number;
+  conversations_affected: number;
+  counts: Record<string, number>;
+  samples: {
+    conversation_id: string;
+    title: string | null;
+    label: string;
+    preview: string;
+  }[];
+};
+
+// PackOptions — must stay in sync with src-tauri/src/commands/packs.rs PackOptions
+export type PackOptions = {
+  // Max estimated tokens per knowledge file (default 50000); longer conversations are split
+  fileTokenBudget?: number;
+  // Optional cap per pack; most recently active conversations are kept
+  packTokenBudget?: number;
+  timeZone?: string;
+  include?: InclusionPolicy;
+  redaction?: RedactionPolicy;
+};
+
+// PackResult — returned by generate_project_packs
+export type PackResult = {
+  packs: number;
+  files_written: number;
+  folder_path: string;
+};
+
+// Usage queries — must stay in sync with src-tauri/src/commands/usage.rs
+export type ToolCategory =
+  | 'browsing'
+  | 'codeInterpreter'
+  | 'imageGeneration'
+  | 'fileSearch'
+  | 'memory'
+  | 'canvas'
+  | 'plugin'
+  | 'other';
+
+export type UsageSummary = {
+  models: { model: string; conversations: number; messages: number }[];
+  tools: { category: ToolCategory; conversations: number; calls: number }[];
+};
+
+// Argument of find_conversations_by_usage; fields combine with AND
+export type UsageFilter = {
+  model?: string;
+  tool?: ToolCategory;
+};
+
+export type ConversationRef = {
+  id: string;
+  title: string | null;
+  created_at: number | null;
+  // Set with set_conversation_private
+  private: boolean;
+};
+
+// ProjectRow — returned by get_projects (snake_case)
+export type ProjectRow = {
+  name: string;
+  created_at: number;
+  conversations: number;
+};
+
+// ConversationSelection — argument of assign_to_project / unassign_from_project,
+// must stay in sync with src-tauri/src/commands/projects.rs ConversationSelection
+export type ConversationSelection =
+  | { kind: 'ids'; ids: string[] }
+  // Title or transcript contains every word, ignoring case
+  | { kind: 'search'; query: string }
+  | { kind: 'cluster'; label: string };
+
 // ClusterEvent — must stay in sync with src-tauri/src/commands/cluster.rs ClusterEvent
 // These events are emitted via Channel<ClusterEvent> during the clustering pipeline
 export type ClusterEvent =
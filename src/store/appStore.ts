@@ -16,8 +16,8 @@ export type ExportMode = 'with-ai' | 'without-ai' | null;
 
 export interface Summary {
   total: number;
-  earliestYear: number;
-  latestYear: number;
+  earliestYear: number | null;
+  latestYear: number | null;
 }
 
 interface AppState {